@@ -0,0 +1,805 @@
+// src/db/cache.rs
+use super::note_ids;
+use super::stats;
+use super::workspace;
+use crate::notes::encryption;
+use crate::notes::formats;
+use crate::notes::helpers;
+use crate::notes::scan::CancelToken;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sqlx::{Pool, Sqlite};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+/// Event emitted with a `ScanProgress` while a sync runs
+pub const SCAN_PROGRESS_EVENT: &str = "vault-scan-progress";
+
+/// How many notes are checked between progress reports
+const PROGRESS_BATCH: usize = 200;
+
+/// A note as stored in the metadata cache
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedNote {
+    /// Stable ID that follows the note when it moves. Only missing from
+    /// notes reported by a sync before they were written to the cache.
+    pub id: Option<String>,
+    pub title: String,
+    pub aliases: Vec<String>,
+    pub absolute_path: String,
+    pub relative_path: String,
+    pub size: i64,
+    /// Modification and creation times in milliseconds since the epoch
+    pub mtime: i64,
+    pub ctime: i64,
+    pub word_count: i64,
+    pub tags: Vec<String>,
+}
+
+/// Columns `note_from_row` expects, for queries on `notes n`
+pub const NOTE_COLUMNS: &str = "n.title, n.aliases, n.absolute_path, n.relative_path,
+    n.size, n.mtime, n.ctime, n.word_count,
+    (SELECT json_group_array(t.tag) FROM note_tags t
+     WHERE t.vault = n.vault AND t.relative_path = n.relative_path) AS tags,
+    (SELECT i.note_id FROM note_ids i
+     WHERE i.vault = n.vault AND i.relative_path = n.relative_path) AS note_id";
+
+/// A row selected with `NOTE_COLUMNS`
+pub type NoteRow = (
+    String,
+    String,
+    String,
+    String,
+    i64,
+    i64,
+    i64,
+    i64,
+    String,
+    Option<String>,
+);
+
+pub fn note_from_row(row: NoteRow) -> CachedNote {
+    let (title, aliases, absolute_path, relative_path, size, mtime, ctime, word_count, tags, id) =
+        row;
+    CachedNote {
+        id,
+        title,
+        aliases: serde_json::from_str(&aliases).unwrap_or_default(),
+        absolute_path,
+        relative_path,
+        size,
+        mtime,
+        ctime,
+        word_count,
+        tags: serde_json::from_str(&tags).unwrap_or_default(),
+    }
+}
+
+/// What reconciling the cache with the files on disk changed
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct CacheSyncSummary {
+    pub vault_directory: String,
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+    /// Notes that moved with their content unchanged, counted neither as
+    /// added nor removed
+    pub renamed: usize,
+    pub unchanged: usize,
+}
+
+impl CacheSyncSummary {
+    pub fn changed(&self) -> bool {
+        self.added + self.updated + self.removed + self.renamed > 0
+    }
+}
+
+/// Progress of a running sync, streamed to the webview
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanProgress {
+    pub vault_directory: String,
+    pub scanned: usize,
+    pub total: usize,
+    /// Notes added or changed since the previous report
+    pub notes: Vec<CachedNote>,
+}
+
+/// Receives progress reports from a running sync
+pub type ProgressCallback = Arc<dyn Fn(ScanProgress) + Send + Sync>;
+
+/// Everything the cache stores about one note
+struct NoteRecord {
+    relative_path: String,
+    absolute_path: String,
+    title: String,
+    aliases: Vec<String>,
+    frontmatter: Option<JsonValue>,
+    /// ID set in the frontmatter
+    note_id: Option<String>,
+    mtime: i64,
+    ctime: i64,
+    size: i64,
+    hash: String,
+    word_count: i64,
+    char_count: i64,
+    heading_count: i64,
+    links: Vec<(String, Option<String>, bool, usize)>,
+    tags: Vec<String>,
+}
+
+/// A cached row's file state, used to spot changed files
+struct CachedState {
+    mtime: i64,
+    size: i64,
+    hash: String,
+}
+
+/// Returns a file's modification time in milliseconds and its size
+fn file_state(path: &Path) -> Option<(i64, i64)> {
+    let metadata = fs::metadata(path).ok()?;
+    let mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_millis() as i64)
+        .unwrap_or(0);
+    Some((mtime, metadata.len() as i64))
+}
+
+/// What a sync found for one file
+enum Scanned {
+    Unchanged,
+    /// Same content with a new mtime or size
+    Touched(i64, i64),
+    Added(NoteRecord),
+    Updated(NoteRecord),
+    /// Gone or unreadable since it was listed
+    Missing,
+}
+
+/// Checks one file against its cached state. The file is only read when its
+/// mtime or size changed, and only parsed when its content did.
+fn scan_note(absolute_path: &str, relative_path: &str, previous: Option<&CachedState>) -> Scanned {
+    let Some(state) = file_state(Path::new(absolute_path)) else {
+        return Scanned::Missing;
+    };
+
+    if let Some(previous) = previous {
+        if previous.mtime == state.0 && previous.size == state.1 {
+            return Scanned::Unchanged;
+        }
+    }
+
+    let Ok(bytes) = fs::read(absolute_path) else {
+        return Scanned::Missing;
+    };
+    let hash = format!("{:x}", md5::compute(&bytes));
+    match previous {
+        // Touched but not edited: only the file state is refreshed
+        Some(previous) if previous.hash == hash => Scanned::Touched(state.0, state.1),
+        Some(_) => Scanned::Updated(read_record(
+            absolute_path,
+            relative_path,
+            bytes,
+            state,
+            hash,
+        )),
+        None => Scanned::Added(read_record(
+            absolute_path,
+            relative_path,
+            bytes,
+            state,
+            hash,
+        )),
+    }
+}
+
+fn cached_note(record: &NoteRecord) -> CachedNote {
+    CachedNote {
+        id: record.note_id.clone(),
+        title: record.title.clone(),
+        aliases: record.aliases.clone(),
+        absolute_path: record.absolute_path.clone(),
+        relative_path: record.relative_path.clone(),
+        size: record.size,
+        mtime: record.mtime,
+        ctime: record.ctime,
+        word_count: record.word_count,
+        tags: record.tags.clone(),
+    }
+}
+
+/// Parses a note for the cache from the bytes that were hashed, so the file
+/// is read once
+fn read_record(
+    absolute_path: &str,
+    relative_path: &str,
+    bytes: Vec<u8>,
+    state: (i64, i64),
+    hash: String,
+) -> NoteRecord {
+    // Files that aren't UTF-8 are kept with their names only
+    let content = String::from_utf8(bytes).unwrap_or_default();
+    let note = helpers::parse_note(relative_path, &content);
+    // An encrypted body stays out of the cache: no links, inline tags or
+    // counts that would give away what it says
+    let content = if encryption::is_encrypted(&note.body) {
+        String::new()
+    } else {
+        note.body
+    };
+    let parsed = formats::format_for_path(relative_path).parse(&content);
+
+    let word_count = stats::count_words(&content);
+
+    // Not every file system records creation times
+    let ctime = fs::metadata(absolute_path)
+        .and_then(|metadata| metadata.created())
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_millis() as i64)
+        .unwrap_or(state.0);
+
+    let links = parsed
+        .links
+        .iter()
+        .map(|link| {
+            (
+                link.target.clone(),
+                link.alias.clone(),
+                link.embed,
+                link.line,
+            )
+        })
+        .collect();
+
+    // Inline #tags and frontmatter `tags`, without duplicates
+    let mut tags = parsed.tags;
+    match note.frontmatter.as_ref().and_then(|fm| fm.get("tags")) {
+        Some(JsonValue::Array(items)) => tags.extend(
+            items
+                .iter()
+                .filter_map(|t| t.as_str())
+                .map(|t| t.to_string()),
+        ),
+        Some(JsonValue::String(list)) => tags.extend(
+            list.split([',', ' '])
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty()),
+        ),
+        _ => {}
+    }
+    let mut seen = HashSet::new();
+    tags.retain(|tag| seen.insert(tag.trim_start_matches('#').to_lowercase()));
+
+    NoteRecord {
+        relative_path: relative_path.to_string(),
+        absolute_path: absolute_path.to_string(),
+        title: note.title,
+        aliases: note.aliases,
+        note_id: note_ids::frontmatter_id(note.frontmatter.as_ref()),
+        frontmatter: note.frontmatter,
+        mtime: state.0,
+        ctime,
+        size: state.1,
+        hash,
+        word_count,
+        char_count: content.chars().count() as i64,
+        heading_count: parsed.headings as i64,
+        links,
+        tags,
+    }
+}
+
+/// Loads the cached notes of a vault, sorted by path
+pub async fn load_notes(
+    pool: &Pool<Sqlite>,
+    vault_directory: &str,
+) -> Result<Vec<CachedNote>, sqlx::Error> {
+    let rows: Vec<NoteRow> = sqlx::query_as(&format!(
+        "SELECT {} FROM notes n WHERE n.vault = ? ORDER BY n.relative_path",
+        NOTE_COLUMNS
+    ))
+    .bind(vault_directory)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(note_from_row).collect())
+}
+
+/// Returns true if the vault has been scanned into the cache before, even
+/// if it had no notes
+pub async fn has_vault(pool: &Pool<Sqlite>, vault_directory: &str) -> Result<bool, sqlx::Error> {
+    let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM vault_scans WHERE vault = ?")
+        .bind(vault_directory)
+        .fetch_one(pool)
+        .await?;
+    Ok(count.0 > 0)
+}
+
+async fn write_record(
+    transaction: &mut sqlx::Transaction<'_, Sqlite>,
+    vault_directory: &str,
+    record: &NoteRecord,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT OR REPLACE INTO notes
+         (vault, relative_path, absolute_path, title, aliases, frontmatter,
+          mtime, ctime, size, hash, word_count, char_count, heading_count)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(vault_directory)
+    .bind(&record.relative_path)
+    .bind(&record.absolute_path)
+    .bind(&record.title)
+    .bind(serde_json::to_string(&record.aliases).unwrap_or_else(|_| "[]".to_string()))
+    .bind(record.frontmatter.as_ref().map(|fm| fm.to_string()))
+    .bind(record.mtime)
+    .bind(record.ctime)
+    .bind(record.size)
+    .bind(&record.hash)
+    .bind(record.word_count)
+    .bind(record.char_count)
+    .bind(record.heading_count)
+    .execute(&mut **transaction)
+    .await?;
+    note_ids::assign(
+        transaction,
+        vault_directory,
+        &record.relative_path,
+        record.note_id.as_deref(),
+    )
+    .await?;
+
+    delete_details(transaction, vault_directory, &record.relative_path).await?;
+    for (target, alias, embed, line) in record.links.iter() {
+        sqlx::query(
+            "INSERT INTO note_links (vault, source_path, target, alias, embed, line)
+             VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(vault_directory)
+        .bind(&record.relative_path)
+        .bind(target)
+        .bind(alias)
+        .bind(*embed)
+        .bind(*line as i64)
+        .execute(&mut **transaction)
+        .await?;
+    }
+    for tag in record.tags.iter() {
+        sqlx::query("INSERT INTO note_tags (vault, relative_path, tag) VALUES (?, ?, ?)")
+            .bind(vault_directory)
+            .bind(&record.relative_path)
+            .bind(tag)
+            .execute(&mut **transaction)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Removes a note's links and tags
+async fn delete_details(
+    transaction: &mut sqlx::Transaction<'_, Sqlite>,
+    vault_directory: &str,
+    relative_path: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM note_links WHERE vault = ? AND source_path = ?")
+        .bind(vault_directory)
+        .bind(relative_path)
+        .execute(&mut **transaction)
+        .await?;
+    sqlx::query("DELETE FROM note_tags WHERE vault = ? AND relative_path = ?")
+        .bind(vault_directory)
+        .bind(relative_path)
+        .execute(&mut **transaction)
+        .await?;
+    Ok(())
+}
+
+/// Brings the cache in line with the vault on disk. Files whose mtime and
+/// size match the cache are not read; changed files are only re-parsed when
+/// their content hash differs. A note that disappeared while a new one with
+/// the same content showed up is taken to have moved: its ID and stored paths
+/// follow it. New IDs are then written into the notes. Files are walked and parsed on a thread pool,
+/// and `progress` gets the notes added or changed so far in batches.
+/// Cancelling the token stops the sync without touching the cache.
+pub async fn sync_vault(
+    pool: &Pool<Sqlite>,
+    vault_directory: &str,
+    cancel: &CancelToken,
+    progress: Option<ProgressCallback>,
+) -> Result<CacheSyncSummary, String> {
+    let rows: Vec<(String, i64, i64, String)> =
+        sqlx::query_as("SELECT relative_path, mtime, size, hash FROM notes WHERE vault = ?")
+            .bind(vault_directory)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| format!("Failed to read note cache: {}", e))?;
+    let cached: HashMap<String, CachedState> = rows
+        .into_iter()
+        .map(|(path, mtime, size, hash)| (path, CachedState { mtime, size, hash }))
+        .collect();
+
+    // Scanning and parsing touch the disk, so keep them off the async runtime
+    let vault = vault_directory.to_string();
+    let token = cancel.clone();
+    let scanned = tauri::async_runtime::spawn_blocking(move || {
+        let notes = helpers::get_all_notes_until(&vault, &token)?;
+        let total = notes.len();
+        let mut records: Vec<NoteRecord> = Vec::new();
+        let mut touched: Vec<(String, i64, i64)> = Vec::new();
+        let mut added: Vec<(String, String)> = Vec::new();
+        let mut present: HashSet<String> = HashSet::new();
+        let mut summary = CacheSyncSummary {
+            vault_directory: vault.clone(),
+            ..Default::default()
+        };
+
+        let mut done = 0;
+        for chunk in notes.chunks(PROGRESS_BATCH) {
+            if token.is_cancelled() {
+                return None;
+            }
+
+            let results: Vec<Scanned> = chunk
+                .par_iter()
+                .map(|(absolute_path, relative_path)| {
+                    scan_note(absolute_path, relative_path, cached.get(relative_path))
+                })
+                .collect();
+
+            let mut batch = Vec::new();
+            for ((_, relative_path), result) in chunk.iter().zip(results) {
+                present.insert(relative_path.clone());
+                match result {
+                    Scanned::Unchanged => summary.unchanged += 1,
+                    Scanned::Touched(mtime, size) => {
+                        summary.unchanged += 1;
+                        touched.push((relative_path.clone(), mtime, size));
+                    }
+                    Scanned::Added(record) => {
+                        summary.added += 1;
+                        added.push((relative_path.clone(), record.hash.clone()));
+                        batch.push(cached_note(&record));
+                        records.push(record);
+                    }
+                    Scanned::Updated(record) => {
+                        summary.updated += 1;
+                        batch.push(cached_note(&record));
+                        records.push(record);
+                    }
+                    Scanned::Missing => {}
+                }
+            }
+
+            done += chunk.len();
+            if let Some(progress) = &progress {
+                progress(ScanProgress {
+                    vault_directory: vault.clone(),
+                    scanned: done,
+                    total,
+                    notes: batch,
+                });
+            }
+        }
+
+        let removed: Vec<(String, String)> = cached
+            .into_iter()
+            .filter(|(path, _)| !present.contains(path))
+            .map(|(path, state)| (path, state.hash))
+            .collect();
+        let renames = note_ids::detect_renames(&removed, &added);
+        let removed: Vec<String> = removed.into_iter().map(|(path, _)| path).collect();
+        Some((records, touched, removed, renames, summary))
+    })
+    .await
+    .map_err(|e| format!("Note scan failed: {}", e))?;
+    let Some((records, touched, removed, renames, mut summary)) = scanned else {
+        return Err(format!("Scan of {} was cancelled", vault_directory));
+    };
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to update note cache: {}", e))?;
+    let result: Result<(), sqlx::Error> = async {
+        for (from, to) in renames.iter() {
+            workspace::move_note_paths(&mut transaction, vault_directory, from, to).await?;
+        }
+        for (relative_path, mtime, size) in touched.iter() {
+            sqlx::query(
+                "UPDATE notes SET mtime = ?, size = ? WHERE vault = ? AND relative_path = ?",
+            )
+            .bind(mtime)
+            .bind(size)
+            .bind(vault_directory)
+            .bind(relative_path)
+            .execute(&mut *transaction)
+            .await?;
+        }
+        for relative_path in removed.iter() {
+            sqlx::query("DELETE FROM notes WHERE vault = ? AND relative_path = ?")
+                .bind(vault_directory)
+                .bind(relative_path)
+                .execute(&mut *transaction)
+                .await?;
+            delete_details(&mut transaction, vault_directory, relative_path).await?;
+            note_ids::remove(&mut transaction, vault_directory, relative_path).await?;
+        }
+        // After the removals, so a deleted note's ID is free for a new one
+        for record in records.iter() {
+            write_record(&mut transaction, vault_directory, record).await?;
+        }
+        sqlx::query("INSERT OR REPLACE INTO vault_scans (vault, scanned_at) VALUES (?, ?)")
+            .bind(vault_directory)
+            .bind(workspace::now_millis())
+            .execute(&mut *transaction)
+            .await?;
+        Ok(())
+    }
+    .await;
+    result.map_err(|e| format!("Failed to update note cache: {}", e))?;
+    transaction
+        .commit()
+        .await
+        .map_err(|e| format!("Failed to update note cache: {}", e))?;
+
+    write_generated_ids(pool, vault_directory).await?;
+
+    summary.renamed = renames.len();
+    summary.added -= renames.len();
+    summary.removed = removed.len() - renames.len();
+    println!(
+        "Note cache for {}: {} added, {} updated, {} removed, {} renamed, {} unchanged",
+        vault_directory,
+        summary.added,
+        summary.updated,
+        summary.removed,
+        summary.renamed,
+        summary.unchanged
+    );
+    Ok(summary)
+}
+
+/// Writes the IDs the cache generated into the notes themselves, so an ID
+/// stays with its note when the note is moved or edited outside the app.
+/// Plain text and encrypted notes keep their IDs in the cache only.
+async fn write_generated_ids(pool: &Pool<Sqlite>, vault_directory: &str) -> Result<(), String> {
+    let rows: Vec<(String, String)> = sqlx::query_as(
+        "SELECT notes.relative_path, note_ids.note_id FROM notes
+         JOIN note_ids ON note_ids.vault = notes.vault
+          AND note_ids.relative_path = notes.relative_path
+         WHERE notes.vault = ? AND json_extract(notes.frontmatter, '$.id') IS NULL",
+    )
+    .bind(vault_directory)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to read note IDs: {}", e))?;
+
+    for (relative_path, note_id) in rows {
+        let path = Path::new(vault_directory).join(&relative_path);
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        if encryption::is_encrypted(&helpers::parse_note(&relative_path, &content).body) {
+            continue;
+        }
+        let Some(updated) = note_ids::with_frontmatter_id(&relative_path, &content, &note_id)
+        else {
+            continue;
+        };
+        if let Err(e) = fs::write(&path, updated) {
+            println!("Failed to write the ID of {}: {}", relative_path, e);
+            continue;
+        }
+        refresh_note(pool, vault_directory, &relative_path).await?;
+    }
+    Ok(())
+}
+
+/// Re-reads a single note into the cache, or drops it if the file is gone.
+/// Called after the app itself writes a note.
+pub async fn refresh_note(
+    pool: &Pool<Sqlite>,
+    vault_directory: &str,
+    relative_path: &str,
+) -> Result<(), String> {
+    let absolute_path = Path::new(vault_directory).join(relative_path);
+    let record = file_state(&absolute_path).and_then(|state| {
+        let bytes = fs::read(&absolute_path).ok()?;
+        let hash = format!("{:x}", md5::compute(&bytes));
+        Some(read_record(
+            &absolute_path.to_string_lossy(),
+            relative_path,
+            bytes,
+            state,
+            hash,
+        ))
+    });
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to update note cache: {}", e))?;
+    let result = match record {
+        Some(record) => write_record(&mut transaction, vault_directory, &record).await,
+        None => {
+            async {
+                sqlx::query("DELETE FROM notes WHERE vault = ? AND relative_path = ?")
+                    .bind(vault_directory)
+                    .bind(relative_path)
+                    .execute(&mut *transaction)
+                    .await?;
+                delete_details(&mut transaction, vault_directory, relative_path).await?;
+                note_ids::remove(&mut transaction, vault_directory, relative_path).await
+            }
+            .await
+        }
+    };
+    result.map_err(|e| format!("Failed to update note cache: {}", e))?;
+    transaction
+        .commit()
+        .await
+        .map_err(|e| format!("Failed to update note cache: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_pool;
+    use tauri::async_runtime::block_on;
+
+    fn write(vault: &Path, relative_path: &str, content: &str) {
+        let path = vault.join(relative_path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    async fn sync(pool: &Pool<Sqlite>, vault: &Path) -> CacheSyncSummary {
+        sync_vault(
+            pool,
+            &vault.to_string_lossy(),
+            &CancelToken::default(),
+            None,
+        )
+        .await
+        .unwrap()
+    }
+
+    #[test]
+    fn empty_vaults_count_as_scanned() {
+        block_on(async {
+            let pool = test_pool().await;
+            let vault = tempfile::tempdir().unwrap();
+            let vault_directory = vault.path().to_string_lossy();
+
+            assert!(!has_vault(&pool, &vault_directory).await.unwrap());
+            sync(&pool, vault.path()).await;
+            assert!(has_vault(&pool, &vault_directory).await.unwrap());
+            assert!(load_notes(&pool, &vault_directory)
+                .await
+                .unwrap()
+                .is_empty());
+        });
+    }
+
+    #[test]
+    fn syncs_added_updated_removed_and_renamed_notes() {
+        block_on(async {
+            let pool = test_pool().await;
+            let vault = tempfile::tempdir().unwrap();
+            let vault_directory = vault.path().to_string_lossy();
+            write(
+                vault.path(),
+                "a.md",
+                "---\ntitle: Alpha\ntags: [x]\n---\none two",
+            );
+            write(vault.path(), "b.md", "bee");
+            write(vault.path(), "c.md", "sea");
+
+            let summary = sync(&pool, vault.path()).await;
+            assert_eq!((summary.added, summary.unchanged), (3, 0));
+            let notes = load_notes(&pool, &vault_directory).await.unwrap();
+            assert_eq!(notes[0].title, "Alpha");
+            assert_eq!(notes[0].tags, vec!["x"]);
+            let id = notes[2].id.clone().unwrap();
+
+            write(vault.path(), "b.md", "bee, changed");
+            fs::remove_file(vault.path().join("a.md")).unwrap();
+            fs::create_dir(vault.path().join("moved")).unwrap();
+            fs::rename(vault.path().join("c.md"), vault.path().join("moved/c.md")).unwrap();
+            let summary = sync(&pool, vault.path()).await;
+            assert_eq!(
+                (
+                    summary.added,
+                    summary.updated,
+                    summary.removed,
+                    summary.renamed
+                ),
+                (0, 1, 1, 1)
+            );
+
+            let notes = load_notes(&pool, &vault_directory).await.unwrap();
+            let paths: Vec<&str> = notes.iter().map(|n| n.relative_path.as_str()).collect();
+            assert_eq!(paths, vec!["b.md", "moved/c.md"]);
+            assert_eq!(notes[1].id.as_deref(), Some(id.as_str()));
+            assert!(!sync(&pool, vault.path()).await.changed());
+        });
+    }
+
+    #[test]
+    fn generated_ids_are_written_into_notes() {
+        block_on(async {
+            let pool = test_pool().await;
+            let vault = tempfile::tempdir().unwrap();
+            let vault_directory = vault.path().to_string_lossy();
+            write(vault.path(), "a.md", "---\ntitle: A\n---\ntext");
+            write(vault.path(), "b.txt", "plain");
+            sync(&pool, vault.path()).await;
+
+            let notes = load_notes(&pool, &vault_directory).await.unwrap();
+            let id = notes[0].id.clone().unwrap();
+            assert_eq!(
+                fs::read_to_string(vault.path().join("a.md")).unwrap(),
+                format!("---\nid: {}\ntitle: A\n---\ntext", id)
+            );
+            assert_eq!(
+                fs::read_to_string(vault.path().join("b.txt")).unwrap(),
+                "plain"
+            );
+            assert!(!sync(&pool, vault.path()).await.changed());
+
+            // Moved and edited in one go, which the content hash can't follow
+            let content = fs::read_to_string(vault.path().join("a.md")).unwrap();
+            fs::remove_file(vault.path().join("a.md")).unwrap();
+            write(vault.path(), "moved.md", &format!("{}, edited", content));
+            sync(&pool, vault.path()).await;
+            assert_eq!(
+                note_ids::id_for_path(&pool, &vault_directory, "moved.md")
+                    .await
+                    .unwrap(),
+                Some(id)
+            );
+        });
+    }
+
+    #[test]
+    fn renamed_notes_stay_open_in_the_workspace() {
+        block_on(async {
+            let pool = test_pool().await;
+            let vault = tempfile::tempdir().unwrap();
+            let vault_directory = vault.path().to_string_lossy();
+            write(vault.path(), "draft.md", "some text");
+            sync(&pool, vault.path()).await;
+
+            let open = workspace::Workspace {
+                open_notes: vec![workspace::OpenNote {
+                    relative_path: "draft.md".to_string(),
+                    selection_start: 3,
+                    selection_end: 7,
+                    ..Default::default()
+                }],
+                active_note: Some("draft.md".to_string()),
+                ..Default::default()
+            };
+            workspace::save_workspace(&pool, &vault_directory, &open)
+                .await
+                .unwrap();
+
+            fs::rename(vault.path().join("draft.md"), vault.path().join("final.md")).unwrap();
+            assert_eq!(sync(&pool, vault.path()).await.renamed, 1);
+
+            let restored = workspace::restore_workspace(&pool, &vault_directory)
+                .await
+                .unwrap();
+            assert_eq!(restored.open_notes[0].relative_path, "final.md");
+            assert_eq!(restored.open_notes[0].selection_end, 7);
+            assert_eq!(restored.active_note.as_deref(), Some("final.md"));
+        });
+    }
+}
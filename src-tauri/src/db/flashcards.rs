@@ -0,0 +1,359 @@
+// src/db/flashcards.rs
+use super::workspace::now_millis;
+use crate::notes::flashcards::Flashcard;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+use std::collections::HashMap;
+
+/// Ease a card starts with
+pub const STARTING_EASE: f64 = 2.5;
+
+/// Lowest ease a card can drop to
+pub const MINIMUM_EASE: f64 = 1.3;
+
+/// New cards handed out per request unless the webview asks for another number
+pub const DEFAULT_NEW_CARDS: usize = 20;
+
+/// Cards handed out per request unless the webview asks for another number
+pub const DEFAULT_DUE_CARDS: usize = 100;
+
+/// How soon a forgotten card comes back, in milliseconds
+const RELEARN_DELAY: i64 = 10 * 60 * 1000;
+
+const DAY: i64 = 24 * 60 * 60 * 1000;
+
+/// How well a card was remembered
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewGrade {
+    Again,
+    Hard,
+    Good,
+    Easy,
+}
+
+impl ReviewGrade {
+    /// The SM-2 response quality, from 0 to 5
+    fn quality(self) -> f64 {
+        match self {
+            ReviewGrade::Again => 1.0,
+            ReviewGrade::Hard => 3.0,
+            ReviewGrade::Good => 4.0,
+            ReviewGrade::Easy => 5.0,
+        }
+    }
+}
+
+/// Where a card is in its review schedule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewState {
+    pub card_id: String,
+    /// Note the card was last reviewed from
+    pub relative_path: String,
+    pub ease: f64,
+    pub interval_days: i64,
+    /// Successful reviews in a row
+    pub repetitions: i64,
+    /// Times the card was forgotten after being learned
+    pub lapses: i64,
+    /// Times in milliseconds since the epoch
+    pub due_at: i64,
+    pub reviewed_at: Option<i64>,
+}
+
+impl ReviewState {
+    fn new(card_id: &str, relative_path: &str) -> Self {
+        ReviewState {
+            card_id: card_id.to_string(),
+            relative_path: relative_path.to_string(),
+            ease: STARTING_EASE,
+            interval_days: 0,
+            repetitions: 0,
+            lapses: 0,
+            due_at: 0,
+            reviewed_at: None,
+        }
+    }
+}
+
+/// Works out a card's next review with SM-2: each successful review
+/// multiplies the interval by the card's ease, and the grade nudges the ease
+/// up or down. A forgotten card starts over and comes back shortly.
+pub fn schedule(state: &ReviewState, grade: ReviewGrade, now: i64) -> ReviewState {
+    let quality = grade.quality();
+    let mut next = state.clone();
+    next.reviewed_at = Some(now);
+
+    if grade == ReviewGrade::Again {
+        if state.repetitions > 0 {
+            next.lapses += 1;
+        }
+        next.repetitions = 0;
+        next.interval_days = 0;
+        next.due_at = now + RELEARN_DELAY;
+    } else {
+        next.repetitions += 1;
+        next.interval_days = match next.repetitions {
+            1 => 1,
+            2 => 6,
+            _ => ((state.interval_days as f64) * state.ease).round() as i64,
+        };
+        next.due_at = now + next.interval_days * DAY;
+    }
+
+    let ease = state.ease + 0.1 - (5.0 - quality) * (0.08 + (5.0 - quality) * 0.02);
+    next.ease = ease.max(MINIMUM_EASE);
+    next
+}
+
+/// A card up for review, with no state if it was never reviewed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DueCard {
+    pub card: Flashcard,
+    pub state: Option<ReviewState>,
+}
+
+/// Cards to review now: due cards first, most overdue first, then new ones
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DueCards {
+    pub cards: Vec<DueCard>,
+    /// Reviewed cards that are due, including ones left out by the limit
+    pub due: usize,
+    /// Cards never reviewed, including ones left out by the limit
+    pub new: usize,
+}
+
+type StateRow = (String, String, f64, i64, i64, i64, i64, Option<i64>);
+
+fn state_from_row(row: StateRow) -> ReviewState {
+    let (card_id, relative_path, ease, interval_days, repetitions, lapses, due_at, reviewed_at) =
+        row;
+    ReviewState {
+        card_id,
+        relative_path,
+        ease,
+        interval_days,
+        repetitions,
+        lapses,
+        due_at,
+        reviewed_at,
+    }
+}
+
+const STATE_COLUMNS: &str =
+    "card_id, relative_path, ease, interval_days, repetitions, lapses, due_at, reviewed_at";
+
+/// Picks the cards to review now out of the cards found in the vault
+pub async fn due_cards(
+    pool: &Pool<Sqlite>,
+    vault_directory: &str,
+    cards: Vec<Flashcard>,
+    limit: Option<usize>,
+    new_limit: Option<usize>,
+) -> Result<DueCards, String> {
+    let rows: Vec<StateRow> = sqlx::query_as(&format!(
+        "SELECT {} FROM flashcard_reviews WHERE vault = ?",
+        STATE_COLUMNS
+    ))
+    .bind(vault_directory)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to read flashcard reviews: {}", e))?;
+    let mut states: HashMap<String, ReviewState> = rows
+        .into_iter()
+        .map(|row| {
+            let state = state_from_row(row);
+            (state.card_id.clone(), state)
+        })
+        .collect();
+
+    let now = now_millis();
+    let mut due = Vec::new();
+    let mut new = Vec::new();
+    for card in cards {
+        match states.remove(&card.id) {
+            Some(state) if state.due_at <= now => due.push(DueCard {
+                card,
+                state: Some(state),
+            }),
+            Some(_) => {}
+            None => new.push(DueCard { card, state: None }),
+        }
+    }
+    due.sort_by_key(|card| card.state.as_ref().map(|state| state.due_at));
+
+    let (due_count, new_count) = (due.len(), new.len());
+    let mut cards: Vec<DueCard> = due;
+    cards.extend(new.into_iter().take(new_limit.unwrap_or(DEFAULT_NEW_CARDS)));
+    cards.truncate(limit.unwrap_or(DEFAULT_DUE_CARDS));
+
+    Ok(DueCards {
+        cards,
+        due: due_count,
+        new: new_count,
+    })
+}
+
+/// Records a review of a card and returns its new schedule
+pub async fn record_review(
+    pool: &Pool<Sqlite>,
+    vault_directory: &str,
+    card_id: &str,
+    relative_path: &str,
+    grade: ReviewGrade,
+) -> Result<ReviewState, String> {
+    let row: Option<StateRow> = sqlx::query_as(&format!(
+        "SELECT {} FROM flashcard_reviews WHERE vault = ? AND card_id = ?",
+        STATE_COLUMNS
+    ))
+    .bind(vault_directory)
+    .bind(card_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to read flashcard review: {}", e))?;
+
+    let mut state = row
+        .map(state_from_row)
+        .unwrap_or_else(|| ReviewState::new(card_id, relative_path));
+    state.relative_path = relative_path.to_string();
+    let next = schedule(&state, grade, now_millis());
+
+    sqlx::query(&format!(
+        "INSERT OR REPLACE INTO flashcard_reviews (vault, {}) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        STATE_COLUMNS
+    ))
+    .bind(vault_directory)
+    .bind(&next.card_id)
+    .bind(&next.relative_path)
+    .bind(next.ease)
+    .bind(next.interval_days)
+    .bind(next.repetitions)
+    .bind(next.lapses)
+    .bind(next.due_at)
+    .bind(next.reviewed_at)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to save flashcard review: {}", e))?;
+
+    Ok(next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_pool;
+    use crate::notes::flashcards::extract_flashcards;
+    use tauri::async_runtime::block_on;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn grows_intervals_with_successful_reviews() {
+        let now = 1_000_000;
+        let new = ReviewState::new("card", "a.md");
+
+        let first = schedule(&new, ReviewGrade::Good, now);
+        assert_eq!((first.repetitions, first.interval_days), (1, 1));
+        assert_eq!(first.due_at, now + DAY);
+        assert_eq!(first.reviewed_at, Some(now));
+        assert!(close(first.ease, STARTING_EASE));
+
+        let second = schedule(&first, ReviewGrade::Good, now);
+        assert_eq!(second.interval_days, 6);
+        let third = schedule(&second, ReviewGrade::Good, now);
+        assert_eq!(third.interval_days, 15);
+        assert_eq!(third.due_at, now + 15 * DAY);
+    }
+
+    #[test]
+    fn grades_adjust_the_ease() {
+        let new = ReviewState::new("card", "a.md");
+        assert!(close(schedule(&new, ReviewGrade::Easy, 0).ease, 2.6));
+        assert!(close(schedule(&new, ReviewGrade::Hard, 0).ease, 2.36));
+        assert!(close(schedule(&new, ReviewGrade::Again, 0).ease, 1.96));
+
+        let mut state = new;
+        for _ in 0..10 {
+            state = schedule(&state, ReviewGrade::Again, 0);
+        }
+        assert!(close(state.ease, MINIMUM_EASE));
+    }
+
+    #[test]
+    fn forgotten_cards_start_over() {
+        let new = ReviewState::new("card", "a.md");
+        let failed = schedule(&new, ReviewGrade::Again, 0);
+        assert_eq!((failed.repetitions, failed.lapses), (0, 0));
+        assert_eq!(failed.due_at, RELEARN_DELAY);
+
+        let learned = schedule(&schedule(&new, ReviewGrade::Good, 0), ReviewGrade::Good, 0);
+        let lapsed = schedule(&learned, ReviewGrade::Again, 0);
+        assert_eq!(
+            (lapsed.repetitions, lapsed.interval_days, lapsed.lapses),
+            (0, 0, 1)
+        );
+        assert_eq!(schedule(&lapsed, ReviewGrade::Good, 0).interval_days, 1);
+    }
+
+    #[test]
+    fn hands_out_due_cards_before_new_ones() {
+        block_on(async {
+            let pool = test_pool().await;
+            let cards = extract_flashcards("a", "a.md", "Q1 :: A\nQ2 :: A\nQ3 :: A\nQ4 :: A");
+            for card in &cards[..3] {
+                record_review(&pool, "/vault", &card.id, "a.md", ReviewGrade::Good)
+                    .await
+                    .unwrap();
+            }
+            // Q1 and Q2 are overdue, Q2 the most; Q3 isn't due yet
+            for (card, due_at) in [(&cards[0], 2000), (&cards[1], 1000)] {
+                sqlx::query("UPDATE flashcard_reviews SET due_at = ? WHERE card_id = ?")
+                    .bind(due_at)
+                    .bind(&card.id)
+                    .execute(&pool)
+                    .await
+                    .unwrap();
+            }
+
+            let due = due_cards(&pool, "/vault", cards.clone(), None, None)
+                .await
+                .unwrap();
+            assert_eq!((due.due, due.new), (2, 1));
+            let questions: Vec<&str> = due
+                .cards
+                .iter()
+                .map(|card| card.card.question.as_str())
+                .collect();
+            assert_eq!(questions, vec!["Q2", "Q1", "Q4"]);
+            assert!(due.cards[2].state.is_none());
+
+            let limited = due_cards(&pool, "/vault", cards.clone(), Some(1), Some(0))
+                .await
+                .unwrap();
+            assert_eq!(limited.cards.len(), 1);
+            assert_eq!((limited.due, limited.new), (2, 1));
+
+            let elsewhere = due_cards(&pool, "/other", cards, None, None).await.unwrap();
+            assert_eq!((elsewhere.due, elsewhere.new), (0, 4));
+        });
+    }
+
+    #[test]
+    fn records_reviews_against_the_current_note() {
+        block_on(async {
+            let pool = test_pool().await;
+            let first = record_review(&pool, "/vault", "card", "a.md", ReviewGrade::Good)
+                .await
+                .unwrap();
+            assert_eq!(first.repetitions, 1);
+
+            let second = record_review(&pool, "/vault", "card", "moved/a.md", ReviewGrade::Good)
+                .await
+                .unwrap();
+            assert_eq!((second.repetitions, second.interval_days), (2, 6));
+            assert_eq!(second.relative_path, "moved/a.md");
+        });
+    }
+}
@@ -0,0 +1,746 @@
+// src/db/graph.rs
+use crate::notes::attachments;
+use crate::notes::links::{self, LinkResolver};
+use crate::notes::outline;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Distance the layout aims for between linked nodes
+pub const IDEAL_EDGE_LENGTH: f64 = 60.0;
+
+/// Layout iterations when the query doesn't ask for a number
+pub const DEFAULT_LAYOUT_ITERATIONS: usize = 300;
+
+/// Most layout iterations a single request can run
+pub const MAX_LAYOUT_ITERATIONS: usize = 2000;
+
+/// Pull towards the origin per unit of distance, so parts of the graph that
+/// aren't linked to each other stay close: a node at distance `r` is pulled
+/// back by `r * GRAVITY`. The repulsion of the nodes inside that radius
+/// grows linearly with `r` too, so the two settle at a density of about
+/// one node per `π * IDEAL_EDGE_LENGTH²`.
+const GRAVITY: f64 = 1.0;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeKind {
+    Note,
+    Tag,
+    Attachment,
+    /// A link target no single note or attachment matches. Ambiguous links,
+    /// which match several notes, end up here too.
+    Unresolved,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeKind {
+    Link,
+    Embed,
+    /// From a note to one of its tags
+    Tag,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphNode {
+    /// Unique within the graph: the kind, then the path, tag or link target
+    pub id: String,
+    pub kind: NodeKind,
+    pub label: String,
+    /// Vault-relative path of notes and attachments
+    pub relative_path: Option<String>,
+    /// Sum of the weights of the edges touching the node
+    pub degree: usize,
+    /// Position from the layout, when one was asked for
+    pub x: Option<f64>,
+    pub y: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+    pub kind: EdgeKind,
+    /// How many times the source links to the target
+    pub weight: usize,
+}
+
+/// Which part of the vault the graph shows
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphQuery {
+    /// Only notes inside this folder, at any depth
+    pub folder: Option<String>,
+    /// Only notes having this tag
+    pub tag: Option<String>,
+    /// Only notes within `depth` links of this note, in either direction
+    pub center: Option<String>,
+    pub depth: usize,
+    pub include_tags: bool,
+    pub include_attachments: bool,
+    pub include_unresolved: bool,
+    /// Compute node positions on the backend
+    pub layout: bool,
+    pub iterations: Option<usize>,
+}
+
+impl Default for GraphQuery {
+    fn default() -> Self {
+        GraphQuery {
+            folder: None,
+            tag: None,
+            center: None,
+            depth: 1,
+            include_tags: false,
+            include_attachments: false,
+            include_unresolved: false,
+            layout: false,
+            iterations: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Graph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+/// The cached notes, links and tags a graph is built from
+pub struct GraphData {
+    /// Path, title and aliases of each note
+    notes: Vec<(String, String, Vec<String>)>,
+    /// Source path, raw target and whether it is an embed
+    links: Vec<(String, String, bool)>,
+    /// Path and tag
+    tags: Vec<(String, String)>,
+}
+
+/// Reads what the graph needs from the note cache
+pub async fn load_graph_data(
+    pool: &Pool<Sqlite>,
+    vault_directory: &str,
+) -> Result<GraphData, String> {
+    let notes: Vec<(String, String, String)> =
+        sqlx::query_as("SELECT relative_path, title, aliases FROM notes WHERE vault = ?")
+            .bind(vault_directory)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| format!("Failed to read note cache: {}", e))?;
+    let notes = notes
+        .into_iter()
+        .map(|(path, title, aliases)| {
+            (
+                path,
+                title,
+                serde_json::from_str(&aliases).unwrap_or_default(),
+            )
+        })
+        .collect();
+    let links: Vec<(String, String, bool)> =
+        sqlx::query_as("SELECT source_path, target, embed FROM note_links WHERE vault = ?")
+            .bind(vault_directory)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| format!("Failed to read note cache: {}", e))?;
+    let tags: Vec<(String, String)> =
+        sqlx::query_as("SELECT relative_path, tag FROM note_tags WHERE vault = ?")
+            .bind(vault_directory)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| format!("Failed to read note cache: {}", e))?;
+
+    Ok(GraphData { notes, links, tags })
+}
+
+/// Where a link ended up
+enum LinkEnd {
+    Note(String),
+    Attachment(String),
+    Unresolved(String),
+}
+
+fn tag_name(tag: &str) -> String {
+    tag.trim_start_matches('#').to_lowercase()
+}
+
+/// Resolves the cached links, dropping links a note makes to itself.
+/// Links to files are only kept when attachments are shown. A link that
+/// is ambiguous has no single end, so it counts as unresolved.
+fn resolve_links(
+    vault_directory: &str,
+    data: &GraphData,
+    include_attachments: bool,
+) -> Vec<(String, LinkEnd, bool)> {
+    let resolver = LinkResolver::with_names(vault_directory, data.notes.clone());
+    // Telling files from notes with dots in their names needs the files
+    let all_attachments = attachments::get_all_attachments(vault_directory);
+
+    let mut resolved = Vec::new();
+    for (source, target, embed) in &data.links {
+        let (note, _) = outline::split_link_target(target);
+        if note.is_empty() {
+            continue;
+        }
+
+        let end = if links::is_attachment_target(&resolver, target, Some(source), &all_attachments)
+        {
+            if !include_attachments {
+                continue;
+            }
+            match attachments::resolve_attachment(&all_attachments, source, &note, true).attachment
+            {
+                Some(attachment) => LinkEnd::Attachment(attachment.relative_path),
+                None => LinkEnd::Unresolved(note),
+            }
+        } else {
+            match resolver.resolve_path(target, Some(source)) {
+                Some(path) if path == *source => continue,
+                Some(path) => LinkEnd::Note(path),
+                None => LinkEnd::Unresolved(note),
+            }
+        };
+        resolved.push((source.clone(), end, *embed));
+    }
+    resolved
+}
+
+/// Builds the graph for a query, laying it out if asked to
+pub fn build_graph(
+    vault_directory: &str,
+    data: GraphData,
+    query: &GraphQuery,
+) -> Result<Graph, String> {
+    let links = resolve_links(vault_directory, &data, query.include_attachments);
+
+    // Notes matching the folder and tag filters
+    let folder = query
+        .folder
+        .as_deref()
+        .map(|folder| folder.trim_matches('/'))
+        .filter(|folder| !folder.is_empty());
+    let tagged: Option<HashSet<&str>> = query.tag.as_deref().map(|wanted| {
+        let wanted = tag_name(wanted);
+        data.tags
+            .iter()
+            .filter(|(_, tag)| tag_name(tag) == wanted)
+            .map(|(path, _)| path.as_str())
+            .collect()
+    });
+    let mut kept: HashSet<&str> = data
+        .notes
+        .iter()
+        .map(|(path, _, _)| path.as_str())
+        .filter(|path| folder.is_none_or(|folder| path.starts_with(&format!("{}/", folder))))
+        .filter(|path| tagged.as_ref().is_none_or(|tagged| tagged.contains(path)))
+        .collect();
+
+    // Then the neighborhood of the center note, following links both ways
+    if let Some(center) = query.center.as_deref() {
+        let wanted = center.replace('\\', "/");
+        let Some(center) = data
+            .notes
+            .iter()
+            .map(|(path, _, _)| path.as_str())
+            .find(|path| *path == wanted)
+        else {
+            return Err(format!("Note not found: {}", wanted));
+        };
+
+        let mut neighbors: HashMap<&str, Vec<&str>> = HashMap::new();
+        for (source, end, _) in &links {
+            if let LinkEnd::Note(target) = end {
+                neighbors.entry(source).or_default().push(target);
+                neighbors.entry(target).or_default().push(source);
+            }
+        }
+
+        let mut distances: HashMap<&str, usize> = HashMap::new();
+        let mut queue = VecDeque::new();
+        distances.insert(center, 0);
+        queue.push_back(center);
+        while let Some(path) = queue.pop_front() {
+            let distance = distances[path];
+            if distance >= query.depth {
+                continue;
+            }
+            for &next in neighbors.get(path).into_iter().flatten() {
+                if !distances.contains_key(next) {
+                    distances.insert(next, distance + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        kept.retain(|path| distances.contains_key(path));
+        kept.insert(center);
+    }
+
+    let titles: HashMap<&str, &str> = data
+        .notes
+        .iter()
+        .map(|(path, title, _)| (path.as_str(), title.as_str()))
+        .collect();
+    let mut nodes: HashMap<String, GraphNode> = HashMap::new();
+    let mut add_node = |id: String, kind: NodeKind, label: String, path: Option<String>| {
+        nodes.entry(id.clone()).or_insert(GraphNode {
+            id,
+            kind,
+            label,
+            relative_path: path,
+            degree: 0,
+            x: None,
+            y: None,
+        });
+    };
+    for path in &kept {
+        add_node(
+            format!("note:{}", path),
+            NodeKind::Note,
+            titles.get(path).unwrap_or(path).to_string(),
+            Some(path.to_string()),
+        );
+    }
+
+    // Edges between kept notes and what they point to, counted by kind
+    let mut weights: HashMap<(String, String, EdgeKind), usize> = HashMap::new();
+    for (source, end, embed) in &links {
+        if !kept.contains(source.as_str()) {
+            continue;
+        }
+        let target = match end {
+            LinkEnd::Note(path) if kept.contains(path.as_str()) => format!("note:{}", path),
+            LinkEnd::Note(_) => continue,
+            LinkEnd::Attachment(path) => {
+                let name = path.rsplit('/').next().unwrap_or(path).to_string();
+                add_node(
+                    format!("attachment:{}", path),
+                    NodeKind::Attachment,
+                    name,
+                    Some(path.clone()),
+                );
+                format!("attachment:{}", path)
+            }
+            LinkEnd::Unresolved(_) if !query.include_unresolved => continue,
+            LinkEnd::Unresolved(target) => {
+                add_node(
+                    format!("unresolved:{}", target),
+                    NodeKind::Unresolved,
+                    target.clone(),
+                    None,
+                );
+                format!("unresolved:{}", target)
+            }
+        };
+        let kind = if *embed {
+            EdgeKind::Embed
+        } else {
+            EdgeKind::Link
+        };
+        *weights
+            .entry((format!("note:{}", source), target, kind))
+            .or_default() += 1;
+    }
+    if query.include_tags {
+        for (path, tag) in &data.tags {
+            if !kept.contains(path.as_str()) {
+                continue;
+            }
+            let name = tag_name(tag);
+            add_node(
+                format!("tag:{}", name),
+                NodeKind::Tag,
+                format!("#{}", name),
+                None,
+            );
+            *weights
+                .entry((
+                    format!("note:{}", path),
+                    format!("tag:{}", name),
+                    EdgeKind::Tag,
+                ))
+                .or_default() += 1;
+        }
+    }
+
+    let mut edges: Vec<GraphEdge> = weights
+        .into_iter()
+        .map(|((source, target, kind), weight)| GraphEdge {
+            source,
+            target,
+            kind,
+            weight,
+        })
+        .collect();
+    edges.sort_by(|a, b| (&a.source, &a.target, a.kind).cmp(&(&b.source, &b.target, b.kind)));
+
+    let mut nodes: Vec<GraphNode> = nodes.into_values().collect();
+    nodes.sort_by(|a, b| a.id.cmp(&b.id));
+    let index: HashMap<String, usize> = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| (node.id.clone(), i))
+        .collect();
+    for edge in &edges {
+        nodes[index[&edge.source]].degree += edge.weight;
+        nodes[index[&edge.target]].degree += edge.weight;
+    }
+
+    if query.layout {
+        let springs: Vec<(usize, usize, f64)> = edges
+            .iter()
+            .map(|edge| {
+                (
+                    index[&edge.source],
+                    index[&edge.target],
+                    1.0 + (edge.weight as f64).ln(),
+                )
+            })
+            .collect();
+        let iterations = query
+            .iterations
+            .unwrap_or(DEFAULT_LAYOUT_ITERATIONS)
+            .min(MAX_LAYOUT_ITERATIONS);
+        let positions = force_layout(nodes.len(), &springs, iterations);
+        for (node, (x, y)) in nodes.iter_mut().zip(positions) {
+            node.x = Some(x);
+            node.y = Some(y);
+        }
+    }
+
+    Ok(Graph { nodes, edges })
+}
+
+/// A quadtree over node positions. Far away groups of nodes repel as one
+/// node at their centre of mass (Barnes-Hut), so computing the repulsion on
+/// every node takes O(n log n) instead of O(n²).
+struct QuadTree {
+    quads: Vec<Quad>,
+}
+
+struct Quad {
+    /// Top left corner and side length
+    x: f64,
+    y: f64,
+    size: f64,
+    mass: f64,
+    center_x: f64,
+    center_y: f64,
+    /// The node in a leaf holding a single one
+    body: Option<usize>,
+    /// Index of the first of four children
+    children: Option<usize>,
+}
+
+/// How deep nodes at (almost) the same spot are split before they're lumped
+/// together
+const MAX_QUAD_DEPTH: usize = 24;
+
+/// Groups of nodes smaller than this fraction of their distance count as one
+const BARNES_HUT_THETA: f64 = 0.9;
+
+impl Quad {
+    fn new(x: f64, y: f64, size: f64) -> Self {
+        Quad {
+            x,
+            y,
+            size,
+            mass: 0.0,
+            center_x: 0.0,
+            center_y: 0.0,
+            body: None,
+            children: None,
+        }
+    }
+}
+
+impl QuadTree {
+    fn build(positions: &[(f64, f64)]) -> Self {
+        let (mut min_x, mut min_y) = (f64::MAX, f64::MAX);
+        let (mut max_x, mut max_y) = (f64::MIN, f64::MIN);
+        for &(x, y) in positions {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+        let size = (max_x - min_x).max(max_y - min_y) + 1.0;
+
+        let mut tree = QuadTree {
+            quads: vec![Quad::new(min_x, min_y, size)],
+        };
+        for body in 0..positions.len() {
+            tree.insert(0, body, positions, 0);
+        }
+        tree
+    }
+
+    fn insert(&mut self, quad: usize, body: usize, positions: &[(f64, f64)], depth: usize) {
+        let (x, y) = positions[body];
+        let node = &mut self.quads[quad];
+        node.center_x = (node.center_x * node.mass + x) / (node.mass + 1.0);
+        node.center_y = (node.center_y * node.mass + y) / (node.mass + 1.0);
+        node.mass += 1.0;
+
+        if node.mass == 1.0 {
+            node.body = Some(body);
+            return;
+        }
+        if depth >= MAX_QUAD_DEPTH {
+            return;
+        }
+
+        if node.children.is_none() {
+            self.split(quad, positions, depth);
+        }
+        let child = self.child_for(quad, (x, y));
+        self.insert(child, body, positions, depth + 1);
+    }
+
+    /// Gives a leaf four children and moves its node into one of them
+    fn split(&mut self, quad: usize, positions: &[(f64, f64)], depth: usize) {
+        let first_child = self.quads.len();
+        let node = &mut self.quads[quad];
+        let (x, y, half) = (node.x, node.y, node.size / 2.0);
+        let existing = node.body.take();
+        node.children = Some(first_child);
+
+        self.quads.push(Quad::new(x, y, half));
+        self.quads.push(Quad::new(x + half, y, half));
+        self.quads.push(Quad::new(x, y + half, half));
+        self.quads.push(Quad::new(x + half, y + half, half));
+        if let Some(existing) = existing {
+            let child = self.child_for(quad, positions[existing]);
+            self.insert(child, existing, positions, depth + 1);
+        }
+    }
+
+    fn child_for(&self, quad: usize, (x, y): (f64, f64)) -> usize {
+        let node = &self.quads[quad];
+        let half = node.size / 2.0;
+        let right = (x >= node.x + half) as usize;
+        let below = (y >= node.y + half) as usize;
+        node.children.unwrap_or_default() + right + 2 * below
+    }
+
+    /// Repulsion on a node at `(x, y)`, with `k` the ideal edge length
+    fn repulsion(&self, (x, y): (f64, f64), k: f64) -> (f64, f64) {
+        let mut force = (0.0, 0.0);
+        let mut stack = vec![0];
+        while let Some(quad) = stack.pop() {
+            let node = &self.quads[quad];
+            if node.mass == 0.0 {
+                continue;
+            }
+            let delta_x = x - node.center_x;
+            let delta_y = y - node.center_y;
+            let distance = (delta_x * delta_x + delta_y * delta_y).sqrt();
+
+            match node.children {
+                Some(children) if node.size >= distance * BARNES_HUT_THETA => {
+                    stack.extend(children..children + 4);
+                }
+                // A node doesn't repel itself
+                _ if distance < 0.01 => {}
+                _ => {
+                    let strength = node.mass * k * k / distance;
+                    force.0 += delta_x / distance * strength;
+                    force.1 += delta_y / distance * strength;
+                }
+            }
+        }
+        force
+    }
+}
+
+/// Places nodes with a force-directed layout (Fruchterman-Reingold): edges
+/// pull their ends together, nodes push each other apart and a pull towards
+/// the origin that grows with the distance (`GRAVITY`) keeps unlinked parts
+/// near the rest. Edges are `(from, to, strength)`.
+pub fn force_layout(
+    node_count: usize,
+    edges: &[(usize, usize, f64)],
+    iterations: usize,
+) -> Vec<(f64, f64)> {
+    let k = IDEAL_EDGE_LENGTH;
+
+    // Start on a sunflower spiral: evenly spread, and the same on every run
+    let golden_angle = std::f64::consts::PI * (3.0 - 5f64.sqrt());
+    let mut positions: Vec<(f64, f64)> = (0..node_count)
+        .map(|i| {
+            let radius = k * (i as f64 + 0.5).sqrt();
+            let angle = i as f64 * golden_angle;
+            (radius * angle.cos(), radius * angle.sin())
+        })
+        .collect();
+    if node_count < 2 {
+        return positions;
+    }
+
+    let start_temperature = (k * (node_count as f64).sqrt() / 10.0).max(k);
+    for iteration in 0..iterations {
+        let temperature = start_temperature * (1.0 - iteration as f64 / iterations as f64);
+
+        let tree = QuadTree::build(&positions);
+        let mut displacement: Vec<(f64, f64)> = positions
+            .par_iter()
+            .map(|&position| tree.repulsion(position, k))
+            .collect();
+
+        for &(from, to, strength) in edges {
+            if from == to {
+                continue;
+            }
+            let delta_x = positions[from].0 - positions[to].0;
+            let delta_y = positions[from].1 - positions[to].1;
+            let distance = (delta_x * delta_x + delta_y * delta_y).sqrt().max(0.01);
+            let force = distance * distance / k * strength;
+            displacement[from].0 -= delta_x / distance * force;
+            displacement[from].1 -= delta_y / distance * force;
+            displacement[to].0 += delta_x / distance * force;
+            displacement[to].1 += delta_y / distance * force;
+        }
+
+        for (position, moved) in positions.iter_mut().zip(displacement.iter_mut()) {
+            moved.0 -= position.0 * GRAVITY;
+            moved.1 -= position.1 * GRAVITY;
+
+            let length = (moved.0 * moved.0 + moved.1 * moved.1).sqrt();
+            if length > 0.0 {
+                let step = length.min(temperature);
+                position.0 += moved.0 / length * step;
+                position.1 += moved.1 / length * step;
+            }
+        }
+    }
+
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(path: &str) -> (String, String, Vec<String>) {
+        (
+            path.to_string(),
+            path.trim_end_matches(".md").to_string(),
+            Vec::new(),
+        )
+    }
+
+    fn link(source: &str, target: &str) -> (String, String, bool) {
+        (source.to_string(), target.to_string(), false)
+    }
+
+    fn sample() -> GraphData {
+        GraphData {
+            notes: vec![
+                note("a.md"),
+                note("b.md"),
+                note("x/dup.md"),
+                note("y/dup.md"),
+                note("lonely.md"),
+            ],
+            links: vec![
+                link("a.md", "b"),
+                link("a.md", "b#Heading"),
+                link("a.md", "a"),
+                link("a.md", "dup"),
+                link("b.md", "missing"),
+            ],
+            tags: vec![("a.md".to_string(), "#Topic".to_string())],
+        }
+    }
+
+    fn ids(graph: &Graph) -> Vec<&str> {
+        let mut ids: Vec<&str> = graph.nodes.iter().map(|node| node.id.as_str()).collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn builds_weighted_edges_between_notes() {
+        let graph = build_graph("/vault", sample(), &GraphQuery::default()).unwrap();
+        assert_eq!(graph.nodes.len(), 5);
+        assert_eq!(graph.edges.len(), 1);
+        assert_eq!(graph.edges[0].weight, 2);
+        assert_eq!(graph.edges[0].kind, EdgeKind::Link);
+    }
+
+    #[test]
+    fn ambiguous_links_count_as_unresolved() {
+        let query = GraphQuery {
+            include_unresolved: true,
+            include_tags: true,
+            ..Default::default()
+        };
+        let graph = build_graph("/vault", sample(), &query).unwrap();
+        let unresolved: Vec<&str> = graph
+            .nodes
+            .iter()
+            .filter(|node| node.kind == NodeKind::Unresolved)
+            .map(|node| node.label.as_str())
+            .collect();
+        assert_eq!(unresolved.len(), 2);
+        assert!(unresolved.contains(&"dup") && unresolved.contains(&"missing"));
+        assert!(graph.nodes.iter().any(|node| node.kind == NodeKind::Tag));
+    }
+
+    #[test]
+    fn links_to_notes_with_dots_in_their_names_are_edges() {
+        let data = GraphData {
+            notes: vec![note("a.md"), note("v1.2 release.md"), note("Dr. Smith.md")],
+            links: vec![
+                link("a.md", "v1.2 release"),
+                link("a.md", "Dr. Smith#Contact"),
+                link("a.md", "diagram.png"),
+            ],
+            tags: Vec::new(),
+        };
+        let query = GraphQuery {
+            include_unresolved: true,
+            ..Default::default()
+        };
+        let graph = build_graph("/vault", data, &query).unwrap();
+        assert_eq!(graph.edges.len(), 2);
+        assert!(graph
+            .nodes
+            .iter()
+            .all(|node| node.kind != NodeKind::Unresolved));
+    }
+
+    #[test]
+    fn filters_to_the_neighborhood_of_a_note() {
+        let query = GraphQuery {
+            center: Some("b.md".to_string()),
+            ..Default::default()
+        };
+        let graph = build_graph("/vault", sample(), &query).unwrap();
+        assert_eq!(ids(&graph).len(), 2);
+
+        let query = GraphQuery {
+            center: Some("nope.md".to_string()),
+            ..Default::default()
+        };
+        assert!(build_graph("/vault", sample(), &query).is_err());
+    }
+
+    #[test]
+    fn layout_is_deterministic_and_spreads_nodes() {
+        let edges = vec![(0, 1, 1.0), (1, 2, 1.0), (2, 0, 1.0)];
+        let first = force_layout(6, &edges, 200);
+        assert_eq!(first, force_layout(6, &edges, 200));
+
+        for (i, a) in first.iter().enumerate() {
+            assert!(a.0.is_finite() && a.1.is_finite());
+            for b in &first[i + 1..] {
+                let distance = ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt();
+                assert!(distance > IDEAL_EDGE_LENGTH / 10.0);
+                assert!(distance < IDEAL_EDGE_LENGTH * 10.0);
+            }
+        }
+    }
+}
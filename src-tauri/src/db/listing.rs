@@ -0,0 +1,466 @@
+// src/db/listing.rs
+use super::cache::{self, CachedNote, NoteRow, NOTE_COLUMNS};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as JsonValue};
+use sqlx::{Pool, QueryBuilder, Sqlite};
+
+/// Page size when the webview doesn't ask for one
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// Largest page a single request can get
+pub const MAX_PAGE_SIZE: usize = 1000;
+
+/// What the note list is sorted by
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SortField {
+    #[default]
+    Title,
+    Path,
+    Modified,
+    Created,
+    Size,
+    WordCount,
+    /// The frontmatter value named by `sort_key`
+    Frontmatter,
+}
+
+/// Sorting, filtering and paging of the note list
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct NoteQuery {
+    pub sort: SortField,
+    pub sort_key: Option<String>,
+    pub descending: bool,
+    /// Case-insensitive text matched against titles, aliases and paths
+    pub search: Option<String>,
+    /// Only notes inside this folder, at any depth
+    pub folder: Option<String>,
+    /// Only notes having all of these tags
+    pub tags: Vec<String>,
+    /// Only notes whose frontmatter has these values. A list field matches
+    /// if any of its items does.
+    pub frontmatter: Map<String, JsonValue>,
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// One page of the note list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotePage {
+    pub notes: Vec<CachedNote>,
+    /// Notes matching the filters across all pages
+    pub total: i64,
+    /// Pass back to get the next page; `None` on the last one
+    pub next_cursor: Option<String>,
+}
+
+/// JSON path of a top-level frontmatter key
+fn frontmatter_path(key: &str) -> String {
+    format!("$.\"{}\"", key.replace('"', ""))
+}
+
+/// Escapes `LIKE` wildcards so user text matches literally
+fn like_pattern(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Binds a JSON value as the SQLite value `json_extract` would return for it
+fn push_json_bind(builder: &mut QueryBuilder<'_, Sqlite>, value: &JsonValue) {
+    match value {
+        JsonValue::String(text) => builder.push_bind(text.clone()),
+        JsonValue::Bool(flag) => builder.push_bind(*flag as i64),
+        JsonValue::Number(number) => match number.as_i64() {
+            Some(integer) => builder.push_bind(integer),
+            None => builder.push_bind(number.as_f64().unwrap_or_default()),
+        },
+        other => builder.push_bind(other.to_string()),
+    };
+}
+
+/// Pushes the expression the list is sorted by
+fn push_sort_expression(builder: &mut QueryBuilder<'_, Sqlite>, query: &NoteQuery) {
+    match query.sort {
+        SortField::Title => builder.push("lower(n.title)"),
+        SortField::Path => builder.push("lower(n.relative_path)"),
+        SortField::Modified => builder.push("n.mtime"),
+        SortField::Created => builder.push("n.ctime"),
+        SortField::Size => builder.push("n.size"),
+        SortField::WordCount => builder.push("n.word_count"),
+        SortField::Frontmatter => builder
+            .push("COALESCE(json_extract(n.frontmatter, ")
+            .push_bind(frontmatter_path(
+                query.sort_key.as_deref().unwrap_or_default(),
+            ))
+            .push("), '')"),
+    };
+}
+
+fn push_filters(builder: &mut QueryBuilder<'_, Sqlite>, vault_directory: &str, query: &NoteQuery) {
+    builder
+        .push(" WHERE n.vault = ")
+        .push_bind(vault_directory.to_string());
+
+    if let Some(search) = query
+        .search
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+    {
+        let pattern = format!("%{}%", like_pattern(search));
+        builder
+            .push(" AND (n.title LIKE ")
+            .push_bind(pattern.clone())
+            .push(" ESCAPE '\\' OR n.relative_path LIKE ")
+            .push_bind(pattern.clone())
+            .push(" ESCAPE '\\' OR n.aliases LIKE ")
+            .push_bind(pattern)
+            .push(" ESCAPE '\\')");
+    }
+
+    if let Some(folder) = query
+        .folder
+        .as_deref()
+        .map(|folder| folder.trim_matches('/'))
+        .filter(|folder| !folder.is_empty())
+    {
+        builder
+            .push(" AND n.relative_path LIKE ")
+            .push_bind(format!("{}/%", like_pattern(folder)))
+            .push(" ESCAPE '\\'");
+    }
+
+    for tag in &query.tags {
+        builder
+            .push(
+                " AND EXISTS (SELECT 1 FROM note_tags t
+                  WHERE t.vault = n.vault AND t.relative_path = n.relative_path
+                  AND lower(ltrim(t.tag, '#')) = ",
+            )
+            .push_bind(tag.trim_start_matches('#').to_lowercase())
+            .push(")");
+    }
+
+    for (key, value) in &query.frontmatter {
+        builder
+            .push(" AND EXISTS (SELECT 1 FROM json_each(n.frontmatter, ")
+            .push_bind(frontmatter_path(key))
+            .push(") j WHERE j.value = ");
+        push_json_bind(builder, value);
+        builder.push(")");
+    }
+}
+
+/// The cursor is the sort value and path of the last note on a page
+fn encode_cursor(sort_value: &str, relative_path: &str) -> String {
+    let value: JsonValue = serde_json::from_str(sort_value).unwrap_or(JsonValue::Null);
+    let cursor = serde_json::json!([value, relative_path]);
+    URL_SAFE_NO_PAD.encode(cursor.to_string())
+}
+
+fn decode_cursor(cursor: &str) -> Result<(JsonValue, String), String> {
+    let invalid = || "Invalid note list cursor".to_string();
+    let bytes = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| invalid())?;
+    let (value, path): (JsonValue, String) =
+        serde_json::from_slice(&bytes).map_err(|_| invalid())?;
+    Ok((value, path))
+}
+
+/// Lists a page of cached notes
+pub async fn list_notes(
+    pool: &Pool<Sqlite>,
+    vault_directory: &str,
+    query: &NoteQuery,
+) -> Result<NotePage, String> {
+    if query.sort == SortField::Frontmatter && query.sort_key.is_none() {
+        return Err("Sorting by frontmatter needs a sort_key".to_string());
+    }
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    let mut count = QueryBuilder::new("SELECT COUNT(*) FROM notes n");
+    push_filters(&mut count, vault_directory, query);
+    let (total,): (i64,) = count
+        .build_query_as()
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("Failed to list notes: {}", e))?;
+
+    let mut select = QueryBuilder::new("SELECT ");
+    select.push(NOTE_COLUMNS).push(", json_quote(");
+    push_sort_expression(&mut select, query);
+    select.push(") FROM notes n");
+    push_filters(&mut select, vault_directory, query);
+
+    // Keyset paging: continue after the last note of the previous page
+    let (after, direction) = if query.descending {
+        ("<", "DESC")
+    } else {
+        (">", "ASC")
+    };
+    if let Some(cursor) = &query.cursor {
+        let (value, path) = decode_cursor(cursor)?;
+        select.push(" AND (");
+        push_sort_expression(&mut select, query);
+        select.push(format!(" {} ", after));
+        push_json_bind(&mut select, &value);
+        select.push(" OR (");
+        push_sort_expression(&mut select, query);
+        select.push(" = ");
+        push_json_bind(&mut select, &value);
+        select
+            .push(format!(" AND n.relative_path {} ", after))
+            .push_bind(path)
+            .push("))");
+    }
+
+    select.push(" ORDER BY ");
+    push_sort_expression(&mut select, query);
+    select
+        .push(format!(
+            " {}, n.relative_path {} LIMIT ",
+            direction, direction
+        ))
+        .push_bind(limit as i64 + 1);
+
+    type PageRow = (
+        String,
+        String,
+        String,
+        String,
+        i64,
+        i64,
+        i64,
+        i64,
+        String,
+        Option<String>,
+        String,
+    );
+    let mut rows: Vec<PageRow> = select
+        .build_query_as()
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to list notes: {}", e))?;
+
+    let has_more = rows.len() > limit;
+    rows.truncate(limit);
+    let next_cursor = if has_more {
+        rows.last().map(|row| encode_cursor(&row.10, &row.3))
+    } else {
+        None
+    };
+
+    let notes = rows
+        .into_iter()
+        .map(|row| {
+            let note: NoteRow = (
+                row.0, row.1, row.2, row.3, row.4, row.5, row.6, row.7, row.8, row.9,
+            );
+            cache::note_from_row(note)
+        })
+        .collect();
+
+    Ok(NotePage {
+        notes,
+        total,
+        next_cursor,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{cache::sync_vault, test_pool};
+    use crate::notes::scan::CancelToken;
+    use std::fs;
+    use tauri::async_runtime::block_on;
+
+    const NOTES: &[(&str, &str)] = &[
+        (
+            "a.md",
+            "---\ntitle: Same\nstatus: done\npriority: 2\ntags: [Work]\n---\none two three",
+        ),
+        ("b.md", "---\ntitle: Same\nstatus: open\npriority: 1\n---\nbody"),
+        (
+            "notes/c.md",
+            "---\ntitle: Charlie_x\nstatus: [open, later]\npriority: 3\npublished: true\n---\n#work",
+        ),
+        ("notes/d.md", "---\ntitle: delta\n---\nfoo"),
+        ("e.md", "---\ntitle: Echo 100%\n---\n"),
+        ("f.md", "---\ntitle: Complex\n---\n"),
+    ];
+
+    async fn vault_pool() -> (tempfile::TempDir, Pool<Sqlite>, String) {
+        let vault = tempfile::tempdir().unwrap();
+        for (path, content) in NOTES {
+            let path = vault.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        let pool = test_pool().await;
+        let vault_directory = vault.path().to_string_lossy().to_string();
+        sync_vault(&pool, &vault_directory, &CancelToken::default(), None)
+            .await
+            .unwrap();
+        (vault, pool, vault_directory)
+    }
+
+    /// Follows the cursors to the end and returns the paths of each page
+    async fn pages(pool: &Pool<Sqlite>, vault: &str, query: NoteQuery) -> Vec<Vec<String>> {
+        let mut query = query;
+        let mut pages = Vec::new();
+        loop {
+            let page = list_notes(pool, vault, &query).await.unwrap();
+            assert_eq!(page.total, NOTES.len() as i64);
+            pages.push(
+                page.notes
+                    .into_iter()
+                    .map(|note| note.relative_path)
+                    .collect(),
+            );
+            match page.next_cursor {
+                Some(cursor) => query.cursor = Some(cursor),
+                None => return pages,
+            }
+        }
+    }
+
+    async fn paths(pool: &Pool<Sqlite>, vault: &str, query: NoteQuery) -> Vec<String> {
+        list_notes(pool, vault, &query)
+            .await
+            .unwrap()
+            .notes
+            .into_iter()
+            .map(|note| note.relative_path)
+            .collect()
+    }
+
+    #[test]
+    fn pages_through_notes_with_ties_broken_by_path() {
+        block_on(async {
+            let (_dir, pool, vault) = vault_pool().await;
+            let query = NoteQuery {
+                limit: Some(2),
+                ..Default::default()
+            };
+            assert_eq!(
+                pages(&pool, &vault, query.clone()).await,
+                vec![
+                    vec!["notes/c.md", "f.md"],
+                    vec!["notes/d.md", "e.md"],
+                    vec!["a.md", "b.md"],
+                ]
+            );
+
+            let descending = NoteQuery {
+                descending: true,
+                limit: Some(4),
+                ..query
+            };
+            assert_eq!(
+                pages(&pool, &vault, descending).await,
+                vec![
+                    vec!["b.md", "a.md", "e.md", "notes/d.md"],
+                    vec!["f.md", "notes/c.md"],
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn pages_by_frontmatter_values() {
+        block_on(async {
+            let (_dir, pool, vault) = vault_pool().await;
+            let query = NoteQuery {
+                sort: SortField::Frontmatter,
+                limit: Some(2),
+                ..Default::default()
+            };
+            assert!(list_notes(&pool, &vault, &query).await.is_err());
+
+            let query = NoteQuery {
+                sort_key: Some("priority".to_string()),
+                ..query
+            };
+            assert_eq!(
+                pages(&pool, &vault, query).await,
+                vec![
+                    vec!["b.md", "a.md"],
+                    vec!["notes/c.md", "e.md"],
+                    vec!["f.md", "notes/d.md"],
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn filters_by_search_folder_tags_and_frontmatter() {
+        block_on(async {
+            let (_dir, pool, vault) = vault_pool().await;
+            let search = |text: &str| NoteQuery {
+                search: Some(text.to_string()),
+                ..Default::default()
+            };
+            assert_eq!(
+                paths(&pool, &vault, search("e_x")).await,
+                vec!["notes/c.md"]
+            );
+            assert_eq!(paths(&pool, &vault, search("0%")).await, vec!["e.md"]);
+            assert_eq!(paths(&pool, &vault, search("  ")).await.len(), NOTES.len());
+
+            let folder = NoteQuery {
+                folder: Some("/notes/".to_string()),
+                ..Default::default()
+            };
+            assert_eq!(
+                paths(&pool, &vault, folder).await,
+                vec!["notes/c.md", "notes/d.md"]
+            );
+
+            let tags = NoteQuery {
+                tags: vec!["#WORK".to_string()],
+                ..Default::default()
+            };
+            assert_eq!(paths(&pool, &vault, tags).await, vec!["notes/c.md", "a.md"]);
+
+            let frontmatter = |key: &str, value: JsonValue| NoteQuery {
+                frontmatter: Map::from_iter([(key.to_string(), value)]),
+                ..Default::default()
+            };
+            let status = frontmatter("status", JsonValue::from("open"));
+            assert_eq!(
+                paths(&pool, &vault, status).await,
+                vec!["notes/c.md", "b.md"]
+            );
+            let priority = frontmatter("priority", JsonValue::from(2));
+            assert_eq!(paths(&pool, &vault, priority).await, vec!["a.md"]);
+            let published = frontmatter("published", JsonValue::from(true));
+            assert_eq!(paths(&pool, &vault, published).await, vec!["notes/c.md"]);
+        });
+    }
+
+    #[test]
+    fn rejects_invalid_cursors() {
+        block_on(async {
+            let (_dir, pool, vault) = vault_pool().await;
+            let query = NoteQuery {
+                cursor: Some("not a cursor".to_string()),
+                ..Default::default()
+            };
+            assert!(list_notes(&pool, &vault, &query).await.is_err());
+
+            let cursor = encode_cursor("\"same\"", "a.md");
+            assert_eq!(
+                decode_cursor(&cursor).unwrap(),
+                (JsonValue::from("same"), "a.md".to_string())
+            );
+        });
+    }
+}
@@ -0,0 +1,243 @@
+// src/db/mod.rs
+pub mod cache;
+pub mod flashcards;
+pub mod graph;
+pub mod listing;
+pub mod note_ids;
+pub mod settings;
+pub mod stats;
+pub mod workspace;
+
+use sqlx::{Pool, Sqlite};
+use tauri::{AppHandle, Manager, Runtime};
+use tauri_plugin_sql::{DbInstances, DbPool};
+
+/// The app database, loaded and migrated by the SQL plugin at startup
+pub const DB_URL: &str = "sqlite:settings.db";
+
+/// Schema changes, applied in order by the SQL plugin
+pub fn migrations() -> Vec<tauri_plugin_sql::Migration> {
+    vec![
+        tauri_plugin_sql::Migration {
+            version: 1,
+            description: "create_settings_table",
+            sql: "CREATE TABLE IF NOT EXISTS settings (id INTEGER PRIMARY KEY AUTOINCREMENT, key TEXT UNIQUE, value TEXT);",
+            kind: tauri_plugin_sql::MigrationKind::Up,
+        },
+        tauri_plugin_sql::Migration {
+            version: 2,
+            description: "create_note_cache_tables",
+            sql: "CREATE TABLE IF NOT EXISTS notes (
+                    vault TEXT NOT NULL,
+                    relative_path TEXT NOT NULL,
+                    absolute_path TEXT NOT NULL,
+                    title TEXT NOT NULL,
+                    aliases TEXT NOT NULL DEFAULT '[]',
+                    frontmatter TEXT,
+                    mtime INTEGER NOT NULL,
+                    size INTEGER NOT NULL,
+                    hash TEXT NOT NULL,
+                    PRIMARY KEY (vault, relative_path)
+                );
+                CREATE TABLE IF NOT EXISTS note_links (
+                    vault TEXT NOT NULL,
+                    source_path TEXT NOT NULL,
+                    target TEXT NOT NULL,
+                    alias TEXT,
+                    embed INTEGER NOT NULL DEFAULT 0,
+                    line INTEGER NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS idx_note_links_source ON note_links (vault, source_path);
+                CREATE INDEX IF NOT EXISTS idx_note_links_target ON note_links (vault, target);
+                CREATE TABLE IF NOT EXISTS note_tags (
+                    vault TEXT NOT NULL,
+                    relative_path TEXT NOT NULL,
+                    tag TEXT NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS idx_note_tags_path ON note_tags (vault, relative_path);
+                CREATE INDEX IF NOT EXISTS idx_note_tags_tag ON note_tags (vault, tag);",
+            kind: tauri_plugin_sql::MigrationKind::Up,
+        },
+        tauri_plugin_sql::Migration {
+            version: 3,
+            description: "create_workspace_tables",
+            sql: "CREATE TABLE IF NOT EXISTS workspaces (
+                    vault TEXT PRIMARY KEY,
+                    state TEXT NOT NULL,
+                    updated_at INTEGER NOT NULL
+                );
+                CREATE TABLE IF NOT EXISTS recent_notes (
+                    vault TEXT NOT NULL,
+                    relative_path TEXT NOT NULL,
+                    opened_at INTEGER NOT NULL,
+                    open_count INTEGER NOT NULL DEFAULT 1,
+                    PRIMARY KEY (vault, relative_path)
+                );
+                CREATE INDEX IF NOT EXISTS idx_recent_notes_opened ON recent_notes (vault, opened_at);",
+            kind: tauri_plugin_sql::MigrationKind::Up,
+        },
+        tauri_plugin_sql::Migration {
+            version: 4,
+            description: "create_folder_states_table",
+            sql: "CREATE TABLE IF NOT EXISTS folder_states (
+                    vault TEXT NOT NULL,
+                    relative_path TEXT NOT NULL,
+                    collapsed INTEGER NOT NULL DEFAULT 0,
+                    position INTEGER,
+                    PRIMARY KEY (vault, relative_path)
+                );",
+            kind: tauri_plugin_sql::MigrationKind::Up,
+        },
+        tauri_plugin_sql::Migration {
+            version: 5,
+            description: "add_note_listing_columns",
+            // Clearing the file state makes the next sync re-parse every note
+            sql: "ALTER TABLE notes ADD COLUMN ctime INTEGER NOT NULL DEFAULT 0;
+                ALTER TABLE notes ADD COLUMN word_count INTEGER NOT NULL DEFAULT 0;
+                UPDATE notes SET mtime = 0, hash = '';
+                CREATE INDEX IF NOT EXISTS idx_notes_mtime ON notes (vault, mtime);",
+            kind: tauri_plugin_sql::MigrationKind::Up,
+        },
+        tauri_plugin_sql::Migration {
+            version: 6,
+            description: "add_note_stats_and_writing_activity",
+            sql: "ALTER TABLE notes ADD COLUMN char_count INTEGER NOT NULL DEFAULT 0;
+                ALTER TABLE notes ADD COLUMN heading_count INTEGER NOT NULL DEFAULT 0;
+                UPDATE notes SET mtime = 0, hash = '';
+                CREATE TABLE IF NOT EXISTS writing_activity (
+                    vault TEXT NOT NULL,
+                    day TEXT NOT NULL,
+                    relative_path TEXT NOT NULL,
+                    words_added INTEGER NOT NULL DEFAULT 0,
+                    words_removed INTEGER NOT NULL DEFAULT 0,
+                    saves INTEGER NOT NULL DEFAULT 0,
+                    PRIMARY KEY (vault, day, relative_path)
+                );",
+            kind: tauri_plugin_sql::MigrationKind::Up,
+        },
+        tauri_plugin_sql::Migration {
+            version: 7,
+            description: "create_flashcard_reviews_table",
+            sql: "CREATE TABLE IF NOT EXISTS flashcard_reviews (
+                    vault TEXT NOT NULL,
+                    card_id TEXT NOT NULL,
+                    relative_path TEXT NOT NULL,
+                    ease REAL NOT NULL,
+                    interval_days INTEGER NOT NULL,
+                    repetitions INTEGER NOT NULL,
+                    lapses INTEGER NOT NULL DEFAULT 0,
+                    due_at INTEGER NOT NULL,
+                    reviewed_at INTEGER,
+                    PRIMARY KEY (vault, card_id)
+                );
+                CREATE INDEX IF NOT EXISTS idx_flashcard_reviews_due ON flashcard_reviews (vault, due_at);",
+            kind: tauri_plugin_sql::MigrationKind::Up,
+        },
+        tauri_plugin_sql::Migration {
+            version: 8,
+            description: "create_note_ids_table",
+            // Re-parsing every note picks up IDs set in frontmatter
+            sql: "CREATE TABLE IF NOT EXISTS note_ids (
+                    vault TEXT NOT NULL,
+                    note_id TEXT NOT NULL,
+                    relative_path TEXT NOT NULL,
+                    PRIMARY KEY (vault, note_id)
+                );
+                CREATE UNIQUE INDEX IF NOT EXISTS idx_note_ids_path ON note_ids (vault, relative_path);
+                UPDATE notes SET mtime = 0, hash = '';",
+            kind: tauri_plugin_sql::MigrationKind::Up,
+        },
+        tauri_plugin_sql::Migration {
+            version: 9,
+            description: "create_vault_scans_table",
+            // Vaults with cached notes have been scanned before
+            sql: "CREATE TABLE IF NOT EXISTS vault_scans (
+                    vault TEXT PRIMARY KEY,
+                    scanned_at INTEGER NOT NULL
+                );
+                INSERT OR IGNORE INTO vault_scans (vault, scanned_at)
+                    SELECT DISTINCT vault, 0 FROM notes;",
+            kind: tauri_plugin_sql::MigrationKind::Up,
+        },
+    ]
+}
+
+/// Returns the connection pool the SQL plugin opened for the app database
+pub async fn pool<R: Runtime>(app: &AppHandle<R>) -> Result<Pool<Sqlite>, String> {
+    let instances = app.state::<DbInstances>();
+    let instances = instances.0.read().await;
+    match instances.get(DB_URL) {
+        Some(DbPool::Sqlite(pool)) => Ok(pool.clone()),
+        None => Err(format!("Database {} is not loaded", DB_URL)),
+    }
+}
+
+/// An in-memory database with every migration applied, for tests
+#[cfg(test)]
+pub(crate) async fn test_pool() -> Pool<Sqlite> {
+    let pool = sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    for migration in migrations() {
+        sqlx::raw_sql(migration.sql).execute(&pool).await.unwrap();
+    }
+    pool
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tauri::async_runtime::block_on;
+
+    #[test]
+    fn migration_versions_run_in_order() {
+        let versions: Vec<i64> = migrations().iter().map(|m| m.version).collect();
+        let expected: Vec<i64> = (1..=versions.len() as i64).collect();
+        assert_eq!(versions, expected);
+    }
+
+    #[test]
+    fn upgrades_a_database_with_cached_notes() {
+        block_on(async {
+            let pool = sqlx::sqlite::SqlitePoolOptions::new()
+                .max_connections(1)
+                .connect("sqlite::memory:")
+                .await
+                .unwrap();
+            let (old, new): (Vec<_>, Vec<_>) =
+                migrations().into_iter().partition(|m| m.version <= 4);
+            for migration in old {
+                sqlx::raw_sql(migration.sql).execute(&pool).await.unwrap();
+            }
+            sqlx::query(
+                "INSERT INTO notes (vault, relative_path, absolute_path, title, mtime, size, hash)
+                 VALUES ('/vault', 'a.md', '/vault/a.md', 'A', 123, 4, 'abc')",
+            )
+            .execute(&pool)
+            .await
+            .unwrap();
+            for migration in new {
+                sqlx::raw_sql(migration.sql).execute(&pool).await.unwrap();
+            }
+
+            // Old rows get the new columns and are marked for re-parsing
+            let (mtime, hash, ctime, words): (i64, String, i64, i64) = sqlx::query_as(
+                "SELECT mtime, hash, ctime, word_count FROM notes WHERE relative_path = 'a.md'",
+            )
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+            assert_eq!((mtime, hash.as_str(), ctime, words), (0, "", 0, 0));
+
+            let scanned: Vec<(String,)> = sqlx::query_as("SELECT vault FROM vault_scans")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+            assert_eq!(scanned, vec![("/vault".to_string(),)]);
+        });
+    }
+}
@@ -0,0 +1,323 @@
+// src/db/note_ids.rs
+use crate::notes::formats;
+use crate::notes::helpers;
+use sqlx::{Pool, Sqlite};
+use std::collections::HashMap;
+
+/// Reads a note ID set in frontmatter as `id:`. Numbers are taken as text.
+pub fn frontmatter_id(frontmatter: Option<&serde_json::Value>) -> Option<String> {
+    let id = match frontmatter?.get("id")? {
+        serde_json::Value::String(id) => id.trim().to_string(),
+        serde_json::Value::Number(id) => id.to_string(),
+        _ => return None,
+    };
+    (!id.is_empty()).then_some(id)
+}
+
+/// Writes a note ID into a note's metadata, as `id:` in markdown frontmatter
+/// or an `:ID:` property in org files, leaving the rest of the note as it
+/// is. Returns None for formats without metadata.
+pub fn with_frontmatter_id(relative_path: &str, content: &str, note_id: &str) -> Option<String> {
+    let first_line = content.split_once('\n');
+    if formats::is_markdown_path(relative_path) {
+        let has_frontmatter = helpers::extract_frontmatter_and_content(content)
+            .0
+            .is_some();
+        return Some(match first_line {
+            Some((first, rest)) if has_frontmatter && first.trim_end() == "---" => {
+                format!("{}\nid: {}\n{}", first, note_id, rest)
+            }
+            _ => format!("---\nid: {}\n---\n{}", note_id, content),
+        });
+    }
+    if !relative_path.to_lowercase().ends_with(".org") {
+        return None;
+    }
+    Some(match first_line {
+        Some((first, rest)) if first.trim().eq_ignore_ascii_case(":properties:") => {
+            format!("{}\n:ID: {}\n{}", first, note_id, rest)
+        }
+        _ => format!(":PROPERTIES:\n:ID: {}\n:END:\n{}", note_id, content),
+    })
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// Pairs notes that disappeared with new notes of the same content, as
+/// `(from, to)` paths. `removed` and `added` hold `(path, hash)` pairs.
+/// Among several notes with the content, the one with the same file name
+/// wins; a pair that stays ambiguous is left as a removal and an addition.
+pub fn detect_renames(
+    removed: &[(String, String)],
+    added: &[(String, String)],
+) -> Vec<(String, String)> {
+    let mut by_hash: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (path, hash) in removed {
+        if !hash.is_empty() {
+            by_hash.entry(hash).or_default().push(path);
+        }
+    }
+
+    let mut renames = Vec::new();
+    for (path, hash) in added {
+        let Some(candidates) = by_hash.get_mut(hash.as_str()) else {
+            continue;
+        };
+        let index = match candidates
+            .iter()
+            .position(|candidate| file_name(candidate) == file_name(path))
+        {
+            Some(index) => index,
+            None if candidates.len() == 1 => 0,
+            None => continue,
+        };
+        renames.push((candidates.remove(index).to_string(), path.clone()));
+    }
+    renames
+}
+
+/// Gives a note its ID: the frontmatter one if it has one, or else a new
+/// random one unless the note already has an ID. A frontmatter ID another
+/// note already holds stays with that note; the duplicate is reported and
+/// the note gets an ID of its own.
+pub(super) async fn assign(
+    transaction: &mut sqlx::Transaction<'_, Sqlite>,
+    vault_directory: &str,
+    relative_path: &str,
+    frontmatter_id: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    if let Some(note_id) = frontmatter_id {
+        let owner: Option<(String,)> =
+            sqlx::query_as("SELECT relative_path FROM note_ids WHERE vault = ? AND note_id = ?")
+                .bind(vault_directory)
+                .bind(note_id)
+                .fetch_optional(&mut **transaction)
+                .await?;
+        match owner {
+            Some((owner,)) if owner == relative_path => return Ok(()),
+            Some((owner,)) => {
+                println!(
+                    "Note ID '{}' of {} is already used by {}, keeping it there",
+                    note_id, relative_path, owner
+                );
+            }
+            None => {
+                sqlx::query("DELETE FROM note_ids WHERE vault = ? AND relative_path = ?")
+                    .bind(vault_directory)
+                    .bind(relative_path)
+                    .execute(&mut **transaction)
+                    .await?;
+                sqlx::query(
+                    "INSERT INTO note_ids (vault, note_id, relative_path) VALUES (?, ?, ?)",
+                )
+                .bind(vault_directory)
+                .bind(note_id)
+                .bind(relative_path)
+                .execute(&mut **transaction)
+                .await?;
+                return Ok(());
+            }
+        }
+    }
+
+    sqlx::query(
+        "INSERT OR IGNORE INTO note_ids (vault, note_id, relative_path)
+         VALUES (?, lower(hex(randomblob(8))), ?)",
+    )
+    .bind(vault_directory)
+    .bind(relative_path)
+    .execute(&mut **transaction)
+    .await?;
+    Ok(())
+}
+
+/// Forgets the ID of a note that was deleted
+pub(super) async fn remove(
+    transaction: &mut sqlx::Transaction<'_, Sqlite>,
+    vault_directory: &str,
+    relative_path: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM note_ids WHERE vault = ? AND relative_path = ?")
+        .bind(vault_directory)
+        .bind(relative_path)
+        .execute(&mut **transaction)
+        .await?;
+    Ok(())
+}
+
+/// Returns the ID of the note at a path, if the note has been cached
+pub async fn id_for_path(
+    pool: &Pool<Sqlite>,
+    vault_directory: &str,
+    relative_path: &str,
+) -> Result<Option<String>, String> {
+    let row: Option<(String,)> =
+        sqlx::query_as("SELECT note_id FROM note_ids WHERE vault = ? AND relative_path = ?")
+            .bind(vault_directory)
+            .bind(relative_path)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| format!("Failed to read note ID: {}", e))?;
+    Ok(row.map(|(note_id,)| note_id))
+}
+
+/// Returns the current path of the note with an ID
+pub async fn path_for_id(
+    pool: &Pool<Sqlite>,
+    vault_directory: &str,
+    note_id: &str,
+) -> Result<Option<String>, String> {
+    let row: Option<(String,)> =
+        sqlx::query_as("SELECT relative_path FROM note_ids WHERE vault = ? AND note_id = ?")
+            .bind(vault_directory)
+            .bind(note_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| format!("Failed to resolve note ID: {}", e))?;
+    Ok(row.map(|(relative_path,)| relative_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_pool;
+    use serde_json::json;
+    use tauri::async_runtime::block_on;
+
+    fn pairs(items: &[(&str, &str)]) -> Vec<(String, String)> {
+        items
+            .iter()
+            .map(|(path, hash)| (path.to_string(), hash.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn reads_frontmatter_ids() {
+        assert_eq!(
+            frontmatter_id(Some(&json!({"id": " abc "}))).as_deref(),
+            Some("abc")
+        );
+        assert_eq!(
+            frontmatter_id(Some(&json!({"id": 42}))).as_deref(),
+            Some("42")
+        );
+        assert_eq!(frontmatter_id(Some(&json!({"id": ""}))), None);
+        assert_eq!(frontmatter_id(Some(&json!({"id": ["x"]}))), None);
+        assert_eq!(frontmatter_id(None), None);
+    }
+
+    #[test]
+    fn writes_ids_into_note_metadata() {
+        assert_eq!(
+            with_frontmatter_id("a.md", "---\ntitle: A\n---\nBody", "x1").as_deref(),
+            Some("---\nid: x1\ntitle: A\n---\nBody")
+        );
+        assert_eq!(
+            with_frontmatter_id("a.md", "Body", "x1").as_deref(),
+            Some("---\nid: x1\n---\nBody")
+        );
+        assert_eq!(
+            with_frontmatter_id("a.org", ":PROPERTIES:\n:END:\n#+TITLE: A", "x1").as_deref(),
+            Some(":PROPERTIES:\n:ID: x1\n:END:\n#+TITLE: A")
+        );
+        assert_eq!(
+            with_frontmatter_id("a.org", "#+TITLE: A", "x1").as_deref(),
+            Some(":PROPERTIES:\n:ID: x1\n:END:\n#+TITLE: A")
+        );
+        assert_eq!(with_frontmatter_id("a.txt", "Body", "x1"), None);
+
+        let content = with_frontmatter_id("a.org", "#+TITLE: A", "x1").unwrap();
+        let note = helpers::parse_note("a.org", &content);
+        assert_eq!(
+            frontmatter_id(note.frontmatter.as_ref()).as_deref(),
+            Some("x1")
+        );
+        assert_eq!(note.title, "A");
+    }
+
+    #[test]
+    fn pairs_renames_by_content() {
+        let removed = pairs(&[("old/a.md", "h1"), ("b.md", "h2"), ("empty.md", "")]);
+        let added = pairs(&[("new/a.md", "h1"), ("c.md", "h3"), ("other.md", "")]);
+        assert_eq!(
+            detect_renames(&removed, &added),
+            vec![("old/a.md".to_string(), "new/a.md".to_string())]
+        );
+    }
+
+    #[test]
+    fn same_content_renames_prefer_the_same_file_name() {
+        let removed = pairs(&[("x/one.md", "h"), ("x/two.md", "h")]);
+        let added = pairs(&[("y/two.md", "h"), ("y/three.md", "h")]);
+        assert_eq!(
+            detect_renames(&removed, &added),
+            vec![
+                ("x/two.md".to_string(), "y/two.md".to_string()),
+                ("x/one.md".to_string(), "y/three.md".to_string()),
+            ]
+        );
+
+        let removed = pairs(&[("x/one.md", "h"), ("x/two.md", "h")]);
+        let added = pairs(&[("y/three.md", "h")]);
+        assert!(detect_renames(&removed, &added).is_empty());
+    }
+
+    #[test]
+    fn duplicate_frontmatter_ids_stay_with_the_first_note() {
+        block_on(async {
+            let pool = test_pool().await;
+            let mut transaction = pool.begin().await.unwrap();
+            assign(&mut transaction, "v", "a.md", Some("shared"))
+                .await
+                .unwrap();
+            assign(&mut transaction, "v", "b.md", Some("shared"))
+                .await
+                .unwrap();
+            assign(&mut transaction, "v", "a.md", Some("shared"))
+                .await
+                .unwrap();
+            assign(&mut transaction, "v", "b.md", Some("shared"))
+                .await
+                .unwrap();
+            transaction.commit().await.unwrap();
+
+            assert_eq!(
+                path_for_id(&pool, "v", "shared").await.unwrap().as_deref(),
+                Some("a.md")
+            );
+            let own = id_for_path(&pool, "v", "b.md").await.unwrap().unwrap();
+            assert_ne!(own, "shared");
+
+            // Once the first note lets go of the ID the other can take it
+            let mut transaction = pool.begin().await.unwrap();
+            remove(&mut transaction, "v", "a.md").await.unwrap();
+            assign(&mut transaction, "v", "b.md", Some("shared"))
+                .await
+                .unwrap();
+            transaction.commit().await.unwrap();
+            assert_eq!(
+                path_for_id(&pool, "v", "shared").await.unwrap().as_deref(),
+                Some("b.md")
+            );
+            assert_eq!(path_for_id(&pool, "v", &own).await.unwrap(), None);
+        });
+    }
+
+    #[test]
+    fn random_ids_are_kept_across_assignments() {
+        block_on(async {
+            let pool = test_pool().await;
+            let mut transaction = pool.begin().await.unwrap();
+            assign(&mut transaction, "v", "a.md", None).await.unwrap();
+            transaction.commit().await.unwrap();
+            let first = id_for_path(&pool, "v", "a.md").await.unwrap().unwrap();
+
+            let mut transaction = pool.begin().await.unwrap();
+            assign(&mut transaction, "v", "a.md", None).await.unwrap();
+            transaction.commit().await.unwrap();
+            assert_eq!(id_for_path(&pool, "v", "a.md").await.unwrap(), Some(first));
+        });
+    }
+}
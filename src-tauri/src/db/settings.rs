@@ -0,0 +1,753 @@
+// src/db/settings.rs
+use crate::notes::attachments::DEFAULT_ATTACHMENTS_FOLDER;
+use crate::notes::{formats, scan};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sqlx::{Pool, Sqlite};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Event emitted with the new settings whenever they change
+pub const SETTINGS_CHANGED_EVENT: &str = "settings-changed";
+
+/// Event emitted with the effective settings when a vault's overrides change
+pub const VAULT_SETTINGS_CHANGED_EVENT: &str = "vault-settings-changed";
+
+/// Current layout of stored settings, in the `settings` table and in vault
+/// config files alike. Version 1 is the untyped layout from before, which
+/// only stored `vault_path`, as typed in the settings dialog.
+pub const SETTINGS_VERSION: u32 = 2;
+
+/// Per-vault overrides, relative to the vault root. Kept inside the vault so
+/// they travel with it when it's synced.
+pub const VAULT_CONFIG_PATH: &str = ".notemancy/config";
+
+/// Row in the `settings` table holding the layout version
+const VERSION_KEY: &str = "settings_version";
+
+/// Settings that only make sense globally
+const GLOBAL_ONLY: &[&str] = &["vault_path"];
+
+/// Colour scheme of the app
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    Light,
+    Dark,
+    #[default]
+    System,
+}
+
+/// Editor preferences
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct EditorSettings {
+    pub font_size: u32,
+    pub tab_size: u32,
+    pub line_wrap: bool,
+    pub spell_check: bool,
+    pub vim_mode: bool,
+}
+
+impl Default for EditorSettings {
+    fn default() -> Self {
+        EditorSettings {
+            font_size: 16,
+            tab_size: 4,
+            line_wrap: true,
+            spell_check: false,
+            vim_mode: false,
+        }
+    }
+}
+
+/// Which files vault scanning reads as notes, and what it skips besides
+/// `.notemancyignore`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ScanSettings {
+    pub include_hidden: bool,
+    /// Extra ignore patterns in `.gitignore` syntax
+    pub ignore_patterns: Vec<String>,
+    /// Extensions of the files scanned as notes, without the dot
+    pub note_extensions: Vec<String>,
+}
+
+impl Default for ScanSettings {
+    fn default() -> Self {
+        ScanSettings {
+            include_hidden: false,
+            ignore_patterns: vec!["node_modules/".to_string()],
+            note_extensions: formats::DEFAULT_NOTE_EXTENSIONS
+                .iter()
+                .map(|ext| ext.to_string())
+                .collect(),
+        }
+    }
+}
+
+/// Encrypted notes
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct EncryptionSettings {
+    /// Minutes an unlocked vault key stays in memory without being used
+    pub lock_timeout_minutes: u32,
+}
+
+impl Default for EncryptionSettings {
+    fn default() -> Self {
+        EncryptionSettings {
+            lock_timeout_minutes: 15,
+        }
+    }
+}
+
+/// App settings. Missing values fall back to their defaults.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    pub vault_path: String,
+    pub theme: Theme,
+    pub editor: EditorSettings,
+    pub attachment_folder: String,
+    pub daily_note_format: String,
+    pub scan: ScanSettings,
+    pub encryption: EncryptionSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            vault_path: String::new(),
+            theme: Theme::default(),
+            editor: EditorSettings::default(),
+            attachment_folder: DEFAULT_ATTACHMENTS_FOLDER.to_string(),
+            daily_note_format: "%Y-%m-%d".to_string(),
+            scan: ScanSettings::default(),
+            encryption: EncryptionSettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Checks values the type system can't
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.vault_path.is_empty() && !Path::new(&self.vault_path).is_absolute() {
+            return Err("Vault path must be an absolute path".to_string());
+        }
+
+        if !(8..=48).contains(&self.editor.font_size) {
+            return Err("Editor font size must be between 8 and 48".to_string());
+        }
+        if !(1..=8).contains(&self.editor.tab_size) {
+            return Err("Editor tab size must be between 1 and 8".to_string());
+        }
+
+        let folder = self.attachment_folder.trim();
+        if folder.is_empty()
+            || Path::new(folder).is_absolute()
+            || folder.split(['/', '\\']).any(|segment| segment == "..")
+        {
+            return Err("Attachment folder must be a folder inside the vault".to_string());
+        }
+
+        let format = self.daily_note_format.trim();
+        let invalid_format = chrono::format::StrftimeItems::new(format)
+            .any(|item| matches!(item, chrono::format::Item::Error));
+        if format.is_empty() || invalid_format {
+            return Err(format!(
+                "Invalid daily note format: '{}'",
+                self.daily_note_format
+            ));
+        }
+
+        scan::check_patterns(&self.scan.ignore_patterns)?;
+        scan::check_extensions(&self.scan.note_extensions)?;
+
+        if !(1..=24 * 60).contains(&self.encryption.lock_timeout_minutes) {
+            return Err("Encryption lock timeout must be between 1 and 1440 minutes".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// Flattens a tree into its leaf values under dotted keys (`editor.font_size`)
+fn flatten(prefix: &str, value: &JsonValue, leaves: &mut Vec<(String, JsonValue)>) {
+    match value {
+        JsonValue::Object(map) => {
+            for (key, child) in map {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten(&key, child, leaves);
+            }
+        }
+        other => leaves.push((prefix.to_string(), other.clone())),
+    }
+}
+
+/// Text stored in the `settings` table for a value. Strings are stored as-is
+/// so existing rows like `vault_path` keep working.
+fn stored_text(value: &JsonValue) -> String {
+    match value {
+        JsonValue::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Finds a dotted key in a tree
+fn lookup_mut<'a>(tree: &'a mut JsonValue, key: &str) -> Option<&'a mut JsonValue> {
+    let mut node = tree;
+    for segment in key.split('.') {
+        node = node.get_mut(segment)?;
+    }
+    Some(node)
+}
+
+/// Removes a dotted key from a tree
+fn take_path(tree: &mut JsonValue, key: &str) -> Option<JsonValue> {
+    let (parent, last) = match key.rsplit_once('.') {
+        Some((parent, last)) => (lookup_mut(tree, parent)?, last),
+        None => (tree, key),
+    };
+    parent.as_object_mut()?.remove(last)
+}
+
+/// Drops `null` values, which mark overrides to remove
+fn remove_nulls(tree: &mut JsonValue) {
+    if let JsonValue::Object(map) = tree {
+        map.retain(|_, value| !value.is_null());
+        for value in map.values_mut() {
+            remove_nulls(value);
+        }
+        map.retain(|_, value| !matches!(value, JsonValue::Object(child) if child.is_empty()));
+    }
+}
+
+/// Sets a dotted key in the defaults tree, using the default's type to
+/// decide how the stored text is read. Unknown keys are ignored.
+fn set_stored(tree: &mut JsonValue, key: &str, stored: &str) {
+    let Some(node) = lookup_mut(tree, key) else {
+        return;
+    };
+
+    *node = match node {
+        JsonValue::String(_) => JsonValue::String(stored.to_string()),
+        _ => match serde_json::from_str(stored) {
+            Ok(value) => value,
+            Err(_) => return,
+        },
+    };
+}
+
+/// Rejects keys the schema doesn't have, so typos don't go unnoticed
+fn check_keys(schema: &JsonValue, changes: &JsonValue, prefix: &str) -> Result<(), String> {
+    let (Some(schema), Some(changes)) = (schema.as_object(), changes.as_object()) else {
+        return Ok(());
+    };
+    for (key, value) in changes {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        match schema.get(key) {
+            Some(child) => check_keys(child, value, &path)?,
+            None => return Err(format!("Unknown setting: {}", path)),
+        }
+    }
+    Ok(())
+}
+
+/// Merges `changes` into `target`, recursing into objects
+fn merge(target: &mut JsonValue, changes: &JsonValue) {
+    match (target, changes) {
+        (JsonValue::Object(target), JsonValue::Object(changes)) => {
+            for (key, value) in changes {
+                match target.get_mut(key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        target.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (target, changes) => *target = changes.clone(),
+    }
+}
+
+/// The version 2 form of a version 1 vault path. Version 1 stored whatever
+/// was typed, so the path may be relative to the folder the app ran in, be
+/// padded with spaces or end in a separator. Version 2 only takes absolute
+/// paths; a path that can't be made absolute is dropped.
+fn upgrade_vault_path(stored: &str) -> Option<String> {
+    let path = Path::new(stored.trim());
+    if path.as_os_str().is_empty() {
+        return None;
+    }
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        fs::canonicalize(path).ok()?
+    };
+    let normalized: PathBuf = absolute.components().collect();
+    Some(normalized.to_string_lossy().to_string())
+}
+
+/// Upgrades version 1 rows to the current layout
+async fn upgrade_stored(pool: &Pool<Sqlite>) -> Result<(), String> {
+    let vault_path: Option<(Option<String>,)> =
+        sqlx::query_as("SELECT value FROM settings WHERE key = 'vault_path'")
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| format!("Failed to read settings: {}", e))?;
+    let upgraded = vault_path
+        .and_then(|(value,)| value)
+        .map(|value| upgrade_vault_path(&value));
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to upgrade settings: {}", e))?;
+    match upgraded {
+        Some(Some(path)) => {
+            sqlx::query("UPDATE settings SET value = ? WHERE key = 'vault_path'")
+                .bind(path)
+                .execute(&mut *transaction)
+                .await
+        }
+        Some(None) => {
+            println!("Dropping a stored vault path that isn't absolute");
+            sqlx::query("DELETE FROM settings WHERE key = 'vault_path'")
+                .execute(&mut *transaction)
+                .await
+        }
+        None => Ok(Default::default()),
+    }
+    .map_err(|e| format!("Failed to upgrade settings: {}", e))?;
+    sqlx::query("INSERT OR REPLACE INTO settings (key, value) VALUES (?, ?)")
+        .bind(VERSION_KEY)
+        .bind(SETTINGS_VERSION.to_string())
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| format!("Failed to upgrade settings: {}", e))?;
+    transaction
+        .commit()
+        .await
+        .map_err(|e| format!("Failed to upgrade settings: {}", e))
+}
+
+/// Checks the layout of the stored settings and upgrades it, then loads
+/// them. Run once at startup, before anything else reads the settings.
+pub async fn init_settings(pool: &Pool<Sqlite>) -> Result<Settings, String> {
+    let stored: Option<(Option<String>,)> =
+        sqlx::query_as("SELECT value FROM settings WHERE key = ?")
+            .bind(VERSION_KEY)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| format!("Failed to read settings: {}", e))?;
+    let version = stored
+        .and_then(|(value,)| value)
+        .and_then(|value| value.parse::<u32>().ok())
+        .unwrap_or(1);
+    if version > SETTINGS_VERSION {
+        return Err("Settings were written by a newer version of the app".to_string());
+    }
+
+    if version < SETTINGS_VERSION {
+        upgrade_stored(pool).await?;
+    }
+    load_settings(pool).await
+}
+
+/// Reads the settings, filling in defaults for anything not stored
+pub async fn load_settings(pool: &Pool<Sqlite>) -> Result<Settings, String> {
+    let rows: Vec<(String, Option<String>)> = sqlx::query_as("SELECT key, value FROM settings")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to read settings: {}", e))?;
+
+    let mut tree = serde_json::to_value(Settings::default()).map_err(|e| e.to_string())?;
+    for (key, value) in rows {
+        let Some(value) = value else {
+            continue;
+        };
+
+        // A stored value that no longer fits the schema keeps its default
+        let mut candidate = tree.clone();
+        set_stored(&mut candidate, &key, &value);
+        if serde_json::from_value::<Settings>(candidate.clone()).is_ok() {
+            tree = candidate;
+        }
+    }
+
+    let settings: Settings =
+        serde_json::from_value(tree).map_err(|e| format!("Invalid settings: {}", e))?;
+    scan::set_global_settings(&settings.scan);
+    Ok(settings)
+}
+
+/// Applies a partial update (`{"editor": {"font_size": 14}}`), validates the
+/// result and stores it. Returns the new settings.
+pub async fn update_settings(pool: &Pool<Sqlite>, changes: &JsonValue) -> Result<Settings, String> {
+    if !changes.is_object() {
+        return Err("Settings changes must be an object".to_string());
+    }
+
+    let current = load_settings(pool).await?;
+    let mut tree = serde_json::to_value(&current).map_err(|e| e.to_string())?;
+    check_keys(&tree, changes, "")?;
+    merge(&mut tree, changes);
+
+    let updated: Settings =
+        serde_json::from_value(tree).map_err(|e| format!("Invalid settings: {}", e))?;
+    updated.validate()?;
+
+    let mut rows = Vec::new();
+    flatten(
+        "",
+        &serde_json::to_value(&updated).map_err(|e| e.to_string())?,
+        &mut rows,
+    );
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to save settings: {}", e))?;
+    for (key, value) in rows {
+        sqlx::query("INSERT OR REPLACE INTO settings (key, value) VALUES (?, ?)")
+            .bind(key)
+            .bind(stored_text(&value))
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| format!("Failed to save settings: {}", e))?;
+    }
+    transaction
+        .commit()
+        .await
+        .map_err(|e| format!("Failed to save settings: {}", e))?;
+
+    scan::set_global_settings(&updated.scan);
+    Ok(updated)
+}
+
+/// Settings as they apply to one vault: the global settings with the vault's
+/// overrides on top
+#[derive(Debug, Serialize)]
+pub struct EffectiveSettings {
+    pub version: u32,
+    pub settings: Settings,
+    /// Dotted keys whose value comes from the vault config
+    pub overridden: Vec<String>,
+    /// Overrides that were left out, with the reason
+    pub ignored: Vec<String>,
+    /// The vault config file, if the vault has one
+    pub config_path: Option<String>,
+}
+
+fn vault_config_path(vault_directory: &str) -> PathBuf {
+    Path::new(vault_directory).join(VAULT_CONFIG_PATH)
+}
+
+/// Reads a vault's overrides. A vault without a config file has no
+/// overrides.
+pub fn load_vault_overrides(vault_directory: &str) -> Result<JsonValue, String> {
+    let content = match fs::read_to_string(vault_config_path(vault_directory)) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Ok(JsonValue::Object(Default::default()))
+        }
+        Err(e) => return Err(format!("Failed to read {}: {}", VAULT_CONFIG_PATH, e)),
+    };
+
+    let mut config: JsonValue = serde_json::from_str(&content)
+        .map_err(|e| format!("Invalid {}: {}", VAULT_CONFIG_PATH, e))?;
+    let Some(map) = config.as_object_mut() else {
+        return Err(format!("{} must contain a JSON object", VAULT_CONFIG_PATH));
+    };
+
+    let version = map
+        .remove("version")
+        .and_then(|version| version.as_u64())
+        .unwrap_or(1) as u32;
+    if version > SETTINGS_VERSION {
+        return Err(format!(
+            "{} was written by a newer version of the app",
+            VAULT_CONFIG_PATH
+        ));
+    }
+
+    for key in GLOBAL_ONLY {
+        take_path(&mut config, key);
+    }
+
+    Ok(config)
+}
+
+/// The scan settings of a vault: the global ones with the vault's overrides
+/// on top. Unusable overrides are ignored.
+pub fn vault_scan_settings(vault_directory: &str, global: &ScanSettings) -> ScanSettings {
+    let overrides = load_vault_overrides(vault_directory)
+        .ok()
+        .and_then(|mut config| take_path(&mut config, "scan"));
+    let (Some(overrides), Ok(mut tree)) = (overrides, serde_json::to_value(global)) else {
+        return global.clone();
+    };
+
+    merge(&mut tree, &overrides);
+    serde_json::from_value::<ScanSettings>(tree)
+        .ok()
+        .filter(|scan| {
+            scan::check_patterns(&scan.ignore_patterns).is_ok()
+                && scan::check_extensions(&scan.note_extensions).is_ok()
+        })
+        .unwrap_or_else(|| global.clone())
+}
+
+/// Merges a vault's overrides into the global settings. Overrides that are
+/// unknown or invalid are skipped one by one rather than failing the vault.
+pub async fn effective_settings(
+    pool: &Pool<Sqlite>,
+    vault_directory: &str,
+) -> Result<EffectiveSettings, String> {
+    let global = load_settings(pool).await?;
+    let overrides = load_vault_overrides(vault_directory)?;
+
+    let mut leaves = Vec::new();
+    flatten("", &overrides, &mut leaves);
+
+    let mut tree = serde_json::to_value(&global).map_err(|e| e.to_string())?;
+    let mut overridden = Vec::new();
+    let mut ignored = Vec::new();
+    for (key, value) in leaves {
+        let mut candidate = tree.clone();
+        match lookup_mut(&mut candidate, &key) {
+            Some(node) => *node = value,
+            None => {
+                ignored.push(format!("{}: unknown setting", key));
+                continue;
+            }
+        }
+
+        let checked = serde_json::from_value::<Settings>(candidate.clone())
+            .map_err(|e| e.to_string())
+            .and_then(|settings| settings.validate());
+        match checked {
+            Ok(()) => {
+                tree = candidate;
+                overridden.push(key);
+            }
+            Err(e) => ignored.push(format!("{}: {}", key, e)),
+        }
+    }
+
+    let config_path = vault_config_path(vault_directory);
+    Ok(EffectiveSettings {
+        version: SETTINGS_VERSION,
+        settings: serde_json::from_value(tree).map_err(|e| format!("Invalid settings: {}", e))?,
+        overridden,
+        ignored,
+        config_path: config_path
+            .exists()
+            .then(|| config_path.to_string_lossy().to_string()),
+    })
+}
+
+/// Applies a partial update to a vault's overrides and writes the config
+/// file. A `null` value removes an override so the global value applies
+/// again.
+pub fn update_vault_overrides(vault_directory: &str, changes: &JsonValue) -> Result<(), String> {
+    if !changes.is_object() {
+        return Err("Settings changes must be an object".to_string());
+    }
+
+    let schema = serde_json::to_value(Settings::default()).map_err(|e| e.to_string())?;
+    check_keys(&schema, changes, "")?;
+    if let Some(key) = GLOBAL_ONLY.iter().find(|key| changes.get(**key).is_some()) {
+        return Err(format!("{} can't be overridden per vault", key));
+    }
+
+    let mut config = load_vault_overrides(vault_directory)?;
+    merge(&mut config, changes);
+    remove_nulls(&mut config);
+
+    // The overrides have to make sense on their own, whatever the globals are
+    let mut check = schema;
+    merge(&mut check, &config);
+    let checked: Settings =
+        serde_json::from_value(check).map_err(|e| format!("Invalid settings: {}", e))?;
+    checked.validate()?;
+
+    let mut file = serde_json::Map::new();
+    file.insert("version".to_string(), JsonValue::from(SETTINGS_VERSION));
+    if let JsonValue::Object(map) = config {
+        file.extend(map);
+    }
+    let content =
+        serde_json::to_string_pretty(&JsonValue::Object(file)).map_err(|e| e.to_string())?;
+
+    let path = vault_config_path(vault_directory);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    fs::write(&path, content + "\n")
+        .map_err(|e| format!("Failed to write {}: {}", VAULT_CONFIG_PATH, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_pool;
+    use serde_json::json;
+    use tauri::async_runtime::block_on;
+
+    async fn stored(pool: &Pool<Sqlite>, key: &str) -> Option<String> {
+        sqlx::query_as::<_, (String,)>("SELECT value FROM settings WHERE key = ?")
+            .bind(key)
+            .fetch_optional(pool)
+            .await
+            .unwrap()
+            .map(|(value,)| value)
+    }
+
+    #[test]
+    fn reads_the_version_one_layout() {
+        block_on(async {
+            let pool = test_pool().await;
+            sqlx::query("INSERT INTO settings (key, value) VALUES ('vault_path', '/notes')")
+                .execute(&pool)
+                .await
+                .unwrap();
+
+            let settings = init_settings(&pool).await.unwrap();
+            assert_eq!(settings.vault_path, "/notes");
+            assert_eq!(settings.editor, EditorSettings::default());
+            assert_eq!(stored(&pool, VERSION_KEY).await.as_deref(), Some("2"));
+        });
+    }
+
+    #[test]
+    fn upgrades_version_one_vault_paths() {
+        let vault = tempfile::tempdir().unwrap();
+        let canonical = fs::canonicalize(vault.path()).unwrap();
+        let typed = format!(" {}/ ", canonical.display());
+        assert_eq!(
+            upgrade_vault_path(&typed).as_deref(),
+            Some(canonical.to_str().unwrap())
+        );
+        assert_eq!(upgrade_vault_path("   "), None);
+        assert_eq!(upgrade_vault_path("no/such/folder"), None);
+
+        block_on(async {
+            let pool = test_pool().await;
+            sqlx::query("INSERT INTO settings (key, value) VALUES ('vault_path', ?)")
+                .bind(&typed)
+                .execute(&pool)
+                .await
+                .unwrap();
+            let settings = init_settings(&pool).await.unwrap();
+            assert_eq!(settings.vault_path, canonical.to_str().unwrap());
+            // Upgraded settings take updates again
+            update_settings(&pool, &json!({"theme": "dark"}))
+                .await
+                .unwrap();
+
+            let pool = test_pool().await;
+            sqlx::query(
+                "INSERT INTO settings (key, value) VALUES ('vault_path', 'no/such/folder')",
+            )
+            .execute(&pool)
+            .await
+            .unwrap();
+            assert_eq!(init_settings(&pool).await.unwrap().vault_path, "");
+            assert_eq!(stored(&pool, "vault_path").await, None);
+        });
+    }
+
+    #[test]
+    fn loading_settings_writes_nothing() {
+        block_on(async {
+            let pool = test_pool().await;
+            load_settings(&pool).await.unwrap();
+            assert_eq!(stored(&pool, VERSION_KEY).await, None);
+        });
+    }
+
+    #[test]
+    fn refuses_settings_from_a_newer_version() {
+        block_on(async {
+            let pool = test_pool().await;
+            sqlx::query("INSERT INTO settings (key, value) VALUES (?, '99')")
+                .bind(VERSION_KEY)
+                .execute(&pool)
+                .await
+                .unwrap();
+            assert!(init_settings(&pool).await.is_err());
+        });
+    }
+
+    #[test]
+    fn updates_are_validated_and_stored_by_dotted_key() {
+        block_on(async {
+            let pool = test_pool().await;
+            let updated = update_settings(&pool, &json!({"editor": {"font_size": 20}}))
+                .await
+                .unwrap();
+            assert_eq!(updated.editor.font_size, 20);
+            assert_eq!(
+                stored(&pool, "editor.font_size").await.as_deref(),
+                Some("20")
+            );
+            assert_eq!(load_settings(&pool).await.unwrap(), updated);
+
+            assert!(
+                update_settings(&pool, &json!({"editor": {"font_size": 99}}))
+                    .await
+                    .is_err()
+            );
+            assert!(update_settings(&pool, &json!({"editr": {}})).await.is_err());
+            assert!(
+                update_settings(&pool, &json!({"attachment_folder": "a\\..\\..\\b"}))
+                    .await
+                    .is_err()
+            );
+        });
+    }
+
+    #[test]
+    fn vault_overrides_apply_on_top_of_the_globals() {
+        block_on(async {
+            let pool = test_pool().await;
+            let vault = tempfile::tempdir().unwrap();
+            let vault_directory = vault.path().to_string_lossy();
+
+            update_vault_overrides(&vault_directory, &json!({"editor": {"tab_size": 2}})).unwrap();
+            assert!(
+                update_vault_overrides(&vault_directory, &json!({"vault_path": "/x"})).is_err()
+            );
+            let effective = effective_settings(&pool, &vault_directory).await.unwrap();
+            assert_eq!(effective.overridden, vec!["editor.tab_size"]);
+            update_vault_overrides(&vault_directory, &json!({"editor": {"tab_size": null}}))
+                .unwrap();
+            let effective = effective_settings(&pool, &vault_directory).await.unwrap();
+            assert!(effective.overridden.is_empty());
+
+            fs::write(
+                vault_config_path(&vault_directory),
+                r#"{"version": 2, "editor": {"tab_size": 2, "font_size": 1}, "nope": 1}"#,
+            )
+            .unwrap();
+
+            let effective = effective_settings(&pool, &vault_directory).await.unwrap();
+            assert_eq!(effective.settings.editor.tab_size, 2);
+            assert_eq!(effective.settings.editor.font_size, 16);
+            assert_eq!(effective.overridden, vec!["editor.tab_size"]);
+            assert_eq!(effective.ignored.len(), 2);
+        });
+    }
+}
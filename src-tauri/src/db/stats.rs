@@ -0,0 +1,345 @@
+// src/db/stats.rs
+use crate::notes::encryption;
+use crate::notes::formats;
+use crate::notes::helpers;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+
+/// Reading speed used for reading time estimates
+pub const READING_WORDS_PER_MINUTE: i64 = 200;
+
+/// Counts the words of a note. Words are runs with at least one letter or
+/// digit, so list markers and heading hashes don't count.
+pub fn count_words(text: &str) -> i64 {
+    text.split_whitespace()
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .count() as i64
+}
+
+/// Minutes it takes to read some number of words, rounded up
+pub fn reading_minutes(words: i64) -> i64 {
+    (words + READING_WORDS_PER_MINUTE - 1) / READING_WORDS_PER_MINUTE
+}
+
+/// Statistics of a single note, read from the file as it is now
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteStats {
+    pub relative_path: String,
+    pub words: i64,
+    pub characters: i64,
+    /// Characters other than whitespace
+    pub characters_no_spaces: i64,
+    pub reading_minutes: i64,
+    pub headings: i64,
+    /// Links from this note to others
+    pub outgoing_links: i64,
+    /// Notes linking to this one
+    pub incoming_links: i64,
+}
+
+/// Totals over every cached note of a vault
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultStats {
+    pub notes: i64,
+    pub words: i64,
+    pub characters: i64,
+    pub reading_minutes: i64,
+    pub headings: i64,
+    pub links: i64,
+    pub tags: i64,
+    /// Bytes on disk
+    pub size: i64,
+}
+
+/// What was written on one day, from the saves made through the app
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WritingDay {
+    /// Local date as `YYYY-MM-DD`
+    pub day: String,
+    pub words_added: i64,
+    pub words_removed: i64,
+    pub saves: i64,
+    pub notes_edited: i64,
+}
+
+/// Computes the statistics of a note. Frontmatter doesn't count towards
+/// words or characters, and neither does an encrypted body.
+pub fn note_stats(relative_path: &str, vault_directory: &str) -> Result<NoteStats, String> {
+    let path = helpers::resolve_note_path(None, Some(relative_path), Some(vault_directory))
+        .map_err(|e| format!("Failed to read note: {}", e))?;
+    let content =
+        helpers::read_file_content(&path).map_err(|e| format!("Failed to read note: {}", e))?;
+
+    let format = formats::format_for_path(&path);
+    let (_, body) = format.split(&content);
+    let body = if encryption::is_encrypted(&body) {
+        String::new()
+    } else {
+        body
+    };
+    let parsed = format.parse(&body);
+    let words = count_words(&body);
+
+    Ok(NoteStats {
+        relative_path: relative_path.to_string(),
+        words,
+        characters: body.chars().count() as i64,
+        characters_no_spaces: body.chars().filter(|c| !c.is_whitespace()).count() as i64,
+        reading_minutes: reading_minutes(words),
+        headings: parsed.headings as i64,
+        outgoing_links: parsed.links.len() as i64,
+        incoming_links: helpers::find_backlinks(relative_path, vault_directory).len() as i64,
+    })
+}
+
+/// Adds up the cached statistics of a vault's notes
+pub async fn vault_stats(pool: &Pool<Sqlite>, vault_directory: &str) -> Result<VaultStats, String> {
+    let (notes, words, characters, headings, size): (i64, i64, i64, i64, i64) = sqlx::query_as(
+        "SELECT COUNT(*), COALESCE(SUM(word_count), 0), COALESCE(SUM(char_count), 0),
+                COALESCE(SUM(heading_count), 0), COALESCE(SUM(size), 0)
+         FROM notes WHERE vault = ?",
+    )
+    .bind(vault_directory)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Failed to read vault stats: {}", e))?;
+
+    let (links, tags): (i64, i64) = sqlx::query_as(
+        "SELECT (SELECT COUNT(*) FROM note_links WHERE vault = ?),
+                (SELECT COUNT(DISTINCT lower(ltrim(tag, '#'))) FROM note_tags WHERE vault = ?)",
+    )
+    .bind(vault_directory)
+    .bind(vault_directory)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Failed to read vault stats: {}", e))?;
+
+    Ok(VaultStats {
+        notes,
+        words,
+        characters,
+        reading_minutes: reading_minutes(words),
+        headings,
+        links,
+        tags,
+        size,
+    })
+}
+
+/// Records a save of a note that took its word count from `words_before`
+/// to `words_after`, under today's date
+pub async fn record_save(
+    pool: &Pool<Sqlite>,
+    vault_directory: &str,
+    relative_path: &str,
+    words_before: i64,
+    words_after: i64,
+) -> Result<(), String> {
+    let day = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let change = words_after - words_before;
+    sqlx::query(
+        "INSERT INTO writing_activity
+         (vault, day, relative_path, words_added, words_removed, saves)
+         VALUES (?, ?, ?, ?, ?, 1)
+         ON CONFLICT (vault, day, relative_path) DO UPDATE SET
+             words_added = words_added + excluded.words_added,
+             words_removed = words_removed + excluded.words_removed,
+             saves = saves + 1",
+    )
+    .bind(vault_directory)
+    .bind(day)
+    .bind(relative_path)
+    .bind(change.max(0))
+    .bind((-change).max(0))
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to record writing activity: {}", e))?;
+    Ok(())
+}
+
+/// Lists writing activity per day, oldest first, for the whole vault or a
+/// single note. `from` and `to` are inclusive `YYYY-MM-DD` dates. Days
+/// without saves are left out.
+pub async fn writing_activity(
+    pool: &Pool<Sqlite>,
+    vault_directory: &str,
+    relative_path: Option<&str>,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> Result<Vec<WritingDay>, String> {
+    for date in [from, to].into_iter().flatten() {
+        if chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+            return Err(format!("Invalid date '{}', expected YYYY-MM-DD", date));
+        }
+    }
+
+    let rows: Vec<(String, i64, i64, i64, i64)> = sqlx::query_as(
+        "SELECT day, SUM(words_added), SUM(words_removed), SUM(saves), COUNT(*)
+         FROM writing_activity
+         WHERE vault = ?
+           AND (? IS NULL OR relative_path = ?)
+           AND (? IS NULL OR day >= ?)
+           AND (? IS NULL OR day <= ?)
+         GROUP BY day
+         ORDER BY day",
+    )
+    .bind(vault_directory)
+    .bind(relative_path)
+    .bind(relative_path)
+    .bind(from)
+    .bind(from)
+    .bind(to)
+    .bind(to)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to read writing activity: {}", e))?;
+
+    Ok(rows
+        .into_iter()
+        .map(
+            |(day, words_added, words_removed, saves, notes_edited)| WritingDay {
+                day,
+                words_added,
+                words_removed,
+                saves,
+                notes_edited,
+            },
+        )
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{cache::sync_vault, test_pool};
+    use crate::notes::scan::CancelToken;
+    use std::fs;
+    use tauri::async_runtime::block_on;
+
+    fn vault() -> tempfile::TempDir {
+        let vault = tempfile::tempdir().unwrap();
+        fs::write(
+            vault.path().join("a.md"),
+            "---\ntitle: A long title\ntags: [x]\n---\n# Heading\n\n- one [[b]]\n- two #tag",
+        )
+        .unwrap();
+        fs::write(vault.path().join("b.md"), "# B\nlinks back to [[a]] #Tag").unwrap();
+        fs::write(
+            vault.path().join("secret.md"),
+            "---\nencrypted: true\n---\n-----BEGIN ENCRYPTED NOTE-----\nAAAA\n-----END ENCRYPTED NOTE-----\n",
+        )
+        .unwrap();
+        vault
+    }
+
+    #[test]
+    fn counts_words_and_reading_time() {
+        assert_eq!(count_words("# Title\n\n- one two\n- 3 -- ?"), 4);
+        assert_eq!(count_words(""), 0);
+        assert_eq!(reading_minutes(0), 0);
+        assert_eq!(reading_minutes(1), 1);
+        assert_eq!(reading_minutes(READING_WORDS_PER_MINUTE), 1);
+        assert_eq!(reading_minutes(READING_WORDS_PER_MINUTE + 1), 2);
+    }
+
+    #[test]
+    fn computes_note_stats_without_frontmatter() {
+        let vault = vault();
+        let vault_directory = vault.path().to_str().unwrap();
+        let stats = note_stats("a.md", vault_directory).unwrap();
+        assert_eq!(stats.words, 5);
+        assert_eq!(stats.headings, 1);
+        assert_eq!(stats.outgoing_links, 1);
+        assert_eq!(stats.incoming_links, 1);
+        assert_eq!(stats.reading_minutes, 1);
+        assert_eq!(
+            stats.characters,
+            "# Heading\n\n- one [[b]]\n- two #tag".chars().count() as i64
+        );
+        assert!(stats.characters_no_spaces < stats.characters);
+
+        let secret = note_stats("secret.md", vault_directory).unwrap();
+        assert_eq!((secret.words, secret.characters), (0, 0));
+        assert!(note_stats("missing.md", vault_directory).is_err());
+    }
+
+    #[test]
+    fn adds_up_vault_stats() {
+        block_on(async {
+            let pool = test_pool().await;
+            let vault = vault();
+            let vault_directory = vault.path().to_string_lossy().to_string();
+            sync_vault(&pool, &vault_directory, &CancelToken::default(), None)
+                .await
+                .unwrap();
+
+            let stats = vault_stats(&pool, &vault_directory).await.unwrap();
+            assert_eq!(stats.notes, 3);
+            assert_eq!(stats.links, 2);
+            assert_eq!(stats.headings, 2);
+            assert_eq!(stats.tags, 2);
+            assert!(stats.words > 0 && stats.size > 0);
+
+            let empty = vault_stats(&pool, "/elsewhere").await.unwrap();
+            assert_eq!((empty.notes, empty.words, empty.size), (0, 0, 0));
+        });
+    }
+
+    #[test]
+    fn records_writing_activity_per_day() {
+        block_on(async {
+            let pool = test_pool().await;
+            record_save(&pool, "/vault", "a.md", 10, 25).await.unwrap();
+            record_save(&pool, "/vault", "a.md", 25, 20).await.unwrap();
+            record_save(&pool, "/vault", "b.md", 0, 3).await.unwrap();
+            record_save(&pool, "/other", "a.md", 0, 100).await.unwrap();
+            sqlx::query(
+                "INSERT INTO writing_activity
+                 (vault, day, relative_path, words_added, words_removed, saves)
+                 VALUES ('/vault', '2020-01-01', 'a.md', 7, 0, 2)",
+            )
+            .execute(&pool)
+            .await
+            .unwrap();
+
+            let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+            let days = writing_activity(&pool, "/vault", None, None, None)
+                .await
+                .unwrap();
+            assert_eq!(days.len(), 2);
+            assert_eq!(days[0].day, "2020-01-01");
+            assert_eq!(days[1].day, today);
+            assert_eq!(
+                (
+                    days[1].words_added,
+                    days[1].words_removed,
+                    days[1].saves,
+                    days[1].notes_edited
+                ),
+                (18, 5, 3, 2)
+            );
+
+            let note = writing_activity(&pool, "/vault", Some("b.md"), None, None)
+                .await
+                .unwrap();
+            assert_eq!(note.len(), 1);
+            assert_eq!(note[0].words_added, 3);
+
+            let range = writing_activity(
+                &pool,
+                "/vault",
+                None,
+                Some("2020-01-01"),
+                Some("2020-12-31"),
+            )
+            .await
+            .unwrap();
+            assert_eq!(range.len(), 1);
+            assert!(
+                writing_activity(&pool, "/vault", None, Some("yesterday"), None)
+                    .await
+                    .is_err()
+            );
+        });
+    }
+}
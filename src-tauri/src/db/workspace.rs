@@ -0,0 +1,544 @@
+// src/db/workspace.rs
+use crate::notes::folders::{self, FolderState};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How many recently opened notes are kept per vault
+pub const RECENT_NOTES_LIMIT: i64 = 50;
+
+/// Event emitted with the vault whenever its recent notes change
+pub const RECENT_NOTES_CHANGED_EVENT: &str = "recent-notes-changed";
+
+/// A note open in the workspace and where the user was in it. The selection
+/// holds offsets into the note's body as the editor counts them; both ends
+/// are the same when only the cursor is placed.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct OpenNote {
+    pub relative_path: String,
+    pub editing: bool,
+    pub selection_start: u32,
+    pub selection_end: u32,
+    pub scroll_top: f64,
+}
+
+/// Which panels are shown
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct PanelLayout {
+    pub backlinks_visible: bool,
+}
+
+/// The state of a vault's workspace, restored on the next launch
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct Workspace {
+    pub open_notes: Vec<OpenNote>,
+    pub active_note: Option<String>,
+    pub panels: PanelLayout,
+}
+
+/// A note from the recently opened list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecentNote {
+    pub relative_path: String,
+    /// Title from the note cache, if the note has been cached
+    pub title: Option<String>,
+    /// When the note was last opened, in milliseconds since the epoch
+    pub opened_at: i64,
+    pub open_count: i64,
+}
+
+pub(super) fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// Stores the workspace of a vault, replacing the previous one
+pub async fn save_workspace(
+    pool: &Pool<Sqlite>,
+    vault_directory: &str,
+    workspace: &Workspace,
+) -> Result<(), String> {
+    let state = serde_json::to_string(workspace).map_err(|e| e.to_string())?;
+    sqlx::query("INSERT OR REPLACE INTO workspaces (vault, state, updated_at) VALUES (?, ?, ?)")
+        .bind(vault_directory)
+        .bind(state)
+        .bind(now_millis())
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to save workspace: {}", e))?;
+    Ok(())
+}
+
+/// Loads the saved workspace of a vault. Notes deleted since it was saved
+/// are dropped; a vault without a saved workspace gets the default one.
+pub async fn restore_workspace(
+    pool: &Pool<Sqlite>,
+    vault_directory: &str,
+) -> Result<Workspace, String> {
+    let stored: Option<(String,)> = sqlx::query_as("SELECT state FROM workspaces WHERE vault = ?")
+        .bind(vault_directory)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Failed to read workspace: {}", e))?;
+
+    // A state that no longer parses isn't worth failing the launch over
+    let mut workspace: Workspace = stored
+        .and_then(|(state,)| serde_json::from_str(&state).ok())
+        .unwrap_or_default();
+
+    let vault_path = Path::new(vault_directory);
+    workspace
+        .open_notes
+        .retain(|note| vault_path.join(&note.relative_path).is_file());
+    if let Some(active) = &workspace.active_note {
+        if !workspace
+            .open_notes
+            .iter()
+            .any(|note| &note.relative_path == active)
+        {
+            workspace.active_note = workspace
+                .open_notes
+                .first()
+                .map(|note| note.relative_path.clone());
+        }
+    }
+
+    Ok(workspace)
+}
+
+/// Records that a note was opened, keeping the list to `RECENT_NOTES_LIMIT`
+pub async fn record_note_opened(
+    pool: &Pool<Sqlite>,
+    vault_directory: &str,
+    relative_path: &str,
+) -> Result<(), String> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to record opened note: {}", e))?;
+    sqlx::query(
+        "INSERT INTO recent_notes (vault, relative_path, opened_at, open_count) VALUES (?, ?, ?, 1)
+         ON CONFLICT (vault, relative_path)
+         DO UPDATE SET opened_at = excluded.opened_at, open_count = open_count + 1",
+    )
+    .bind(vault_directory)
+    .bind(relative_path)
+    .bind(now_millis())
+    .execute(&mut *transaction)
+    .await
+    .map_err(|e| format!("Failed to record opened note: {}", e))?;
+    sqlx::query(
+        "DELETE FROM recent_notes WHERE vault = ? AND relative_path NOT IN (
+             SELECT relative_path FROM recent_notes WHERE vault = ?
+             ORDER BY opened_at DESC LIMIT ?
+         )",
+    )
+    .bind(vault_directory)
+    .bind(vault_directory)
+    .bind(RECENT_NOTES_LIMIT)
+    .execute(&mut *transaction)
+    .await
+    .map_err(|e| format!("Failed to record opened note: {}", e))?;
+    transaction
+        .commit()
+        .await
+        .map_err(|e| format!("Failed to record opened note: {}", e))
+}
+
+/// Lists recently opened notes, most recent first. Notes that no longer
+/// exist are left out.
+pub async fn recent_notes(
+    pool: &Pool<Sqlite>,
+    vault_directory: &str,
+    limit: usize,
+) -> Result<Vec<RecentNote>, String> {
+    let rows: Vec<(String, Option<String>, i64, i64)> = sqlx::query_as(
+        "SELECT r.relative_path, n.title, r.opened_at, r.open_count
+         FROM recent_notes r
+         LEFT JOIN notes n ON n.vault = r.vault AND n.relative_path = r.relative_path
+         WHERE r.vault = ?
+         ORDER BY r.opened_at DESC",
+    )
+    .bind(vault_directory)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to read recent notes: {}", e))?;
+
+    let vault_path = Path::new(vault_directory);
+    Ok(rows
+        .into_iter()
+        .filter(|(relative_path, ..)| vault_path.join(relative_path).is_file())
+        .take(limit)
+        .map(|(relative_path, title, opened_at, open_count)| RecentNote {
+            relative_path,
+            title,
+            opened_at,
+            open_count,
+        })
+        .collect())
+}
+
+/// Loads the saved view state of a vault's folders, keyed by relative path
+pub async fn load_folder_states(
+    pool: &Pool<Sqlite>,
+    vault_directory: &str,
+) -> Result<HashMap<String, FolderState>, String> {
+    let rows: Vec<(String, bool, Option<i64>)> = sqlx::query_as(
+        "SELECT relative_path, collapsed, position FROM folder_states WHERE vault = ?",
+    )
+    .bind(vault_directory)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to read folder state: {}", e))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(relative_path, collapsed, position)| {
+            (
+                relative_path,
+                FolderState {
+                    collapsed,
+                    position,
+                },
+            )
+        })
+        .collect())
+}
+
+/// Stores a folder's view state
+pub async fn save_folder_state(
+    pool: &Pool<Sqlite>,
+    vault_directory: &str,
+    relative_path: &str,
+    state: &FolderState,
+) -> Result<(), String> {
+    sqlx::query(
+        "INSERT OR REPLACE INTO folder_states (vault, relative_path, collapsed, position)
+         VALUES (?, ?, ?, ?)",
+    )
+    .bind(vault_directory)
+    .bind(relative_path)
+    .bind(state.collapsed)
+    .bind(state.position)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to save folder state: {}", e))?;
+    Ok(())
+}
+
+/// Tables holding paths that follow a note or folder when it moves
+const PATH_TABLES: &[&str] = &[
+    "recent_notes",
+    "folder_states",
+    "writing_activity",
+    "flashcard_reviews",
+    "note_ids",
+];
+
+/// Points the stored paths of a note that was renamed outside the app at
+/// its new path, including where it's open in the saved workspace
+pub(super) async fn move_note_paths(
+    transaction: &mut sqlx::Transaction<'_, Sqlite>,
+    vault_directory: &str,
+    from: &str,
+    to: &str,
+) -> Result<(), sqlx::Error> {
+    move_paths(transaction, vault_directory, from, Some(to)).await
+}
+
+/// Matches rows at a path or inside it, bound as path, prefix length and
+/// prefix
+const PATH_MATCH: &str = "(relative_path = ? OR substr(relative_path, 1, ?) = ?)";
+
+/// Moves every stored path at or inside `from` to `to`, or drops them
+/// without a `to`. Moved rows replace the ones they collide with, except
+/// writing activity, which is added up.
+async fn move_paths(
+    transaction: &mut sqlx::Transaction<'_, Sqlite>,
+    vault_directory: &str,
+    from: &str,
+    to: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    let prefix = format!("{}/", from);
+    let prefix_length = prefix.chars().count() as i64;
+
+    for table in PATH_TABLES
+        .iter()
+        .filter(|table| **table != "writing_activity")
+    {
+        let query = match to {
+            Some(_) => format!(
+                "UPDATE OR REPLACE {} SET relative_path = ? || substr(relative_path, ?)
+                 WHERE vault = ? AND {}",
+                table, PATH_MATCH
+            ),
+            None => format!("DELETE FROM {} WHERE vault = ? AND {}", table, PATH_MATCH),
+        };
+
+        let mut query = sqlx::query(&query);
+        if let Some(to) = to {
+            query = query.bind(to).bind(from.chars().count() as i64 + 1);
+        }
+        query
+            .bind(vault_directory)
+            .bind(from)
+            .bind(prefix_length)
+            .bind(&prefix)
+            .execute(&mut **transaction)
+            .await?;
+    }
+
+    let activity: Vec<(String, String, i64, i64, i64)> = sqlx::query_as(&format!(
+        "SELECT day, relative_path, words_added, words_removed, saves
+         FROM writing_activity WHERE vault = ? AND {}",
+        PATH_MATCH
+    ))
+    .bind(vault_directory)
+    .bind(from)
+    .bind(prefix_length)
+    .bind(&prefix)
+    .fetch_all(&mut **transaction)
+    .await?;
+    sqlx::query(&format!(
+        "DELETE FROM writing_activity WHERE vault = ? AND {}",
+        PATH_MATCH
+    ))
+    .bind(vault_directory)
+    .bind(from)
+    .bind(prefix_length)
+    .bind(&prefix)
+    .execute(&mut **transaction)
+    .await?;
+    if let Some(to) = to {
+        for (day, relative_path, words_added, words_removed, saves) in activity {
+            let Some(moved) = folders::moved_path(&relative_path, from, to) else {
+                continue;
+            };
+            sqlx::query(
+                "INSERT INTO writing_activity
+                 (vault, day, relative_path, words_added, words_removed, saves)
+                 VALUES (?, ?, ?, ?, ?, ?)
+                 ON CONFLICT (vault, day, relative_path) DO UPDATE SET
+                     words_added = words_added + excluded.words_added,
+                     words_removed = words_removed + excluded.words_removed,
+                     saves = saves + excluded.saves",
+            )
+            .bind(vault_directory)
+            .bind(day)
+            .bind(moved)
+            .bind(words_added)
+            .bind(words_removed)
+            .bind(saves)
+            .execute(&mut **transaction)
+            .await?;
+        }
+    }
+
+    move_workspace_paths(transaction, vault_directory, from, to).await
+}
+
+/// Points the notes open in a vault's saved workspace at their new paths,
+/// or closes them without a `to`
+async fn move_workspace_paths(
+    transaction: &mut sqlx::Transaction<'_, Sqlite>,
+    vault_directory: &str,
+    from: &str,
+    to: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    let stored: Option<(String,)> = sqlx::query_as("SELECT state FROM workspaces WHERE vault = ?")
+        .bind(vault_directory)
+        .fetch_optional(&mut **transaction)
+        .await?;
+    let Some(mut workspace) =
+        stored.and_then(|(state,)| serde_json::from_str::<Workspace>(&state).ok())
+    else {
+        return Ok(());
+    };
+
+    let original = workspace.clone();
+    let moved = |path: &str| match to {
+        Some(to) => Some(folders::moved_path(path, from, to).unwrap_or_else(|| path.to_string())),
+        None => folders::moved_path(path, from, "")
+            .is_none()
+            .then(|| path.to_string()),
+    };
+    workspace.open_notes = workspace
+        .open_notes
+        .into_iter()
+        .filter_map(|note| {
+            moved(&note.relative_path).map(|relative_path| OpenNote {
+                relative_path,
+                ..note
+            })
+        })
+        .collect();
+    workspace.active_note = match workspace.active_note.as_deref().map(moved) {
+        Some(Some(active)) => Some(active),
+        Some(None) => workspace
+            .open_notes
+            .first()
+            .map(|note| note.relative_path.clone()),
+        None => None,
+    };
+    if workspace == original {
+        return Ok(());
+    }
+
+    let state = serde_json::to_string(&workspace).map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+    sqlx::query("UPDATE workspaces SET state = ?, updated_at = ? WHERE vault = ?")
+        .bind(state)
+        .bind(now_millis())
+        .bind(vault_directory)
+        .execute(&mut **transaction)
+        .await?;
+    Ok(())
+}
+
+/// Points stored paths inside a moved folder at its new location. Without a
+/// new location the rows are dropped, for a deleted folder.
+pub async fn move_folder_paths(
+    pool: &Pool<Sqlite>,
+    vault_directory: &str,
+    from: &str,
+    to: Option<&str>,
+) -> Result<(), String> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to update stored paths: {}", e))?;
+    move_paths(&mut transaction, vault_directory, from, to)
+        .await
+        .map_err(|e| format!("Failed to update stored paths: {}", e))?;
+    transaction
+        .commit()
+        .await
+        .map_err(|e| format!("Failed to update stored paths: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_pool;
+    use tauri::async_runtime::block_on;
+
+    async fn add_activity(pool: &Pool<Sqlite>, relative_path: &str, words_added: i64) {
+        sqlx::query(
+            "INSERT INTO writing_activity (vault, day, relative_path, words_added, words_removed, saves)
+             VALUES ('v', '2024-01-01', ?, ?, 1, 1)",
+        )
+        .bind(relative_path)
+        .bind(words_added)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    async fn activity(pool: &Pool<Sqlite>) -> Vec<(String, i64, i64, i64)> {
+        sqlx::query_as(
+            "SELECT relative_path, words_added, words_removed, saves FROM writing_activity
+             ORDER BY relative_path",
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
+
+    fn open(relative_path: &str) -> OpenNote {
+        OpenNote {
+            relative_path: relative_path.to_string(),
+            ..Default::default()
+        }
+    }
+
+    async fn stored_workspace(pool: &Pool<Sqlite>) -> Workspace {
+        let (state,): (String,) = sqlx::query_as("SELECT state FROM workspaces WHERE vault = 'v'")
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        serde_json::from_str(&state).unwrap()
+    }
+
+    #[test]
+    fn moving_a_folder_merges_writing_activity() {
+        block_on(async {
+            let pool = test_pool().await;
+            add_activity(&pool, "a/note.md", 10).await;
+            add_activity(&pool, "b/note.md", 5).await;
+            add_activity(&pool, "ab/note.md", 1).await;
+
+            move_folder_paths(&pool, "v", "a", Some("b")).await.unwrap();
+            assert_eq!(
+                activity(&pool).await,
+                vec![
+                    ("ab/note.md".to_string(), 1, 1, 1),
+                    ("b/note.md".to_string(), 15, 2, 2),
+                ]
+            );
+
+            move_folder_paths(&pool, "v", "b", None).await.unwrap();
+            assert_eq!(activity(&pool).await.len(), 1);
+        });
+    }
+
+    #[test]
+    fn moving_a_folder_follows_open_notes() {
+        block_on(async {
+            let pool = test_pool().await;
+            let workspace = Workspace {
+                open_notes: vec![open("a/one.md"), open("a/sub/two.md"), open("other.md")],
+                active_note: Some("a/sub/two.md".to_string()),
+                ..Default::default()
+            };
+            save_workspace(&pool, "v", &workspace).await.unwrap();
+
+            move_folder_paths(&pool, "v", "a", Some("x/a"))
+                .await
+                .unwrap();
+            let moved = stored_workspace(&pool).await;
+            let paths: Vec<&str> = moved
+                .open_notes
+                .iter()
+                .map(|note| note.relative_path.as_str())
+                .collect();
+            assert_eq!(paths, vec!["x/a/one.md", "x/a/sub/two.md", "other.md"]);
+            assert_eq!(moved.active_note.as_deref(), Some("x/a/sub/two.md"));
+
+            move_folder_paths(&pool, "v", "x/a/sub", None)
+                .await
+                .unwrap();
+            let closed = stored_workspace(&pool).await;
+            assert_eq!(closed.open_notes.len(), 2);
+            assert_eq!(closed.active_note.as_deref(), Some("x/a/one.md"));
+        });
+    }
+
+    #[test]
+    fn records_recent_notes_once_per_path() {
+        block_on(async {
+            let pool = test_pool().await;
+            let vault = tempfile::tempdir().unwrap();
+            let vault_directory = vault.path().to_string_lossy();
+            std::fs::write(vault.path().join("a.md"), "").unwrap();
+
+            record_note_opened(&pool, &vault_directory, "a.md")
+                .await
+                .unwrap();
+            record_note_opened(&pool, &vault_directory, "a.md")
+                .await
+                .unwrap();
+            record_note_opened(&pool, &vault_directory, "gone.md")
+                .await
+                .unwrap();
+            let recent = recent_notes(&pool, &vault_directory, 10).await.unwrap();
+            assert_eq!(recent.len(), 1);
+            assert_eq!(recent[0].open_count, 2);
+        });
+    }
+}
@@ -0,0 +1,348 @@
+// src/export/epub.rs
+use super::{
+    encode_url_path, escape_html, frontmatter_table, markdown_events, prepare_note, relative_url,
+    select_bundle, with_extension, ExportSummary, LinkStyle, EXPORT_CSS, UNRESOLVED_SPAN,
+};
+use crate::notes::attachments;
+use crate::notes::helpers;
+use crate::notes::links::LinkResolver;
+use pulldown_cmark::{html as cmark_html, Event};
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Folder inside the archive that holds the book's content
+const CONTENT_DIR: &str = "OEBPS";
+
+const CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+/// Renders markdown for a chapter. Readers parse chapters as XML, where most
+/// hand-written HTML isn't valid, so raw HTML from notes is shown as text.
+/// Only the spans that mark unresolved links are kept.
+fn render_xhtml(markdown: &str) -> String {
+    let mut events = markdown_events(markdown);
+    let mut i = 0;
+
+    while i < events.len() {
+        match &events[i] {
+            Event::InlineHtml(html) if html.as_ref() == UNRESOLVED_SPAN => {
+                let close = events[i + 1..]
+                    .iter()
+                    .position(|event| !matches!(event, Event::Text(_)))
+                    .map(|offset| i + 1 + offset)
+                    .filter(|&j| matches!(&events[j], Event::InlineHtml(html) if html.as_ref() == "</span>"));
+                match close {
+                    Some(j) => i = j,
+                    None => events[i] = Event::Text(UNRESOLVED_SPAN.into()),
+                }
+            }
+            Event::Html(html) | Event::InlineHtml(html) => {
+                events[i] = Event::Text(html.clone());
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    let mut html = String::new();
+    cmark_html::push_html(&mut html, events.into_iter());
+    html
+}
+
+/// Wraps rendered HTML into an XHTML content document
+fn xhtml_page(title: &str, stylesheet: &str, body: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<!DOCTYPE html>\n\
+         <html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\" lang=\"en\">\n\
+         <head>\n<meta charset=\"utf-8\"/>\n<title>{}</title>\n\
+         <link rel=\"stylesheet\" type=\"text/css\" href=\"{}\"/>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape_html(title),
+        stylesheet,
+        body
+    )
+}
+
+/// Exports a note (optionally with the notes it links to), a folder or the
+/// whole vault as an EPUB book with one chapter per note
+pub fn export_epub(
+    vault_directory: &str,
+    relative_path: Option<&str>,
+    folder: Option<&str>,
+    include_linked: bool,
+    book_title: Option<&str>,
+    output_file: &str,
+) -> Result<ExportSummary, String> {
+    let resolver = LinkResolver::new(vault_directory);
+    let selection = select_bundle(
+        vault_directory,
+        relative_path,
+        folder,
+        include_linked,
+        &resolver,
+    )?;
+    let notes = &selection.notes;
+    let exported: HashSet<String> = notes.iter().cloned().collect();
+    let link_style = LinkStyle::RelativeFiles {
+        extension: "xhtml",
+        exported: &exported,
+    };
+
+    let book_title = match book_title {
+        Some(title) if !title.trim().is_empty() => title.trim().to_string(),
+        _ => match (relative_path, folder) {
+            (Some(note), _) => helpers::get_title(None, Some(note), Some(vault_directory)),
+            (None, Some(folder)) => folder.trim_matches('/').to_string(),
+            (None, None) => Path::new(vault_directory)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| "Notes".to_string()),
+        },
+    };
+
+    println!("Exporting {} notes to EPUB {}", notes.len(), output_file);
+
+    let mut warnings: Vec<String> = selection.skipped_warnings();
+    let mut chapters: Vec<(String, String, String)> = Vec::new();
+    let mut assets: BTreeSet<String> = BTreeSet::new();
+
+    for rel_path in notes.iter() {
+        let title = helpers::get_title(None, Some(rel_path), Some(vault_directory));
+        let frontmatter = helpers::get_frontmatter(None, Some(rel_path), Some(vault_directory));
+        let prepared = prepare_note(rel_path, vault_directory, &resolver, &link_style);
+        let chapter_path = with_extension(rel_path, "xhtml");
+
+        let body = format!(
+            "<h1>{}</h1>\n{}{}",
+            escape_html(&title),
+            frontmatter_table(frontmatter.as_ref()),
+            render_xhtml(&prepared.markdown)
+        );
+        let page = xhtml_page(&title, &relative_url(&chapter_path, "style.css"), &body);
+
+        warnings.extend(prepared.warnings);
+        assets.extend(prepared.attachments);
+        chapters.push((chapter_path, title, page));
+    }
+
+    // Only files that can be read go into the book
+    let mut asset_data: Vec<(String, Vec<u8>)> = Vec::new();
+    for asset in assets {
+        match fs::read(Path::new(vault_directory).join(&asset)) {
+            Ok(data) => asset_data.push((asset, data)),
+            Err(e) => warnings.push(format!("Failed to add {}: {}", asset, e)),
+        }
+    }
+
+    // The same notes always make the same book, whatever order they came in
+    let mut sorted_notes = notes.clone();
+    sorted_notes.sort();
+    let identifier = format!("urn:notemancy:{:x}", md5::compute(sorted_notes.join("\n")));
+    let modified = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
+
+    // Package document: metadata, every file in the book and reading order
+    let mut manifest = String::new();
+    let mut spine = String::new();
+    let mut nav_items = String::new();
+    for (index, (chapter_path, title, _)) in chapters.iter().enumerate() {
+        manifest.push_str(&format!(
+            "    <item id=\"chapter-{}\" href=\"{}\" media-type=\"application/xhtml+xml\"/>\n",
+            index,
+            encode_url_path(chapter_path)
+        ));
+        spine.push_str(&format!("    <itemref idref=\"chapter-{}\"/>\n", index));
+        nav_items.push_str(&format!(
+            "      <li><a href=\"{}\">{}</a></li>\n",
+            encode_url_path(chapter_path),
+            escape_html(title)
+        ));
+    }
+    for (index, (asset, _)) in asset_data.iter().enumerate() {
+        let extension = Path::new(asset)
+            .extension()
+            .map(|e| e.to_string_lossy().to_string())
+            .unwrap_or_default();
+        manifest.push_str(&format!(
+            "    <item id=\"asset-{}\" href=\"{}\" media-type=\"{}\"/>\n",
+            index,
+            encode_url_path(asset),
+            attachments::mime_type(&extension)
+        ));
+    }
+
+    let opf = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"book-id\">\n\
+         \x20 <metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n\
+         \x20   <dc:identifier id=\"book-id\">{}</dc:identifier>\n\
+         \x20   <dc:title>{}</dc:title>\n\
+         \x20   <dc:language>en</dc:language>\n\
+         \x20   <meta property=\"dcterms:modified\">{}</meta>\n\
+         \x20 </metadata>\n\
+         \x20 <manifest>\n\
+         \x20   <item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n\
+         \x20   <item id=\"style\" href=\"style.css\" media-type=\"text/css\"/>\n\
+         {}\x20 </manifest>\n\
+         \x20 <spine>\n{}\x20 </spine>\n\
+         </package>\n",
+        identifier,
+        escape_html(&book_title),
+        modified,
+        manifest,
+        spine
+    );
+
+    let nav = xhtml_page(
+        &book_title,
+        "style.css",
+        &format!(
+            "<nav epub:type=\"toc\" id=\"toc\">\n  <h1>{}</h1>\n  <ol>\n{}  </ol>\n</nav>\n",
+            escape_html(&book_title),
+            nav_items
+        ),
+    );
+
+    // Write the archive; the mimetype entry must come first, uncompressed
+    if let Some(parent) = Path::new(output_file).parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create folder: {}", e))?;
+    }
+    let file = fs::File::create(output_file)
+        .map_err(|e| format!("Failed to create {}: {}", output_file, e))?;
+    let mut zip = ZipWriter::new(file);
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let mut entries: Vec<(String, Vec<u8>, SimpleFileOptions)> = vec![
+        (
+            "mimetype".to_string(),
+            b"application/epub+zip".to_vec(),
+            stored,
+        ),
+        (
+            "META-INF/container.xml".to_string(),
+            CONTAINER_XML.as_bytes().to_vec(),
+            deflated,
+        ),
+        (
+            format!("{}/content.opf", CONTENT_DIR),
+            opf.into_bytes(),
+            deflated,
+        ),
+        (
+            format!("{}/nav.xhtml", CONTENT_DIR),
+            nav.into_bytes(),
+            deflated,
+        ),
+        (
+            format!("{}/style.css", CONTENT_DIR),
+            EXPORT_CSS.as_bytes().to_vec(),
+            deflated,
+        ),
+    ];
+    for (chapter_path, _, page) in chapters {
+        entries.push((
+            format!("{}/{}", CONTENT_DIR, chapter_path),
+            page.into_bytes(),
+            deflated,
+        ));
+    }
+
+    let attachments_copied = asset_data.len();
+    for (asset, data) in asset_data {
+        entries.push((format!("{}/{}", CONTENT_DIR, asset), data, deflated));
+    }
+
+    for (name, data, options) in entries {
+        zip.start_file(name.as_str(), options)
+            .map_err(|e| format!("Failed to add {} to EPUB: {}", name, e))?;
+        zip.write_all(&data)
+            .map_err(|e| format!("Failed to write {} to EPUB: {}", name, e))?;
+    }
+    zip.finish()
+        .map_err(|e| format!("Failed to finish EPUB: {}", e))?;
+
+    Ok(ExportSummary {
+        output_path: output_file.to_string(),
+        notes_exported: notes.len(),
+        attachments_copied,
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn writes_a_book_with_linked_chapters() {
+        let vault = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+        fs::write(
+            vault.path().join("start.md"),
+            "---\ntitle: Start\n---\nGo to [[next]] ![[cover.png]] [[missing]]<br>\n\n<div>\n<b>open\n",
+        )
+        .unwrap();
+        fs::write(vault.path().join("next.md"), "# Next").unwrap();
+        fs::write(vault.path().join("cover.png"), "png").unwrap();
+        let book = output.path().join("book.epub");
+
+        let summary = export_epub(
+            vault.path().to_str().unwrap(),
+            Some("start.md"),
+            None,
+            true,
+            None,
+            book.to_str().unwrap(),
+        )
+        .unwrap();
+        assert_eq!((summary.notes_exported, summary.attachments_copied), (2, 1));
+
+        let mut archive = zip::ZipArchive::new(fs::File::open(&book).unwrap()).unwrap();
+        let read = |archive: &mut zip::ZipArchive<fs::File>, name: &str| {
+            let mut content = String::new();
+            archive
+                .by_name(name)
+                .unwrap()
+                .read_to_string(&mut content)
+                .unwrap();
+            content
+        };
+
+        // Readers find the mimetype first and uncompressed
+        let first = archive.by_index(0).unwrap();
+        assert_eq!(first.name(), "mimetype");
+        assert_eq!(first.compression(), CompressionMethod::Stored);
+        drop(first);
+
+        let opf = read(&mut archive, "OEBPS/content.opf");
+        assert!(opf.contains("<dc:title>Start</dc:title>"));
+        assert!(opf.contains("href=\"start.xhtml\""));
+        assert!(opf.contains("href=\"next.xhtml\""));
+        assert!(opf.contains("media-type=\"image/png\""));
+        let identifier = format!("urn:notemancy:{:x}", md5::compute("next.md\nstart.md"));
+        assert!(opf.contains(&identifier));
+        let chapter = read(&mut archive, "OEBPS/start.xhtml");
+        assert!(chapter.contains("href=\"next.xhtml\""));
+
+        // Raw HTML shows as text so the chapter stays well-formed XML
+        assert!(chapter.contains("<span class=\"unresolved\">missing</span>&lt;br&gt;"));
+        assert!(chapter.contains("&lt;b&gt;open"));
+        let mut reader = quick_xml::Reader::from_str(&chapter);
+        loop {
+            match reader.read_event().unwrap() {
+                quick_xml::events::Event::Eof => break,
+                _ => continue,
+            }
+        }
+        assert!(archive.by_name("OEBPS/cover.png").is_ok());
+    }
+}
@@ -0,0 +1,190 @@
+// src/export/html.rs
+use super::{
+    escape_html, frontmatter_table, page_template, prepare_note, relative_url, render_markdown,
+    select_notes, with_extension, ExportSummary, LinkStyle, EXPORT_CSS,
+};
+use crate::notes::helpers;
+use crate::notes::links::LinkResolver;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Name of the stylesheet written next to the exported pages
+const STYLESHEET_NAME: &str = "style.css";
+
+/// Writes a file, creating its parent folders first
+fn write_file(path: &Path, content: &str) -> Result<(), io::Error> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, content)
+}
+
+/// Renders the index page listing every exported note, grouped by folder
+fn render_index(entries: &[(String, String)]) -> String {
+    let mut folders: BTreeMap<String, Vec<&(String, String)>> = BTreeMap::new();
+    for entry in entries {
+        let folder = match entry.0.rsplit_once('/') {
+            Some((folder, _)) => folder.to_string(),
+            None => String::new(),
+        };
+        folders.entry(folder).or_default().push(entry);
+    }
+
+    let mut body = String::new();
+    for (folder, notes) in folders {
+        if !folder.is_empty() {
+            body.push_str(&format!("<h2>{}</h2>\n", escape_html(&folder)));
+        }
+        body.push_str("<ul>\n");
+        for (rel_path, title) in notes {
+            body.push_str(&format!(
+                "<li><a href=\"{}\">{}</a></li>\n",
+                relative_url("index.html", &with_extension(rel_path, "html")),
+                escape_html(title)
+            ));
+        }
+        body.push_str("</ul>\n");
+    }
+
+    let css = format!("<link rel=\"stylesheet\" href=\"{}\">", STYLESHEET_NAME);
+    page_template("Index", &css, "", &body)
+}
+
+/// Exports a single note, a folder or the whole vault to static HTML in
+/// `output_directory`, mirroring the vault's folder structure
+pub fn export_html(
+    vault_directory: &str,
+    relative_path: Option<&str>,
+    folder: Option<&str>,
+    output_directory: &str,
+) -> Result<ExportSummary, String> {
+    let selection = select_notes(vault_directory, relative_path, folder)?;
+    let notes = &selection.notes;
+    let output = Path::new(output_directory);
+    if output.starts_with(vault_directory) {
+        return Err("Export directory must be outside the vault".to_string());
+    }
+    fs::create_dir_all(output).map_err(|e| format!("Failed to create export directory: {}", e))?;
+
+    println!(
+        "Exporting {} notes to HTML in {}",
+        notes.len(),
+        output_directory
+    );
+
+    let resolver = LinkResolver::new(vault_directory);
+    let exported: HashSet<String> = notes.iter().cloned().collect();
+    let link_style = LinkStyle::RelativeFiles {
+        extension: "html",
+        exported: &exported,
+    };
+
+    let mut warnings: Vec<String> = selection.skipped_warnings();
+    let mut attachments: HashSet<String> = HashSet::new();
+    let mut index_entries: Vec<(String, String)> = Vec::new();
+
+    for rel_path in notes.iter() {
+        let title = helpers::get_title(None, Some(rel_path), Some(vault_directory));
+        let frontmatter = helpers::get_frontmatter(None, Some(rel_path), Some(vault_directory));
+        let prepared = prepare_note(rel_path, vault_directory, &resolver, &link_style);
+
+        let page_path = with_extension(rel_path, "html");
+        let css = format!(
+            "<link rel=\"stylesheet\" href=\"{}\">",
+            relative_url(&page_path, STYLESHEET_NAME)
+        );
+        let header = format!(
+            "<nav class=\"breadcrumbs\"><a href=\"{}\">Index</a></nav>\n",
+            relative_url(&page_path, "index.html")
+        );
+        let body = format!(
+            "{}{}",
+            frontmatter_table(frontmatter.as_ref()),
+            render_markdown(&prepared.markdown)
+        );
+
+        write_file(
+            &output.join(&page_path),
+            &page_template(&title, &css, &header, &body),
+        )
+        .map_err(|e| format!("Failed to write {}: {}", page_path, e))?;
+
+        warnings.extend(prepared.warnings);
+        attachments.extend(prepared.attachments);
+        index_entries.push((rel_path.clone(), title));
+    }
+
+    let mut attachments_copied = 0;
+    for attachment in attachments.iter() {
+        let target = output.join(attachment);
+        if let Some(parent) = target.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        match fs::copy(Path::new(vault_directory).join(attachment), &target) {
+            Ok(_) => attachments_copied += 1,
+            Err(e) => warnings.push(format!("Failed to copy {}: {}", attachment, e)),
+        }
+    }
+
+    write_file(&output.join(STYLESHEET_NAME), EXPORT_CSS)
+        .map_err(|e| format!("Failed to write stylesheet: {}", e))?;
+    write_file(&output.join("index.html"), &render_index(&index_entries))
+        .map_err(|e| format!("Failed to write index page: {}", e))?;
+
+    Ok(ExportSummary {
+        output_path: output.join("index.html").to_string_lossy().to_string(),
+        notes_exported: notes.len(),
+        attachments_copied,
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exports_a_folder_with_its_attachments() {
+        let vault = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+        fs::create_dir_all(vault.path().join("docs/img")).unwrap();
+        fs::write(
+            vault.path().join("docs/guide.md"),
+            "---\ntitle: Guide\n---\n![[img/map.png]] [[faq]]",
+        )
+        .unwrap();
+        fs::write(vault.path().join("docs/faq.md"), "# FAQ").unwrap();
+        fs::write(vault.path().join("docs/img/map.png"), "png").unwrap();
+        fs::write(vault.path().join("other.md"), "not exported").unwrap();
+
+        let summary = export_html(
+            vault.path().to_str().unwrap(),
+            None,
+            Some("docs"),
+            output.path().to_str().unwrap(),
+        )
+        .unwrap();
+        assert_eq!((summary.notes_exported, summary.attachments_copied), (2, 1));
+
+        let page = fs::read_to_string(output.path().join("docs/guide.html")).unwrap();
+        assert!(page.contains("<title>Guide</title>"));
+        assert!(page.contains("href=\"faq.html\""));
+        assert!(page.contains("src=\"img/map.png\""));
+        assert!(page.contains("href=\"../style.css\""));
+        assert!(output.path().join("docs/img/map.png").is_file());
+        assert!(!output.path().join("other.html").exists());
+        let index = fs::read_to_string(output.path().join("index.html")).unwrap();
+        assert!(index.contains("docs/guide.html"));
+    }
+
+    #[test]
+    fn refuses_to_export_into_the_vault() {
+        let vault = tempfile::tempdir().unwrap();
+        fs::write(vault.path().join("note.md"), "body").unwrap();
+        let path = vault.path().to_str().unwrap();
+        let inside = vault.path().join("export");
+        assert!(export_html(path, None, None, inside.to_str().unwrap()).is_err());
+    }
+}
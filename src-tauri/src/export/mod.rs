@@ -0,0 +1,584 @@
+// src/export/mod.rs
+pub mod epub;
+pub mod html;
+pub mod single_file;
+
+use crate::notes::ast;
+use crate::notes::embeds;
+use crate::notes::encryption;
+use crate::notes::formats;
+use crate::notes::helpers;
+use crate::notes::links::{self, LinkResolver};
+use crate::notes::outline;
+use pulldown_cmark::{html as cmark_html, CowStr, Event, Parser, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// What an export run produced
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportSummary {
+    pub output_path: String,
+    pub notes_exported: usize,
+    pub attachments_copied: usize,
+    pub warnings: Vec<String>,
+}
+
+/// The notes an export covers. Only markdown notes can be rendered, so org
+/// and plain text notes are left out and listed in `skipped`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NoteSelection {
+    pub notes: Vec<String>,
+    pub skipped: Vec<String>,
+}
+
+impl NoteSelection {
+    /// One export warning per skipped note
+    pub fn skipped_warnings(&self) -> Vec<String> {
+        self.skipped
+            .iter()
+            .map(|rel_path| format!("{}: skipped, only markdown notes can be exported", rel_path))
+            .collect()
+    }
+}
+
+/// Opens the span an unresolved link's text is wrapped in
+pub const UNRESOLVED_SPAN: &str = "<span class=\"unresolved\">";
+
+/// Stylesheet shared by every exported page
+pub const EXPORT_CSS: &str = r#"
+body { font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", sans-serif; line-height: 1.6; max-width: 48rem; margin: 2rem auto; padding: 0 1rem; color: #222; }
+a { color: #2563eb; }
+.unresolved { color: #b91c1c; text-decoration: line-through; }
+pre { background: #f5f5f5; padding: 0.75rem; overflow-x: auto; }
+code { font-family: ui-monospace, monospace; }
+img { max-width: 100%; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ddd; padding: 0.25rem 0.5rem; text-align: left; }
+table.frontmatter { margin-bottom: 1.5rem; font-size: 0.9rem; }
+nav.breadcrumbs { font-size: 0.9rem; margin-bottom: 1rem; }
+"#;
+
+/// Escapes text for use in HTML content and attribute values
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Replaces characters that aren't safe in a URL path
+pub fn encode_url_path(path: &str) -> String {
+    path.replace('%', "%25")
+        .replace(' ', "%20")
+        .replace('#', "%23")
+        .replace('?', "%3F")
+}
+
+/// Swaps a note's extension for another one
+pub fn with_extension(relative_path: &str, extension: &str) -> String {
+    let stem = formats::strip_note_extension(relative_path);
+    format!("{}.{}", stem, extension)
+}
+
+/// Builds a relative URL from one vault-relative file to another
+pub fn relative_url(from_file: &str, to_file: &str) -> String {
+    let from_dirs: Vec<&str> = from_file.split('/').collect();
+    let from_dirs = &from_dirs[..from_dirs.len().saturating_sub(1)];
+    let to_parts: Vec<&str> = to_file.split('/').collect();
+
+    let common = from_dirs
+        .iter()
+        .zip(to_parts.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut parts: Vec<String> = vec!["..".to_string(); from_dirs.len() - common];
+    parts.extend(to_parts[common..].iter().map(|p| p.to_string()));
+    encode_url_path(&parts.join("/"))
+}
+
+/// Parses markdown into the events the exporters render, giving headings the
+/// same ids the editor uses
+pub fn markdown_events(markdown: &str) -> Vec<Event<'_>> {
+    let events: Vec<Event> = Parser::new_ext(markdown, ast::markdown_options()).collect();
+    let mut slug_counts: HashMap<String, usize> = HashMap::new();
+    let mut output: Vec<Event> = Vec::with_capacity(events.len());
+    let mut i = 0;
+
+    while i < events.len() {
+        if let Event::Start(Tag::Heading {
+            level,
+            id: None,
+            classes,
+            attrs,
+        }) = &events[i]
+        {
+            // Collect the heading's text to build its slug
+            let mut text = String::new();
+            let mut j = i + 1;
+            while j < events.len() && !matches!(events[j], Event::End(TagEnd::Heading(_))) {
+                if let Event::Text(t) | Event::Code(t) = &events[j] {
+                    text.push_str(t);
+                }
+                j += 1;
+            }
+
+            let base_slug = outline::slugify(&text);
+            let count = slug_counts.entry(base_slug.clone()).or_insert(0);
+            let slug = if *count == 0 {
+                base_slug
+            } else {
+                format!("{}-{}", base_slug, count)
+            };
+            *count += 1;
+
+            output.push(Event::Start(Tag::Heading {
+                level: *level,
+                id: Some(CowStr::from(slug)),
+                classes: classes.clone(),
+                attrs: attrs.clone(),
+            }));
+        } else {
+            output.push(events[i].clone());
+        }
+        i += 1;
+    }
+    output
+}
+
+/// Renders markdown to HTML, giving headings the same ids the editor uses
+pub fn render_markdown(markdown: &str) -> String {
+    let mut html = String::new();
+    cmark_html::push_html(&mut html, markdown_events(markdown).into_iter());
+    html
+}
+
+/// Renders a note's frontmatter as a key/value table
+pub fn frontmatter_table(frontmatter: Option<&JsonValue>) -> String {
+    let map = match frontmatter.and_then(|f| f.as_object()) {
+        Some(map) if !map.is_empty() => map,
+        _ => return String::new(),
+    };
+
+    let mut rows = String::new();
+    for (key, value) in map {
+        let value = match value {
+            JsonValue::String(s) => s.clone(),
+            JsonValue::Array(items) => items
+                .iter()
+                .map(|item| match item {
+                    JsonValue::String(s) => s.clone(),
+                    other => other.to_string(),
+                })
+                .collect::<Vec<String>>()
+                .join(", "),
+            JsonValue::Null => String::new(),
+            other => other.to_string(),
+        };
+        rows.push_str(&format!(
+            "<tr><th>{}</th><td>{}</td></tr>\n",
+            escape_html(key),
+            escape_html(&value)
+        ));
+    }
+
+    format!("<table class=\"frontmatter\">\n{}</table>\n", rows)
+}
+
+/// Wraps rendered content into a standalone HTML page
+pub fn page_template(title: &str, css: &str, header: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{}</title>\n{}\n</head>\n<body>\n{}<article>\n<h1 class=\"note-title\">{}</h1>\n{}</article>\n</body>\n</html>\n",
+        escape_html(title),
+        css,
+        header,
+        escape_html(title),
+        body
+    )
+}
+
+/// A note's markdown with embeds expanded and links rewritten for export,
+/// plus the vault files it references
+pub struct PreparedNote {
+    pub markdown: String,
+    pub attachments: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+/// How links to other notes are written in exported markdown
+pub enum LinkStyle<'a> {
+    /// Link to `<note>.<extension>` files, relative to the exported note,
+    /// for the given set of exported notes
+    RelativeFiles {
+        extension: &'a str,
+        exported: &'a HashSet<String>,
+    },
+    /// Link to in-document anchors, for notes bundled into one document
+    Anchors { exported: &'a HashSet<String> },
+}
+
+/// Anchor id a note gets when several notes are bundled in one document
+pub fn note_anchor(relative_path: &str) -> String {
+    format!(
+        "note-{}",
+        outline::slugify(&relative_path.replace('/', "-"))
+    )
+}
+
+/// Expands embeds and rewrites `[[wiki links]]` and attachment embeds into
+/// plain markdown links so any markdown renderer can handle the note
+pub fn prepare_note(
+    relative_path: &str,
+    vault_directory: &str,
+    resolver: &LinkResolver,
+    link_style: &LinkStyle,
+) -> PreparedNote {
+    let expanded = embeds::expand_embeds(relative_path, vault_directory, None);
+    let mut warnings: Vec<String> = expanded
+        .unresolved
+        .iter()
+        .map(|target| format!("{}: could not embed '{}'", relative_path, target))
+        .collect();
+    let mut referenced: Vec<String> = Vec::new();
+
+    let all_attachments = crate::notes::attachments::get_all_attachments(vault_directory);
+    let content = expanded.content;
+    let mut markdown = String::with_capacity(content.len());
+    let mut last_index = 0;
+
+    for link in links::extract_wiki_links(&content) {
+        markdown.push_str(&content[last_index..link.start]);
+        last_index = link.end;
+
+        let (note, subpath) = outline::split_link_target(&link.target);
+        let label = link.alias.clone().unwrap_or_else(|| link.target.clone());
+
+        // Attachments: images stay embedded, other files become links
+        if let Some(attachment) = crate::notes::attachments::resolve_attachment(
+            &all_attachments,
+            relative_path,
+            &note,
+            true,
+        )
+        .attachment
+        {
+            let url = relative_url(relative_path, &attachment.relative_path);
+            if link.embed && attachment.kind == "image" {
+                markdown.push_str(&format!("![{}]({})", attachment.name, url));
+            } else {
+                markdown.push_str(&format!("[{}]({})", label, url));
+            }
+            referenced.push(attachment.relative_path);
+            continue;
+        }
+
+        let target = resolver.resolve_path(&link.target, Some(relative_path));
+        let fragment = match &subpath {
+            outline::Subpath::Heading(heading) => format!("#{}", outline::slugify(heading)),
+            _ => String::new(),
+        };
+
+        let url = match (&target, link_style) {
+            (
+                Some(target),
+                LinkStyle::RelativeFiles {
+                    extension,
+                    exported,
+                },
+            ) if exported.contains(target) => Some(format!(
+                "{}{}",
+                relative_url(relative_path, &with_extension(target, extension)),
+                fragment
+            )),
+            (Some(target), LinkStyle::Anchors { exported }) if exported.contains(target) => {
+                Some(format!("#{}", note_anchor(target)))
+            }
+            _ => None,
+        };
+
+        match url {
+            Some(url) => markdown.push_str(&format!("[{}]({})", label, url)),
+            None => {
+                if target.is_none() {
+                    warnings.push(format!(
+                        "{}: unresolved link '{}'",
+                        relative_path, link.target
+                    ));
+                }
+                markdown.push_str(&format!(
+                    "{}{}</span>",
+                    UNRESOLVED_SPAN,
+                    escape_html(&label)
+                ));
+            }
+        }
+    }
+    markdown.push_str(&content[last_index..]);
+
+    // Markdown image and file links keep their relative paths, but the files
+    // they point to still have to be copied along
+    for reference in crate::notes::attachments::extract_attachment_references(
+        resolver,
+        &markdown,
+        relative_path,
+        &all_attachments,
+    ) {
+        if let Some(attachment) = reference.attachment {
+            referenced.push(attachment.relative_path);
+        }
+    }
+
+    referenced.sort();
+    referenced.dedup();
+
+    PreparedNote {
+        markdown,
+        attachments: referenced,
+        warnings,
+    }
+}
+
+/// Lists the notes an export covers: one note, a folder (recursively) or
+/// the whole vault when neither is given
+pub fn select_notes(
+    vault_directory: &str,
+    relative_path: Option<&str>,
+    folder: Option<&str>,
+) -> Result<NoteSelection, String> {
+    if let Some(note) = relative_path {
+        if !Path::new(vault_directory).join(note).is_file() {
+            return Err(format!("Note does not exist: {}", note));
+        }
+        if encryption::is_encrypted_note(note, vault_directory) {
+            return Err(format!("Note is encrypted: {}", note));
+        }
+        if !formats::is_markdown_path(note) {
+            return Err(format!("Only markdown notes can be exported: {}", note));
+        }
+        return Ok(NoteSelection {
+            notes: vec![note.replace('\\', "/")],
+            skipped: Vec::new(),
+        });
+    }
+
+    let prefix = folder
+        .map(|f| format!("{}/", f.trim_matches('/')))
+        .filter(|f| f != "/");
+
+    let mut notes: Vec<String> = helpers::get_all_notes(vault_directory)
+        .into_iter()
+        .map(|(_, rel_path)| rel_path.replace('\\', "/"))
+        .filter(|rel_path| match &prefix {
+            Some(prefix) => rel_path.starts_with(prefix.as_str()),
+            None => true,
+        })
+        // Encrypted notes stay out of exports, which are plain text
+        .filter(|rel_path| !encryption::is_encrypted_note(rel_path, vault_directory))
+        .collect();
+    notes.sort();
+    let (notes, skipped): (Vec<String>, Vec<String>) = notes
+        .into_iter()
+        .partition(|rel_path| formats::is_markdown_path(rel_path));
+
+    if notes.is_empty() {
+        return Err("No notes to export".to_string());
+    }
+    Ok(NoteSelection { notes, skipped })
+}
+
+/// Lists the notes for a bundled export: the selected notes, followed by the
+/// notes they link to when `include_linked` is set
+pub fn select_bundle(
+    vault_directory: &str,
+    relative_path: Option<&str>,
+    folder: Option<&str>,
+    include_linked: bool,
+    resolver: &LinkResolver,
+) -> Result<NoteSelection, String> {
+    let mut selection = select_notes(vault_directory, relative_path, folder)?;
+    if !include_linked {
+        return Ok(selection);
+    }
+
+    let mut seen: HashSet<String> = selection.notes.iter().cloned().collect();
+    let mut linked: Vec<String> = Vec::new();
+    for rel_path in selection.notes.iter() {
+        let content = helpers::get_content(None, Some(rel_path), Some(vault_directory));
+        for link in links::extract_wiki_links(&content) {
+            if link.embed {
+                continue;
+            }
+            if let Some(target) = resolver.resolve_path(&link.target, Some(rel_path)) {
+                if encryption::is_encrypted_note(&target, vault_directory) {
+                    continue;
+                }
+                if !seen.insert(target.clone()) {
+                    continue;
+                }
+                if formats::is_markdown_path(&target) {
+                    linked.push(target);
+                } else {
+                    selection.skipped.push(target);
+                }
+            }
+        }
+    }
+
+    selection.notes.extend(linked);
+    Ok(selection)
+}
+
+/// Turns a URL relative to a vault-relative file back into a vault path
+pub fn resolve_relative_url(from_file: &str, url: &str) -> String {
+    let decoded = crate::notes::attachments::percent_decode(url);
+    let mut parts: Vec<&str> = from_file.split('/').collect();
+    parts.pop();
+
+    for segment in decoded.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            other => parts.push(other),
+        }
+    }
+    parts.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn vault(notes: &[(&str, &str)]) -> tempfile::TempDir {
+        let vault = tempfile::tempdir().unwrap();
+        for (relative_path, content) in notes {
+            let path = vault.path().join(relative_path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        vault
+    }
+
+    const ENCRYPTED: &str =
+        "---\nencrypted: true\n---\n-----BEGIN ENCRYPTED NOTE-----\nAQ==\n-----END ENCRYPTED NOTE-----\n";
+
+    #[test]
+    fn builds_relative_urls() {
+        assert_eq!(relative_url("a/b/note.html", "a/c/d.html"), "../c/d.html");
+        assert_eq!(
+            relative_url("note.html", "img/cat pic.png"),
+            "img/cat%20pic.png"
+        );
+        assert_eq!(relative_url("a/note.html", "a/other.html"), "other.html");
+        assert_eq!(
+            resolve_relative_url("a/b/note.md", "../img/x%20y.png"),
+            "a/img/x y.png"
+        );
+        assert_eq!(with_extension("folder/note.md", "html"), "folder/note.html");
+    }
+
+    #[test]
+    fn numbers_repeated_heading_ids() {
+        let html = render_markdown("# Intro\n\n## Intro\n\ntext <b>");
+        assert!(html.contains("<h1 id=\"intro\">Intro</h1>"), "{}", html);
+        assert!(html.contains("<h2 id=\"intro-1\">Intro</h2>"), "{}", html);
+        assert_eq!(
+            escape_html("<a href=\"x\">'&'</a>"),
+            "&lt;a href=&quot;x&quot;&gt;&#39;&amp;&#39;&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn rewrites_links_for_exported_notes_only() {
+        let vault = vault(&[
+            (
+                "notes/a.md",
+                "[[b#Part Two|B]], [[c]], [[missing]] and ![[cat.png]]",
+            ),
+            ("notes/b.md", "# Part Two"),
+            ("c.md", "not exported"),
+            ("notes/cat.png", "png"),
+        ]);
+        let path = vault.path().to_str().unwrap();
+        let resolver = LinkResolver::new(path);
+        let exported: HashSet<String> = ["notes/a.md", "notes/b.md"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+
+        let prepared = prepare_note(
+            "notes/a.md",
+            path,
+            &resolver,
+            &LinkStyle::RelativeFiles {
+                extension: "html",
+                exported: &exported,
+            },
+        );
+        assert_eq!(
+            prepared.markdown,
+            "[B](b.html#part-two), <span class=\"unresolved\">c</span>, \
+             <span class=\"unresolved\">missing</span> and ![cat.png](cat.png)"
+        );
+        assert_eq!(prepared.attachments, vec!["notes/cat.png".to_string()]);
+        assert_eq!(
+            prepared.warnings,
+            vec!["notes/a.md: unresolved link 'missing'".to_string()]
+        );
+
+        let prepared = prepare_note(
+            "notes/a.md",
+            path,
+            &resolver,
+            &LinkStyle::Anchors {
+                exported: &exported,
+            },
+        );
+        assert!(prepared
+            .markdown
+            .starts_with(&format!("[B](#{})", note_anchor("notes/b.md"))));
+    }
+
+    #[test]
+    fn selects_markdown_notes_without_encrypted_ones() {
+        let vault = vault(&[
+            ("a/one.md", "[[two]] [[secret]] [[plan]]"),
+            ("a/secret.md", ENCRYPTED),
+            ("a/agenda.org", "* Agenda"),
+            ("b/two.md", "two"),
+            ("b/plan.txt", "plan"),
+        ]);
+        let path = vault.path().to_str().unwrap();
+
+        assert_eq!(
+            select_notes(path, None, Some("a")).unwrap(),
+            NoteSelection {
+                notes: vec!["a/one.md".to_string()],
+                skipped: vec!["a/agenda.org".to_string()],
+            }
+        );
+        let all = select_notes(path, None, None).unwrap();
+        assert_eq!(all.notes.len(), 2);
+        assert_eq!(all.skipped.len(), 2);
+        assert!(select_notes(path, Some("a/secret.md"), None).is_err());
+        assert!(select_notes(path, Some("a/agenda.org"), None).is_err());
+        assert!(select_notes(path, Some("a/nope.md"), None).is_err());
+        assert!(select_notes(path, None, Some("empty")).is_err());
+
+        let resolver = LinkResolver::new(path);
+        assert_eq!(
+            select_bundle(path, Some("a/one.md"), None, true, &resolver).unwrap(),
+            NoteSelection {
+                notes: vec!["a/one.md".to_string(), "b/two.md".to_string()],
+                skipped: vec!["b/plan.txt".to_string()],
+            }
+        );
+    }
+}
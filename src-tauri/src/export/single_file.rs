@@ -0,0 +1,193 @@
+// src/export/single_file.rs
+use super::{
+    escape_html, frontmatter_table, note_anchor, page_template, prepare_note, render_markdown,
+    resolve_relative_url, select_bundle, ExportSummary, LinkStyle, EXPORT_CSS,
+};
+use crate::notes::attachments;
+use crate::notes::helpers;
+use crate::notes::links::LinkResolver;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use regex::Regex;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+/// Replaces `<img src="...">` references to vault files with data URIs so
+/// the page doesn't depend on any other file
+fn inline_images(
+    html: &str,
+    note_path: &str,
+    vault_directory: &str,
+    warnings: &mut Vec<String>,
+) -> String {
+    let image_regex = Regex::new(r#"<img src="([^"]+)""#).unwrap();
+
+    image_regex
+        .replace_all(html, |captures: &regex::Captures| {
+            let src = &captures[1];
+            if src.contains("://") || src.starts_with("data:") {
+                return captures[0].to_string();
+            }
+
+            let vault_path = resolve_relative_url(note_path, src);
+            let extension = Path::new(&vault_path)
+                .extension()
+                .map(|e| e.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            match fs::read(Path::new(vault_directory).join(&vault_path)) {
+                Ok(data) => format!(
+                    "<img src=\"data:{};base64,{}\"",
+                    attachments::mime_type(&extension),
+                    BASE64.encode(data)
+                ),
+                Err(e) => {
+                    warnings.push(format!("Failed to inline {}: {}", vault_path, e));
+                    captures[0].to_string()
+                }
+            }
+        })
+        .to_string()
+}
+
+/// Exports a note with its embeds, and optionally the notes it links to, as
+/// one self-contained HTML file with inlined images and CSS
+pub fn export_single_file(
+    vault_directory: &str,
+    relative_path: &str,
+    output_file: &str,
+    include_linked: bool,
+) -> Result<ExportSummary, String> {
+    let resolver = LinkResolver::new(vault_directory);
+    let selection = select_bundle(
+        vault_directory,
+        Some(relative_path),
+        None,
+        include_linked,
+        &resolver,
+    )?;
+    let notes = &selection.notes;
+    let bundled: HashSet<String> = notes.iter().cloned().collect();
+    let link_style = LinkStyle::Anchors { exported: &bundled };
+
+    println!(
+        "Exporting {} notes to single file {}",
+        notes.len(),
+        output_file
+    );
+
+    let mut warnings: Vec<String> = selection.skipped_warnings();
+    let mut attachments_inlined: HashSet<String> = HashSet::new();
+    let mut body = String::new();
+
+    for (index, rel_path) in notes.iter().enumerate() {
+        let prepared = prepare_note(rel_path, vault_directory, &resolver, &link_style);
+        let rendered = inline_images(
+            &render_markdown(&prepared.markdown),
+            rel_path,
+            vault_directory,
+            &mut warnings,
+        );
+        let frontmatter = helpers::get_frontmatter(None, Some(rel_path), Some(vault_directory));
+
+        // The first note's title is the page title; linked notes get their own
+        let heading = if index == 0 {
+            String::new()
+        } else {
+            let title = helpers::get_title(None, Some(rel_path), Some(vault_directory));
+            format!("<hr />\n<h1>{}</h1>\n", escape_html(&title))
+        };
+
+        body.push_str(&format!(
+            "<section id=\"{}\">\n{}{}{}</section>\n",
+            note_anchor(rel_path),
+            heading,
+            frontmatter_table(frontmatter.as_ref()),
+            rendered
+        ));
+
+        warnings.extend(prepared.warnings);
+        attachments_inlined.extend(prepared.attachments);
+    }
+
+    let title = helpers::get_title(None, Some(relative_path), Some(vault_directory));
+    let css = format!("<style>{}</style>", EXPORT_CSS);
+    let page = page_template(&title, &css, "", &body);
+
+    if let Some(parent) = Path::new(output_file).parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create folder: {}", e))?;
+    }
+    fs::write(output_file, page).map_err(|e| format!("Failed to write {}: {}", output_file, e))?;
+
+    Ok(ExportSummary {
+        output_path: output_file.to_string(),
+        notes_exported: notes.len(),
+        attachments_copied: attachments_inlined.len(),
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundles_linked_notes_and_inlines_images() {
+        let vault = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+        fs::write(
+            vault.path().join("main.md"),
+            "See [[appendix]] ![[dot.png]] ![remote](https://example.com/x.png)",
+        )
+        .unwrap();
+        fs::write(
+            vault.path().join("appendix.md"),
+            "---\ntitle: Appendix\n---\nextra",
+        )
+        .unwrap();
+        fs::write(vault.path().join("dot.png"), [1u8, 2, 3]).unwrap();
+        let page_path = output.path().join("out/main.html");
+
+        let summary = export_single_file(
+            vault.path().to_str().unwrap(),
+            "main.md",
+            page_path.to_str().unwrap(),
+            true,
+        )
+        .unwrap();
+        assert_eq!(summary.notes_exported, 2);
+        assert!(summary.warnings.is_empty(), "{:?}", summary.warnings);
+
+        let page = fs::read_to_string(page_path).unwrap();
+        let anchor = note_anchor("appendix.md");
+        assert!(page.contains(&format!("href=\"#{}\"", anchor)));
+        assert!(page.contains(&format!("<section id=\"{}\">", anchor)));
+        assert!(page.contains("<h1>Appendix</h1>"));
+        assert!(page.contains(&format!(
+            "src=\"data:image/png;base64,{}\"",
+            BASE64.encode([1u8, 2, 3])
+        )));
+        assert!(page.contains("src=\"https://example.com/x.png\""));
+    }
+
+    #[test]
+    fn leaves_linked_notes_out_unless_asked() {
+        let vault = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+        fs::write(vault.path().join("main.md"), "See [[appendix]]").unwrap();
+        fs::write(vault.path().join("appendix.md"), "extra").unwrap();
+        let page_path = output.path().join("main.html");
+
+        let summary = export_single_file(
+            vault.path().to_str().unwrap(),
+            "main.md",
+            page_path.to_str().unwrap(),
+            false,
+        )
+        .unwrap();
+        assert_eq!(summary.notes_exported, 1);
+        let page = fs::read_to_string(page_path).unwrap();
+        assert!(page.contains("<span class=\"unresolved\">appendix</span>"));
+    }
+}
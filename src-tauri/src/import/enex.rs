@@ -0,0 +1,344 @@
+// src/import/enex.rs
+use super::html::{html_to_markdown, LinkRewriter};
+use super::{attachment_embed, frontmatter, string_list, wiki_link, ImportReport, ImportTarget};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde_yaml::Value as YamlValue;
+use std::collections::HashMap;
+use std::fs;
+
+/// A file attached to an Evernote note
+#[derive(Default)]
+struct EnexResource {
+    data: String,
+    mime: String,
+    file_name: String,
+}
+
+/// A note as read from an ENEX file
+#[derive(Default)]
+struct EnexNote {
+    title: String,
+    content: String,
+    created: String,
+    updated: String,
+    tags: Vec<String>,
+    resources: Vec<EnexResource>,
+}
+
+/// Converts Evernote's `20200101T120000Z` timestamps to RFC 3339
+fn convert_timestamp(value: &str) -> String {
+    chrono::NaiveDateTime::parse_from_str(value.trim(), "%Y%m%dT%H%M%SZ")
+        .map(|date| date.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+        .unwrap_or_else(|_| value.trim().to_string())
+}
+
+/// Picks a file extension for a resource without a file name
+fn extension_for_mime(mime: &str) -> &str {
+    match mime {
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        "application/pdf" => "pdf",
+        "audio/mpeg" => "mp3",
+        "audio/wav" => "wav",
+        "video/mp4" => "mp4",
+        _ => "bin",
+    }
+}
+
+/// Reads every note of an ENEX export
+fn parse_enex(xml: &str) -> Result<Vec<EnexNote>, String> {
+    let mut reader = Reader::from_str(xml);
+    let mut notes: Vec<EnexNote> = Vec::new();
+    let mut note: Option<EnexNote> = None;
+    let mut resource: Option<EnexResource> = None;
+    let mut path: Vec<String> = Vec::new();
+    let mut text = String::new();
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(element)) => {
+                let name = String::from_utf8_lossy(element.name().as_ref()).to_string();
+                match name.as_str() {
+                    "note" => note = Some(EnexNote::default()),
+                    "resource" => resource = Some(EnexResource::default()),
+                    _ => {}
+                }
+                path.push(name);
+                text.clear();
+            }
+            Ok(Event::Text(content)) => {
+                let unescaped = content
+                    .unescape()
+                    .map_err(|e| format!("Invalid ENEX text: {}", e))?;
+                text.push_str(&unescaped);
+            }
+            Ok(Event::CData(content)) => {
+                text.push_str(&String::from_utf8_lossy(&content));
+            }
+            Ok(Event::End(_)) => {
+                let name = path.pop().unwrap_or_default();
+                let value = std::mem::take(&mut text);
+
+                if let Some(current) = resource.as_mut() {
+                    match name.as_str() {
+                        "data" => current.data = value,
+                        "mime" => current.mime = value.trim().to_string(),
+                        "file-name" => current.file_name = value.trim().to_string(),
+                        "resource" => {
+                            if let (Some(note), Some(finished)) = (note.as_mut(), resource.take()) {
+                                note.resources.push(finished);
+                            }
+                        }
+                        _ => {}
+                    }
+                } else if let Some(current) = note.as_mut() {
+                    match name.as_str() {
+                        "title" => current.title = value.trim().to_string(),
+                        "content" => current.content = value,
+                        "created" => current.created = convert_timestamp(&value),
+                        "updated" => current.updated = convert_timestamp(&value),
+                        "tag" if !value.trim().is_empty() => {
+                            current.tags.push(value.trim().to_string())
+                        }
+                        "note" => notes.extend(note.take()),
+                        _ => {}
+                    }
+                }
+            }
+            Ok(Event::Eof) => break,
+            Ok(_) => {}
+            Err(e) => {
+                return Err(format!(
+                    "Invalid ENEX file at position {}: {}",
+                    reader.buffer_position(),
+                    e
+                ))
+            }
+        }
+    }
+
+    Ok(notes)
+}
+
+/// Turns `<en-media>` references into embeds of the note's resources and
+/// links between Evernote notes into wiki links
+struct EnexRewriter<'a> {
+    note_path: &'a str,
+    resources: &'a HashMap<String, &'a EnexResource>,
+    titles: &'a HashMap<String, String>,
+    written: HashMap<String, String>,
+    target: &'a ImportTarget,
+    report: &'a mut ImportReport,
+}
+
+impl LinkRewriter for EnexRewriter<'_> {
+    fn link(&mut self, href: &str, text: &str) -> Option<String> {
+        if !href.starts_with("evernote:") {
+            return None;
+        }
+        // Evernote links carry the target's GUID, which the export doesn't
+        // include, so match on the link text instead
+        let linked = self.titles.get(&text.trim().to_lowercase())?;
+        Some(wiki_link(linked, Some(text)))
+    }
+
+    fn image(&mut self, src: &str, _alt: &str) -> Option<String> {
+        let hash = src.strip_prefix("en-media:")?.to_lowercase();
+        if let Some(relative_path) = self.written.get(&hash) {
+            return Some(attachment_embed(relative_path));
+        }
+
+        let Some(resource) = self.resources.get(&hash) else {
+            self.report
+                .warnings
+                .push(format!("{}: missing resource {}", self.note_path, hash));
+            return None;
+        };
+        let data = BASE64
+            .decode(resource.data.split_whitespace().collect::<String>())
+            .ok()?;
+        let file_name = if resource.file_name.is_empty() {
+            format!("{}.{}", hash, extension_for_mime(&resource.mime))
+        } else {
+            resource.file_name.clone()
+        };
+
+        match self.target.write_attachment(&file_name, &data) {
+            Ok(relative_path) => {
+                self.report.attachments_copied += 1;
+                self.written.insert(hash, relative_path.clone());
+                Some(attachment_embed(&relative_path))
+            }
+            Err(e) => {
+                self.report.warnings.push(format!(
+                    "{}: could not save {}: {}",
+                    self.note_path, file_name, e
+                ));
+                None
+            }
+        }
+    }
+}
+
+/// Imports the notes of an Evernote `.enex` export
+pub fn import_enex(
+    source_path: &str,
+    vault_directory: &str,
+    target_folder: Option<&str>,
+) -> Result<ImportReport, String> {
+    let xml = fs::read_to_string(source_path)
+        .map_err(|e| format!("Failed to read {}: {}", source_path, e))?;
+    let notes = parse_enex(&xml)?;
+    let target = ImportTarget::new(vault_directory, target_folder)?;
+    let mut report = ImportReport::default();
+
+    let paths: Vec<String> = notes
+        .iter()
+        .map(|note| target.note_path("", &note.title))
+        .collect();
+    let titles: HashMap<String, String> = notes
+        .iter()
+        .zip(paths.iter())
+        .map(|(note, path)| (note.title.to_lowercase(), path.clone()))
+        .collect();
+
+    for (note, note_path) in notes.iter().zip(paths) {
+        // Resources are referenced by the MD5 hash of their data
+        let resources: HashMap<String, &EnexResource> = note
+            .resources
+            .iter()
+            .filter_map(|resource| {
+                let data = BASE64
+                    .decode(resource.data.split_whitespace().collect::<String>())
+                    .ok()?;
+                Some((format!("{:x}", md5::compute(data)), resource))
+            })
+            .collect();
+
+        let body = {
+            let mut rewriter = EnexRewriter {
+                note_path: &note_path,
+                resources: &resources,
+                titles: &titles,
+                written: HashMap::new(),
+                target: &target,
+                report: &mut report,
+            };
+            html_to_markdown(&note.content, &mut rewriter)
+        };
+
+        let frontmatter = frontmatter(vec![
+            ("title", YamlValue::String(note.title.clone())),
+            ("tags", string_list(&note.tags)),
+            ("created", YamlValue::String(note.created.clone())),
+            ("updated", YamlValue::String(note.updated.clone())),
+        ]);
+        match target.write_note(&note_path, &frontmatter, &body) {
+            Ok(_) => report.notes_created.push(note_path),
+            Err(e) => report.skip(&note.title, format!("Could not write note: {}", e)),
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG: &[u8] = b"not really a png";
+
+    fn enex() -> String {
+        let hash = format!("{:x}", md5::compute(PNG));
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<en-export>
+  <note>
+    <title>Groceries</title>
+    <content><![CDATA[<?xml version="1.0"?><en-note><div><en-todo checked="true"/>milk</div><div><en-todo/>eggs</div><en-media hash="{}" type="image/png"/><div>See <a href="evernote:///view/1/s1/abc/abc/">Recipes</a></div></en-note>]]></content>
+    <created>20240102T030405Z</created>
+    <tag>home</tag>
+    <tag>lists</tag>
+    <resource>
+      <data encoding="base64">{}</data>
+      <mime>image/png</mime>
+    </resource>
+  </note>
+  <note>
+    <title>Recipes</title>
+    <content><![CDATA[<en-note><p>Pancakes</p></en-note>]]></content>
+  </note>
+</en-export>"#,
+            hash,
+            BASE64.encode(PNG)
+        )
+    }
+
+    #[test]
+    fn converts_timestamps_and_mime_types() {
+        assert_eq!(
+            convert_timestamp("20240102T030405Z"),
+            "2024-01-02T03:04:05Z"
+        );
+        assert_eq!(convert_timestamp(" garbage "), "garbage");
+        assert_eq!(extension_for_mime("image/jpeg"), "jpg");
+        assert_eq!(extension_for_mime("application/x-unknown"), "bin");
+    }
+
+    #[test]
+    fn imports_notes_with_tasks_resources_and_links() {
+        let source = tempfile::tempdir().unwrap();
+        let vault = tempfile::tempdir().unwrap();
+        let export = source.path().join("export.enex");
+        fs::write(&export, enex()).unwrap();
+
+        let report = import_enex(
+            export.to_str().unwrap(),
+            vault.path().to_str().unwrap(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            report.notes_created,
+            vec![
+                "Imported/Groceries.md".to_string(),
+                "Imported/Recipes.md".to_string()
+            ]
+        );
+        assert_eq!(report.attachments_copied, 1);
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+
+        let note = fs::read_to_string(vault.path().join("Imported/Groceries.md")).unwrap();
+        assert!(note.contains("title: Groceries"), "{}", note);
+        assert!(note.contains("created: 2024-01-02T03:04:05Z"), "{}", note);
+        assert!(note.contains("- home\n- lists"), "{}", note);
+        assert!(note.contains("- [x] milk"), "{}", note);
+        assert!(note.contains("- [ ] eggs"), "{}", note);
+        assert!(note.contains("[[Imported/Recipes|Recipes]]"), "{}", note);
+
+        let hash = format!("{:x}", md5::compute(PNG));
+        let attachment = format!("Imported/attachments/{}.png", hash);
+        assert!(note.contains(&format!("![[{}]]", attachment)), "{}", note);
+        assert_eq!(fs::read(vault.path().join(attachment)).unwrap(), PNG);
+    }
+
+    #[test]
+    fn rejects_malformed_exports() {
+        let source = tempfile::tempdir().unwrap();
+        let vault = tempfile::tempdir().unwrap();
+        let export = source.path().join("broken.enex");
+        fs::write(&export, "<en-export><note><title>x</note>").unwrap();
+        assert!(import_enex(
+            export.to_str().unwrap(),
+            vault.path().to_str().unwrap(),
+            None
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,663 @@
+// src/import/html.rs
+use super::{attachment_embed, frontmatter, wiki_link, ImportReport, ImportTarget};
+use crate::notes::attachments::percent_decode;
+use regex::Regex;
+use serde_yaml::Value as YamlValue;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Decides how links and images found while converting HTML are written.
+/// Returning None keeps a plain markdown link or image.
+pub trait LinkRewriter {
+    fn link(&mut self, href: &str, text: &str) -> Option<String>;
+    fn image(&mut self, src: &str, alt: &str) -> Option<String>;
+}
+
+/// Decodes the HTML entities that show up in exported notes
+pub fn decode_entities(text: &str) -> String {
+    let entity_regex = Regex::new(r"&(#x[0-9a-fA-F]+|#[0-9]+|[a-zA-Z]+);").unwrap();
+    entity_regex
+        .replace_all(text, |captures: &regex::Captures| {
+            let entity = &captures[1];
+            let decoded = if let Some(hex) = entity.strip_prefix("#x") {
+                u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+            } else if let Some(decimal) = entity.strip_prefix('#') {
+                decimal.parse::<u32>().ok().and_then(char::from_u32)
+            } else {
+                match entity {
+                    "amp" => Some('&'),
+                    "lt" => Some('<'),
+                    "gt" => Some('>'),
+                    "quot" => Some('"'),
+                    "apos" => Some('\''),
+                    "nbsp" => Some(' '),
+                    _ => None,
+                }
+            };
+            decoded
+                .map(|c| c.to_string())
+                .unwrap_or_else(|| captures[0].to_string())
+        })
+        .to_string()
+}
+
+/// Parses the attributes of an HTML start tag
+pub fn parse_attributes(text: &str) -> HashMap<String, String> {
+    let attribute_regex =
+        Regex::new(r#"([a-zA-Z_:][-a-zA-Z0-9_:.]*)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#)
+            .unwrap();
+    attribute_regex
+        .captures_iter(text)
+        .map(|captures| {
+            let value = captures
+                .get(2)
+                .or_else(|| captures.get(3))
+                .or_else(|| captures.get(4))
+                .map(|m| m.as_str())
+                .unwrap_or("");
+            (captures[1].to_lowercase(), decode_entities(value))
+        })
+        .collect()
+}
+
+struct Converter<'a> {
+    output: String,
+    rewriter: &'a mut dyn LinkRewriter,
+    lists: Vec<(bool, usize)>,
+    links: Vec<(String, usize)>,
+    quotes: Vec<usize>,
+    pre_depth: usize,
+    skip_depth: usize,
+    table_row: usize,
+    row_cells: usize,
+}
+
+impl Converter<'_> {
+    fn block_break(&mut self) {
+        let trimmed = self.output.trim_end_matches([' ', '\t']).len();
+        self.output.truncate(trimmed);
+        if self.output.is_empty() || self.output.ends_with("\n\n") {
+            return;
+        }
+        if self.output.ends_with('\n') {
+            self.output.push('\n');
+        } else {
+            self.output.push_str("\n\n");
+        }
+    }
+
+    fn line_break(&mut self) {
+        if !self.output.is_empty() && !self.output.ends_with('\n') {
+            self.output.push('\n');
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.skip_depth > 0 {
+            return;
+        }
+
+        let decoded = decode_entities(text);
+        if self.pre_depth > 0 {
+            self.output.push_str(&decoded);
+            return;
+        }
+
+        // Outside of <pre>, HTML whitespace collapses into single spaces
+        let collapsed = decoded.split_whitespace().collect::<Vec<&str>>().join(" ");
+        if collapsed.is_empty() {
+            if !decoded.is_empty() && !self.output.ends_with([' ', '\n']) && !self.output.is_empty()
+            {
+                self.output.push(' ');
+            }
+            return;
+        }
+        if decoded.starts_with(char::is_whitespace)
+            && !self.output.ends_with([' ', '\n'])
+            && !self.output.is_empty()
+        {
+            self.output.push(' ');
+        }
+        self.output.push_str(&collapsed);
+        if decoded.ends_with(char::is_whitespace) {
+            self.output.push(' ');
+        }
+    }
+
+    fn start_tag(&mut self, name: &str, attributes: &HashMap<String, String>, self_closing: bool) {
+        if self.skip_depth > 0 {
+            if !self_closing && matches!(name, "script" | "style" | "head" | "title") {
+                self.skip_depth += 1;
+            }
+            return;
+        }
+
+        match name {
+            "script" | "style" | "head" | "title" if !self_closing => self.skip_depth += 1,
+            "p" | "div" | "section" | "article" | "en-note" | "body" => self.block_break(),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.block_break();
+                let level = name[1..].parse::<usize>().unwrap_or(1);
+                self.output.push_str(&"#".repeat(level));
+                self.output.push(' ');
+            }
+            "br" => self.output.push('\n'),
+            "hr" => {
+                self.block_break();
+                self.output.push_str("---\n\n");
+            }
+            "ul" | "ol" => {
+                if self.lists.is_empty() {
+                    self.block_break();
+                }
+                self.lists.push((name == "ol", 0));
+            }
+            "li" => {
+                self.line_break();
+                let depth = self.lists.len().max(1);
+                let marker = match self.lists.last_mut() {
+                    Some((true, counter)) => {
+                        *counter += 1;
+                        format!("{}. ", counter)
+                    }
+                    _ => "- ".to_string(),
+                };
+                self.output.push_str(&"    ".repeat(depth - 1));
+                self.output.push_str(&marker);
+            }
+            "strong" | "b" => self.output.push_str("**"),
+            "em" | "i" => self.output.push('*'),
+            "s" | "del" | "strike" => self.output.push_str("~~"),
+            "code" if self.pre_depth == 0 => self.output.push('`'),
+            "pre" => {
+                self.block_break();
+                self.output.push_str("```\n");
+                self.pre_depth += 1;
+            }
+            "blockquote" => {
+                self.block_break();
+                self.quotes.push(self.output.len());
+            }
+            "a" => {
+                let href = attributes.get("href").cloned().unwrap_or_default();
+                self.links.push((href, self.output.len()));
+            }
+            "img" => {
+                let src = attributes.get("src").cloned().unwrap_or_default();
+                let alt = attributes.get("alt").cloned().unwrap_or_default();
+                let image = self
+                    .rewriter
+                    .image(&src, &alt)
+                    .unwrap_or_else(|| format!("![{}]({})", alt, src));
+                self.output.push_str(&image);
+            }
+            "en-media" => {
+                let hash = attributes.get("hash").cloned().unwrap_or_default();
+                let kind = attributes.get("type").cloned().unwrap_or_default();
+                if let Some(media) = self.rewriter.image(&format!("en-media:{}", hash), &kind) {
+                    self.output.push_str(&media);
+                }
+            }
+            "en-todo" => {
+                let checked = attributes
+                    .get("checked")
+                    .map(|c| c == "true")
+                    .unwrap_or(false);
+                if !self.output.ends_with("- ") {
+                    self.line_break();
+                    self.output.push_str("- ");
+                }
+                self.output.push_str(if checked { "[x] " } else { "[ ] " });
+            }
+            "tr" => {
+                self.line_break();
+                self.output.push('|');
+                self.row_cells = 0;
+            }
+            "td" | "th" => {
+                self.output.push(' ');
+                self.row_cells += 1;
+            }
+            "table" => {
+                self.block_break();
+                self.table_row = 0;
+            }
+            _ => {}
+        }
+    }
+
+    fn end_tag(&mut self, name: &str) {
+        if self.skip_depth > 0 {
+            if matches!(name, "script" | "style" | "head" | "title") {
+                self.skip_depth -= 1;
+            }
+            return;
+        }
+
+        match name {
+            "p" | "div" | "section" | "article" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6"
+            | "table" => self.block_break(),
+            "ul" | "ol" => {
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.block_break();
+                }
+            }
+            "strong" | "b" => self.output.push_str("**"),
+            "em" | "i" => self.output.push('*'),
+            "s" | "del" | "strike" => self.output.push_str("~~"),
+            "code" if self.pre_depth == 0 => self.output.push('`'),
+            "pre" => {
+                self.pre_depth = self.pre_depth.saturating_sub(1);
+                self.line_break();
+                self.output.push_str("```\n\n");
+            }
+            "blockquote" => {
+                if let Some(start) = self.quotes.pop() {
+                    let quoted: String = self.output.split_off(start);
+                    let quoted = quoted
+                        .trim()
+                        .lines()
+                        .map(|line| format!("> {}", line).trim_end().to_string())
+                        .collect::<Vec<String>>()
+                        .join("\n");
+                    self.output.push_str(&quoted);
+                    self.block_break();
+                }
+            }
+            "a" => {
+                if let Some((href, start)) = self.links.pop() {
+                    let text = self.output.split_off(start);
+                    let text = text.trim().to_string();
+                    let link = if href.is_empty() || href.starts_with('#') {
+                        text.clone()
+                    } else {
+                        self.rewriter
+                            .link(&href, &text)
+                            .unwrap_or_else(|| format!("[{}]({})", text, href))
+                    };
+                    self.output.push_str(&link);
+                }
+            }
+            "td" | "th" => self.output.push_str(" |"),
+            "tr" => {
+                // Markdown tables need a separator after the header row
+                if self.table_row == 0 {
+                    self.output.push_str("\n|");
+                    self.output
+                        .push_str(&" --- |".repeat(self.row_cells.max(1)));
+                }
+                self.table_row += 1;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Converts an HTML document or fragment to markdown
+pub fn html_to_markdown(html: &str, rewriter: &mut dyn LinkRewriter) -> String {
+    let token_regex = Regex::new(
+        r"(?s)<!--.*?-->|<!\[CDATA\[(.*?)\]\]>|<!DOCTYPE[^>]*>|<\?[^>]*\?>|<(/?)([a-zA-Z][a-zA-Z0-9:-]*)((?:[^>\x22']|\x22[^\x22]*\x22|'[^']*')*?)(/?)>|([^<]+|<)",
+    )
+    .unwrap();
+
+    let mut converter = Converter {
+        output: String::new(),
+        rewriter,
+        lists: Vec::new(),
+        links: Vec::new(),
+        quotes: Vec::new(),
+        pre_depth: 0,
+        skip_depth: 0,
+        table_row: 0,
+        row_cells: 0,
+    };
+
+    for captures in token_regex.captures_iter(html) {
+        if let Some(cdata) = captures.get(1) {
+            converter.text(cdata.as_str());
+        } else if let Some(name) = captures.get(3) {
+            let name = name.as_str().to_lowercase();
+            if &captures[2] == "/" {
+                converter.end_tag(&name);
+            } else {
+                let attributes =
+                    parse_attributes(captures.get(4).map(|m| m.as_str()).unwrap_or(""));
+                let self_closing = &captures[5] == "/";
+                converter.start_tag(&name, &attributes, self_closing);
+            }
+        } else if let Some(text) = captures.get(6) {
+            converter.text(text.as_str());
+        }
+    }
+
+    let blank_lines = Regex::new(r"\n{3,}").unwrap();
+    let markdown = converter
+        .output
+        .lines()
+        .map(|line| line.trim_end())
+        .collect::<Vec<&str>>()
+        .join("\n");
+    blank_lines.replace_all(markdown.trim(), "\n\n").to_string()
+}
+
+/// Finds a document's title: `<title>`, then the first `<h1>`
+pub fn html_title(html: &str) -> Option<String> {
+    let title_regex = Regex::new(r"(?is)<title[^>]*>(.*?)</title>|<h1[^>]*>(.*?)</h1>").unwrap();
+    let tag_regex = Regex::new(r"(?s)<[^>]+>").unwrap();
+
+    let title = title_regex.captures_iter(html).find_map(|captures| {
+        let raw = captures.get(1).or_else(|| captures.get(2))?.as_str();
+        let title = decode_entities(&tag_regex.replace_all(raw, ""));
+        let title = title.split_whitespace().collect::<Vec<&str>>().join(" ");
+        if title.is_empty() {
+            None
+        } else {
+            Some(title)
+        }
+    });
+    title
+}
+
+/// Rewrites links between imported HTML files into wiki links and copies
+/// local images into the vault
+struct HtmlFileRewriter<'a> {
+    source_file: &'a Path,
+    root: &'a Path,
+    notes: &'a HashMap<PathBuf, String>,
+    target: &'a ImportTarget,
+    report: &'a mut ImportReport,
+}
+
+impl HtmlFileRewriter<'_> {
+    /// The file a local URL points to. Files outside the imported folder
+    /// are left alone with a warning.
+    fn local_path(&mut self, url: &str) -> Option<PathBuf> {
+        if url.contains("://") || url.starts_with("mailto:") || url.starts_with("data:") {
+            return None;
+        }
+        let path = percent_decode(url.split(['#', '?']).next().unwrap_or(""));
+        let joined = self.source_file.parent()?.join(path);
+        let canonical = fs::canonicalize(joined).ok()?;
+        if !canonical.starts_with(self.root) {
+            self.report.warnings.push(format!(
+                "{}: link target outside the import folder {}",
+                self.source_file.display(),
+                url
+            ));
+            return None;
+        }
+        Some(canonical)
+    }
+}
+
+impl LinkRewriter for HtmlFileRewriter<'_> {
+    fn link(&mut self, href: &str, text: &str) -> Option<String> {
+        let path = self.local_path(href)?;
+        let note = self.notes.get(&path)?;
+        Some(wiki_link(note, Some(text)))
+    }
+
+    fn image(&mut self, src: &str, _alt: &str) -> Option<String> {
+        let path = self.local_path(src)?;
+        match self.target.copy_attachment(&path) {
+            Ok(relative_path) => {
+                self.report.attachments_copied += 1;
+                Some(attachment_embed(&relative_path))
+            }
+            Err(e) => {
+                self.report.warnings.push(format!(
+                    "{}: could not copy image {}: {}",
+                    self.source_file.display(),
+                    src,
+                    e
+                ));
+                None
+            }
+        }
+    }
+}
+
+/// Collects the `.html` / `.htm` files of a file or folder
+fn collect_html_files(path: &Path, result: &mut Vec<PathBuf>) {
+    if path.is_file() {
+        let is_html = path
+            .extension()
+            .map(|e| e.eq_ignore_ascii_case("html") || e.eq_ignore_ascii_case("htm"))
+            .unwrap_or(false);
+        if is_html {
+            if let Ok(canonical) = fs::canonicalize(path) {
+                result.push(canonical);
+            }
+        }
+    } else if path.is_dir() {
+        if let Ok(entries) = fs::read_dir(path) {
+            for entry in entries.flatten() {
+                collect_html_files(&entry.path(), result);
+            }
+        }
+    }
+}
+
+/// Imports a single HTML file or a folder of HTML files
+pub fn import_html(
+    source_path: &str,
+    vault_directory: &str,
+    target_folder: Option<&str>,
+) -> Result<ImportReport, String> {
+    let source = Path::new(source_path);
+    if !source.exists() {
+        return Err(format!("Import source does not exist: {}", source_path));
+    }
+    let target = ImportTarget::new(vault_directory, target_folder)?;
+    let mut report = ImportReport::default();
+
+    let mut files = Vec::new();
+    collect_html_files(source, &mut files);
+    files.sort();
+    // Folder structure below the imported folder is kept
+    let canonical = fs::canonicalize(source).unwrap_or_else(|_| source.to_path_buf());
+    let base = if canonical.is_dir() {
+        canonical
+    } else {
+        canonical
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default()
+    };
+
+    // Reserve every note's path first so links between them can be rewritten
+    let mut documents: Vec<(PathBuf, String, String)> = Vec::new();
+    let mut notes: HashMap<PathBuf, String> = HashMap::new();
+    for file in files {
+        let html = match fs::read(&file) {
+            Ok(bytes) => String::from_utf8_lossy(&bytes).to_string(),
+            Err(e) => {
+                report.skip(
+                    &file.to_string_lossy(),
+                    format!("Could not read file: {}", e),
+                );
+                continue;
+            }
+        };
+        let title = html_title(&html).unwrap_or_else(|| {
+            file.file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default()
+        });
+        let subfolder = file
+            .parent()
+            .and_then(|parent| parent.strip_prefix(&base).ok())
+            .map(|p| p.to_string_lossy().replace('\\', "/"))
+            .unwrap_or_default();
+
+        let note_path = target.note_path(&subfolder, &title);
+        notes.insert(file.clone(), note_path);
+        documents.push((file, title, html));
+    }
+
+    for (file, title, html) in documents {
+        let note_path = notes[&file].clone();
+        let body = {
+            let mut rewriter = HtmlFileRewriter {
+                source_file: &file,
+                root: &base,
+                notes: &notes,
+                target: &target,
+                report: &mut report,
+            };
+            html_to_markdown(&html, &mut rewriter)
+        };
+        let frontmatter = frontmatter(vec![
+            ("title", YamlValue::String(title)),
+            (
+                "source",
+                YamlValue::String(file.to_string_lossy().to_string()),
+            ),
+        ]);
+
+        match target.write_note(&note_path, &frontmatter, &body) {
+            Ok(_) => report.notes_created.push(note_path),
+            Err(e) => report.skip(
+                &file.to_string_lossy(),
+                format!("Could not write note: {}", e),
+            ),
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Keeps every link and image as plain markdown
+    struct KeepLinks;
+
+    impl LinkRewriter for KeepLinks {
+        fn link(&mut self, _href: &str, _text: &str) -> Option<String> {
+            None
+        }
+
+        fn image(&mut self, _src: &str, _alt: &str) -> Option<String> {
+            None
+        }
+    }
+
+    fn convert(html: &str) -> String {
+        html_to_markdown(html, &mut KeepLinks)
+    }
+
+    #[test]
+    fn converts_block_and_inline_elements() {
+        assert_eq!(
+            convert("<h2>Title</h2><p>Some <b>bold</b>, <i>em</i> and <code>x</code>.</p>"),
+            "## Title\n\nSome **bold**, *em* and `x`."
+        );
+        assert_eq!(
+            convert("<ul><li>one<ol><li>a</li><li>b</li></ol></li><li>two</li></ul>"),
+            "- one\n    1. a\n    2. b\n- two"
+        );
+        assert_eq!(
+            convert("<blockquote><p>quoted</p><p>twice</p></blockquote>"),
+            "> quoted\n>\n> twice"
+        );
+        assert_eq!(
+            convert("<pre><code>let x = 1;\n  indented</code></pre>"),
+            "```\nlet x = 1;\n  indented\n```"
+        );
+        assert_eq!(
+            convert("<table><tr><th>A</th><th>B</th></tr><tr><td>1</td><td>2</td></tr></table>"),
+            "| A | B |\n| --- | --- |\n| 1 | 2 |"
+        );
+    }
+
+    #[test]
+    fn keeps_links_and_drops_scripts() {
+        assert_eq!(
+            convert("<head><title>T</title><style>p{}</style></head><script>x()</script><a href=\"https://e.com\">site</a> <a href=\"#top\">top</a> <img src=\"a.png\" alt=\"A\">"),
+            "[site](https://e.com) top ![A](a.png)"
+        );
+        assert_eq!(
+            decode_entities("&lt;&amp;&#65;&#x42;&nbsp;&bogus;"),
+            "<&AB &bogus;"
+        );
+        assert_eq!(
+            parse_attributes("href='x' data-a=\"&amp;\" b=c")["data-a"],
+            "&"
+        );
+    }
+
+    #[test]
+    fn finds_document_titles() {
+        assert_eq!(
+            html_title("<title> My  <b>Page</b> </title><h1>H</h1>").as_deref(),
+            Some("My Page")
+        );
+        assert_eq!(
+            html_title("<h1 class=\"x\">Heading</h1>").as_deref(),
+            Some("Heading")
+        );
+        assert_eq!(html_title("<p>none</p>"), None);
+    }
+
+    #[test]
+    fn imports_linked_files_and_their_images() {
+        let source = tempfile::tempdir().unwrap();
+        let vault = tempfile::tempdir().unwrap();
+        fs::create_dir_all(source.path().join("sub")).unwrap();
+        fs::write(
+            source.path().join("index.html"),
+            "<title>Home</title><p><a href=\"sub/page%20two.html\">next</a> <img src=\"pic.png\"></p>",
+        )
+        .unwrap();
+        fs::write(
+            source.path().join("sub/page two.html"),
+            "<h1>Second</h1><p>body</p>",
+        )
+        .unwrap();
+        fs::write(source.path().join("pic.png"), "png").unwrap();
+
+        let report = import_html(
+            source.path().to_str().unwrap(),
+            vault.path().to_str().unwrap(),
+            Some("Web"),
+        )
+        .unwrap();
+        assert_eq!(
+            report.notes_created,
+            vec!["Web/Home.md".to_string(), "Web/sub/Second.md".to_string()]
+        );
+        assert_eq!(report.attachments_copied, 1);
+
+        let home = fs::read_to_string(vault.path().join("Web/Home.md")).unwrap();
+        assert!(home.contains("[[Web/sub/Second|next]]"), "{}", home);
+        assert!(home.contains("![[Web/attachments/pic.png]]"), "{}", home);
+    }
+
+    #[test]
+    fn leaves_files_outside_the_import_alone() {
+        let outer = tempfile::tempdir().unwrap();
+        let vault = tempfile::tempdir().unwrap();
+        let source = outer.path().join("site");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(outer.path().join("secret.png"), "png").unwrap();
+        fs::write(
+            source.join("index.html"),
+            "<p><img src=\"../secret.png\"> <a href=\"../secret.png\">key</a></p>",
+        )
+        .unwrap();
+
+        let report = import_html(
+            source.to_str().unwrap(),
+            vault.path().to_str().unwrap(),
+            Some("Web"),
+        )
+        .unwrap();
+        assert_eq!(report.attachments_copied, 0);
+        assert_eq!(report.warnings.len(), 2);
+        assert!(!vault.path().join("Web/attachments/secret.png").exists());
+    }
+}
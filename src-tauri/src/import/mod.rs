@@ -0,0 +1,304 @@
+// src/import/mod.rs
+pub mod enex;
+pub mod html;
+pub mod notion;
+pub mod outliner;
+
+use crate::notes::attachments;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value as YamlValue};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Folder imported notes go to when none is given
+pub const DEFAULT_IMPORT_FOLDER: &str = "Imported";
+
+/// Something that couldn't be converted, and why
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportIssue {
+    pub source: String,
+    pub reason: String,
+}
+
+/// What an import run produced
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ImportReport {
+    pub notes_created: Vec<String>,
+    pub attachments_copied: usize,
+    pub skipped: Vec<ImportIssue>,
+    pub warnings: Vec<String>,
+}
+
+impl ImportReport {
+    pub fn skip(&mut self, source: &str, reason: impl Into<String>) {
+        self.skipped.push(ImportIssue {
+            source: source.to_string(),
+            reason: reason.into(),
+        });
+    }
+}
+
+/// Where imported notes and their attachments are written
+pub struct ImportTarget {
+    pub vault_directory: PathBuf,
+    pub folder: String,
+    pub attachments_folder: String,
+    reserved: RefCell<HashSet<String>>,
+}
+
+impl ImportTarget {
+    pub fn new(vault_directory: &str, folder: Option<&str>) -> Result<Self, String> {
+        let vault_path = PathBuf::from(vault_directory);
+        if !vault_path.is_dir() {
+            return Err(format!(
+                "Vault directory does not exist: {}",
+                vault_directory
+            ));
+        }
+
+        let folder = folder
+            .map(|f| f.trim_matches('/').to_string())
+            .filter(|f| !f.is_empty())
+            .unwrap_or_else(|| DEFAULT_IMPORT_FOLDER.to_string());
+        if folder.split('/').any(|segment| segment == "..") {
+            return Err("Import folder must be inside the vault".to_string());
+        }
+
+        Ok(ImportTarget {
+            vault_directory: vault_path,
+            attachments_folder: format!("{}/{}", folder, attachments::DEFAULT_ATTACHMENTS_FOLDER),
+            folder,
+            reserved: RefCell::new(HashSet::new()),
+        })
+    }
+
+    /// Reserves a unique vault-relative path for a note with the given title
+    /// in a subfolder of the import folder
+    pub fn note_path(&self, subfolder: &str, title: &str) -> String {
+        let folder = if subfolder.is_empty() {
+            self.folder.clone()
+        } else {
+            format!("{}/{}", self.folder, subfolder.trim_matches('/'))
+        };
+        let name = sanitize_file_name(title);
+
+        // Paths are reserved before any note is written, so check both
+        let mut reserved = self.reserved.borrow_mut();
+        let mut counter = 0;
+        loop {
+            let candidate = if counter == 0 {
+                format!("{}/{}.md", folder, name)
+            } else {
+                format!("{}/{} {}.md", folder, name, counter)
+            };
+            let taken = reserved.contains(&candidate.to_lowercase())
+                || self.vault_directory.join(&candidate).exists();
+            if !taken {
+                reserved.insert(candidate.to_lowercase());
+                return candidate;
+            }
+            counter += 1;
+        }
+    }
+
+    /// Writes a note with YAML frontmatter to the reserved path
+    pub fn write_note(
+        &self,
+        relative_path: &str,
+        frontmatter: &Mapping,
+        body: &str,
+    ) -> Result<(), io::Error> {
+        let path = self.vault_directory.join(relative_path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let content = if frontmatter.is_empty() {
+            body.trim().to_string() + "\n"
+        } else {
+            let yaml = serde_yaml::to_string(frontmatter)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            format!("---\n{}---\n\n{}\n", yaml, body.trim())
+        };
+        fs::write(path, content)
+    }
+
+    /// Stores an attachment under a unique name and returns its vault path
+    pub fn write_attachment(&self, file_name: &str, data: &[u8]) -> Result<String, io::Error> {
+        let info = attachments::save_pasted_attachment(
+            data,
+            Some(file_name),
+            None,
+            &self.vault_directory.to_string_lossy(),
+            Some(&self.attachments_folder),
+        )?;
+        Ok(info.relative_path)
+    }
+
+    /// Copies an attachment from the source export and returns its vault path
+    pub fn copy_attachment(&self, source: &Path) -> Result<String, io::Error> {
+        let info = attachments::import_attachment(
+            &source.to_string_lossy(),
+            &self.vault_directory.to_string_lossy(),
+            Some(&self.attachments_folder),
+        )?;
+        Ok(info.relative_path)
+    }
+}
+
+/// Makes a title safe to use as a file name
+pub fn sanitize_file_name(title: &str) -> String {
+    let cleaned: String = title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '#' | '^' | '[' | ']' => ' ',
+            c if c.is_control() => ' ',
+            c => c,
+        })
+        .collect();
+    let cleaned = cleaned.split_whitespace().collect::<Vec<&str>>().join(" ");
+    let cleaned = cleaned.trim_matches('.').trim().to_string();
+
+    if cleaned.is_empty() {
+        "Untitled".to_string()
+    } else {
+        cleaned.chars().take(120).collect()
+    }
+}
+
+/// A wiki link to an imported note, by its vault path without extension
+pub fn wiki_link(relative_path: &str, label: Option<&str>) -> String {
+    let target = relative_path.strip_suffix(".md").unwrap_or(relative_path);
+    match label {
+        Some(label) if !label.is_empty() && label != target => {
+            format!("[[{}|{}]]", target, label.replace(['[', ']', '|'], ""))
+        }
+        _ => format!("[[{}]]", target),
+    }
+}
+
+/// An embed of an imported attachment, by its vault path
+pub fn attachment_embed(relative_path: &str) -> String {
+    format!("![[{}]]", relative_path)
+}
+
+/// Builds a frontmatter mapping from key/value pairs, skipping empty values
+pub fn frontmatter(entries: Vec<(&str, YamlValue)>) -> Mapping {
+    let mut mapping = Mapping::new();
+    for (key, value) in entries {
+        let empty = match &value {
+            YamlValue::Null => true,
+            YamlValue::String(s) => s.is_empty(),
+            YamlValue::Sequence(items) => items.is_empty(),
+            _ => false,
+        };
+        if !empty {
+            mapping.insert(YamlValue::String(key.to_string()), value);
+        }
+    }
+    mapping
+}
+
+/// A list of strings as a YAML sequence
+pub fn string_list(items: &[String]) -> YamlValue {
+    YamlValue::Sequence(
+        items
+            .iter()
+            .map(|item| YamlValue::String(item.clone()))
+            .collect(),
+    )
+}
+
+/// Imports notes exported from another tool. `format` is one of `notion`,
+/// `evernote`, `roam`, `logseq` or `html`.
+pub fn import_notes(
+    format: &str,
+    source_path: &str,
+    vault_directory: &str,
+    target_folder: Option<&str>,
+) -> Result<ImportReport, String> {
+    println!("Importing {} export from {}", format, source_path);
+
+    match format.to_lowercase().as_str() {
+        "notion" => notion::import_notion(source_path, vault_directory, target_folder),
+        "evernote" | "enex" => enex::import_enex(source_path, vault_directory, target_folder),
+        "roam" => outliner::import_outliner(
+            source_path,
+            vault_directory,
+            target_folder,
+            outliner::Outliner::Roam,
+        ),
+        "logseq" => outliner::import_outliner(
+            source_path,
+            vault_directory,
+            target_folder,
+            outliner::Outliner::Logseq,
+        ),
+        "html" => html::import_html(source_path, vault_directory, target_folder),
+        _ => Err(format!("Unsupported import format: {}", format)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitizes_titles_into_file_names() {
+        assert_eq!(sanitize_file_name("a/b: c?"), "a b c");
+        assert_eq!(sanitize_file_name("[[Link]] #tag ^id"), "Link tag id");
+        assert_eq!(sanitize_file_name("..."), "Untitled");
+        assert_eq!(sanitize_file_name(&"x".repeat(200)).len(), 120);
+    }
+
+    #[test]
+    fn builds_links_and_frontmatter() {
+        assert_eq!(wiki_link("Imported/Note.md", None), "[[Imported/Note]]");
+        assert_eq!(
+            wiki_link("Imported/Note.md", Some("the [note]")),
+            "[[Imported/Note|the note]]"
+        );
+        assert_eq!(
+            wiki_link("Imported/Note.md", Some("Imported/Note")),
+            "[[Imported/Note]]"
+        );
+
+        let mapping = frontmatter(vec![
+            ("title", YamlValue::String("T".to_string())),
+            ("empty", YamlValue::String(String::new())),
+            ("tags", string_list(&[])),
+            ("none", YamlValue::Null),
+        ]);
+        assert_eq!(mapping.len(), 1);
+    }
+
+    #[test]
+    fn reserves_unique_note_paths() {
+        let vault = tempfile::tempdir().unwrap();
+        fs::create_dir_all(vault.path().join("Imported")).unwrap();
+        fs::write(vault.path().join("Imported/Taken.md"), "").unwrap();
+        let target = ImportTarget::new(vault.path().to_str().unwrap(), None).unwrap();
+
+        assert_eq!(target.note_path("", "Taken"), "Imported/Taken 1.md");
+        assert_eq!(target.note_path("", "New"), "Imported/New.md");
+        assert_eq!(target.note_path("", "new"), "Imported/new 1.md");
+        assert_eq!(target.note_path("/sub/", "New"), "Imported/sub/New.md");
+        assert_eq!(target.attachments_folder, "Imported/attachments");
+    }
+
+    #[test]
+    fn keeps_imports_inside_the_vault() {
+        let vault = tempfile::tempdir().unwrap();
+        let path = vault.path().to_str().unwrap();
+        assert!(ImportTarget::new(path, Some("../outside")).is_err());
+        assert!(ImportTarget::new(&format!("{}/missing", path), None).is_err());
+        assert_eq!(
+            ImportTarget::new(path, Some("/From/")).unwrap().folder,
+            "From"
+        );
+        assert!(import_notes("word", path, path, None).is_err());
+    }
+}
@@ -0,0 +1,299 @@
+// src/import/notion.rs
+use super::{attachment_embed, frontmatter, wiki_link, ImportReport, ImportTarget};
+use crate::notes::attachments::{self, percent_decode};
+use regex::Regex;
+use serde_yaml::Value as YamlValue;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Removes the 32 character id Notion appends to exported file and folder names
+fn strip_notion_id(name: &str) -> String {
+    let id_regex = Regex::new(r"^(.*?)\s+[0-9a-fA-F]{32}$").unwrap();
+    match id_regex.captures(name) {
+        Some(captures) => captures[1].to_string(),
+        None => name.to_string(),
+    }
+}
+
+/// Collects the markdown pages and database CSVs of a Notion export
+fn collect_files(path: &Path, pages: &mut Vec<PathBuf>, databases: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(path) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let entry_path = entry.path();
+        if entry_path.is_dir() {
+            collect_files(&entry_path, pages, databases);
+            continue;
+        }
+        let extension = entry_path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if let Ok(canonical) = fs::canonicalize(&entry_path) {
+            match extension.as_str() {
+                "md" => pages.push(canonical),
+                "csv" => databases.push(canonical),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Splits a page's leading `# Title` line from the rest of its content
+fn split_title(content: &str) -> (Option<String>, String) {
+    let trimmed = content.trim_start();
+    if let Some(rest) = trimmed.strip_prefix("# ") {
+        let (title, body) = rest.split_once('\n').unwrap_or((rest, ""));
+        return (
+            Some(title.trim().to_string()),
+            body.trim_start().to_string(),
+        );
+    }
+    (None, content.to_string())
+}
+
+/// Imports an unzipped Notion "Markdown & CSV" export folder
+pub fn import_notion(
+    source_path: &str,
+    vault_directory: &str,
+    target_folder: Option<&str>,
+) -> Result<ImportReport, String> {
+    let source = fs::canonicalize(source_path)
+        .map_err(|e| format!("Import source does not exist: {}: {}", source_path, e))?;
+    if !source.is_dir() {
+        return Err("Notion imports need the unzipped export folder".to_string());
+    }
+    let target = ImportTarget::new(vault_directory, target_folder)?;
+    let mut report = ImportReport::default();
+
+    let mut pages = Vec::new();
+    let mut databases = Vec::new();
+    collect_files(&source, &mut pages, &mut databases);
+    pages.sort();
+
+    for database in databases {
+        report.skip(
+            &database.to_string_lossy(),
+            "Notion databases are exported as CSV and are not imported",
+        );
+    }
+
+    // Reserve every page's path first so links between pages can be rewritten
+    let mut documents: Vec<(PathBuf, String, String)> = Vec::new();
+    let mut notes: HashMap<PathBuf, String> = HashMap::new();
+    for page in pages {
+        let content = match fs::read_to_string(&page) {
+            Ok(content) => content,
+            Err(e) => {
+                report.skip(
+                    &page.to_string_lossy(),
+                    format!("Could not read page: {}", e),
+                );
+                continue;
+            }
+        };
+        let (title, body) = split_title(&content);
+        let title = title.unwrap_or_else(|| {
+            page.file_stem()
+                .map(|s| strip_notion_id(&s.to_string_lossy()))
+                .unwrap_or_default()
+        });
+        let subfolder = page
+            .parent()
+            .and_then(|parent| parent.strip_prefix(&source).ok())
+            .map(|relative| {
+                relative
+                    .components()
+                    .map(|c| strip_notion_id(&c.as_os_str().to_string_lossy()))
+                    .collect::<Vec<String>>()
+                    .join("/")
+            })
+            .unwrap_or_default();
+
+        notes.insert(page.clone(), target.note_path(&subfolder, &title));
+        documents.push((page, title, body));
+    }
+
+    let link_regex = Regex::new(r"(!?)\[([^\]]*)\]\(([^)\s]+)\)").unwrap();
+    let mut copied: HashMap<PathBuf, String> = HashMap::new();
+
+    for (page, title, body) in documents {
+        let note_path = notes[&page].clone();
+        let page_dir = page.parent().unwrap_or(&source).to_path_buf();
+
+        let body = link_regex
+            .replace_all(&body, |captures: &regex::Captures| {
+                let original = captures[0].to_string();
+                let url = &captures[3];
+                if url.contains("://") || url.starts_with("mailto:") || url.starts_with('#') {
+                    return original;
+                }
+                let decoded = percent_decode(url.split(['#', '?']).next().unwrap_or(""));
+                let Ok(linked) = fs::canonicalize(page_dir.join(decoded)) else {
+                    report
+                        .warnings
+                        .push(format!("{}: missing link target {}", note_path, url));
+                    return original;
+                };
+                // Pages can't pull in files from outside the export
+                if !linked.starts_with(&source) {
+                    report.warnings.push(format!(
+                        "{}: link target outside the import folder {}",
+                        note_path, url
+                    ));
+                    return original;
+                }
+
+                if let Some(linked_note) = notes.get(&linked) {
+                    return wiki_link(linked_note, Some(&captures[2]));
+                }
+                if linked.is_dir() {
+                    return original;
+                }
+
+                let attachment = match copied.get(&linked) {
+                    Some(attachment) => attachment.clone(),
+                    None => match target.copy_attachment(&linked) {
+                        Ok(attachment) => {
+                            report.attachments_copied += 1;
+                            copied.insert(linked.clone(), attachment.clone());
+                            attachment
+                        }
+                        Err(e) => {
+                            report
+                                .warnings
+                                .push(format!("{}: could not copy {}: {}", note_path, url, e));
+                            return original;
+                        }
+                    },
+                };
+
+                let extension = Path::new(&attachment)
+                    .extension()
+                    .map(|e| e.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                if &captures[1] == "!" || attachments::attachment_kind(&extension) == "image" {
+                    attachment_embed(&attachment)
+                } else {
+                    wiki_link(&attachment, Some(&captures[2]))
+                }
+            })
+            .to_string();
+
+        let frontmatter = frontmatter(vec![("title", YamlValue::String(title))]);
+        match target.write_note(&note_path, &frontmatter, &body) {
+            Ok(_) => report.notes_created.push(note_path),
+            Err(e) => report.skip(
+                &page.to_string_lossy(),
+                format!("Could not write note: {}", e),
+            ),
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "0123456789abcdef0123456789abcdef";
+
+    #[test]
+    fn strips_notion_ids_and_titles() {
+        assert_eq!(
+            strip_notion_id(&format!("Project Plan {}", ID)),
+            "Project Plan"
+        );
+        assert_eq!(strip_notion_id("Plain name"), "Plain name");
+        assert_eq!(
+            split_title("# Title\n\nbody"),
+            (Some("Title".to_string()), "body".to_string())
+        );
+        assert_eq!(split_title("no title"), (None, "no title".to_string()));
+    }
+
+    #[test]
+    fn imports_pages_with_links_and_files() {
+        let source = tempfile::tempdir().unwrap();
+        let vault = tempfile::tempdir().unwrap();
+        let folder = source.path().join(format!("Projects {}", ID));
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(
+            source.path().join(format!("Home {}.md", ID)),
+            format!(
+                "# Home\n\nSee [Plan](Projects%20{id}/Plan%20{id}.md), ![](diagram.png), \
+                 [spec](spec.pdf), [site](https://example.com) and [gone](gone.md)",
+                id = ID
+            ),
+        )
+        .unwrap();
+        fs::write(folder.join(format!("Plan {}.md", ID)), "# Plan\n\nSteps").unwrap();
+        fs::write(source.path().join("diagram.png"), "png").unwrap();
+        fs::write(source.path().join("spec.pdf"), "pdf").unwrap();
+        fs::write(source.path().join(format!("Tasks {}.csv", ID)), "a,b").unwrap();
+
+        let report = import_notion(
+            source.path().to_str().unwrap(),
+            vault.path().to_str().unwrap(),
+            Some("Notion"),
+        )
+        .unwrap();
+        let mut created = report.notes_created.clone();
+        created.sort();
+        assert_eq!(created, vec!["Notion/Home.md", "Notion/Projects/Plan.md"]);
+        assert_eq!(report.attachments_copied, 2);
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.warnings.len(), 1);
+
+        let home = fs::read_to_string(vault.path().join("Notion/Home.md")).unwrap();
+        assert!(
+            home.starts_with("---\ntitle: Home\n---\n\nSee [[Notion/Projects/Plan|Plan]]"),
+            "{}",
+            home
+        );
+        assert!(
+            home.contains("![[Notion/attachments/diagram.png]]"),
+            "{}",
+            home
+        );
+        assert!(
+            home.contains("[[Notion/attachments/spec.pdf|spec]]"),
+            "{}",
+            home
+        );
+        assert!(home.contains("[site](https://example.com)"), "{}", home);
+        assert!(home.contains("[gone](gone.md)"), "{}", home);
+    }
+
+    #[test]
+    fn leaves_files_outside_the_import_alone() {
+        let outer = tempfile::tempdir().unwrap();
+        let vault = tempfile::tempdir().unwrap();
+        let source = outer.path().join("export");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(outer.path().join("secret.png"), "png").unwrap();
+        fs::write(
+            source.join(format!("Home {}.md", ID)),
+            "# Home\n\n![](../secret.png) [key](../secret.png)",
+        )
+        .unwrap();
+
+        let report = import_notion(
+            source.to_str().unwrap(),
+            vault.path().to_str().unwrap(),
+            Some("Notion"),
+        )
+        .unwrap();
+        assert_eq!(report.attachments_copied, 0);
+        assert_eq!(report.warnings.len(), 2);
+        let home = fs::read_to_string(vault.path().join("Notion/Home.md")).unwrap();
+        assert!(
+            home.contains("![](../secret.png) [key](../secret.png)"),
+            "{}",
+            home
+        );
+    }
+}
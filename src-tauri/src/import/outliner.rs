@@ -0,0 +1,345 @@
+// src/import/outliner.rs
+use super::{frontmatter, sanitize_file_name, wiki_link, ImportReport, ImportTarget};
+use regex::Regex;
+use serde_json::Value as JsonValue;
+use serde_yaml::Value as YamlValue;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+
+/// A block of an outliner page with its nested children
+struct Block {
+    uid: String,
+    text: String,
+    children: Vec<Block>,
+}
+
+/// A page of a Roam or Logseq graph
+struct Page {
+    title: String,
+    created: Option<i64>,
+    updated: Option<i64>,
+    blocks: Vec<Block>,
+}
+
+/// Which outliner produced a JSON export
+#[derive(Clone, Copy)]
+pub enum Outliner {
+    Roam,
+    Logseq,
+}
+
+/// Block ids in notes only allow letters, digits and dashes
+fn block_id(uid: &str) -> String {
+    uid.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+fn parse_blocks(value: Option<&JsonValue>, outliner: Outliner) -> Vec<Block> {
+    let (text_key, uid_key) = match outliner {
+        Outliner::Roam => ("string", "uid"),
+        Outliner::Logseq => ("content", "id"),
+    };
+
+    value
+        .and_then(|v| v.as_array())
+        .map(|blocks| {
+            blocks
+                .iter()
+                .map(|block| Block {
+                    uid: block
+                        .get(uid_key)
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("")
+                        .to_string(),
+                    text: block
+                        .get(text_key)
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("")
+                        .to_string(),
+                    children: parse_blocks(block.get("children"), outliner),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Reads the pages of a Roam (`[{title, children}]`) or Logseq
+/// (`{blocks: [{page-name, children}]}`) JSON export
+fn parse_pages(json: &JsonValue, outliner: Outliner) -> Result<Vec<Page>, String> {
+    let pages = match outliner {
+        Outliner::Roam => json.as_array(),
+        Outliner::Logseq => json.get("blocks").and_then(|b| b.as_array()),
+    }
+    .ok_or_else(|| "Unexpected JSON layout for this export format".to_string())?;
+
+    Ok(pages
+        .iter()
+        .filter_map(|page| {
+            let title = ["title", "original-name", "page-name"]
+                .iter()
+                .find_map(|key| page.get(*key).and_then(|v| v.as_str()))?
+                .to_string();
+            Some(Page {
+                title,
+                created: page.get("create-time").and_then(|v| v.as_i64()),
+                updated: page.get("edit-time").and_then(|v| v.as_i64()),
+                blocks: parse_blocks(page.get("children"), outliner),
+            })
+        })
+        .collect())
+}
+
+fn collect_uids(blocks: &[Block], page: &str, result: &mut HashMap<String, String>) {
+    for block in blocks {
+        if !block.uid.is_empty() {
+            result.insert(block.uid.clone(), page.to_string());
+        }
+        collect_uids(&block.children, page, result);
+    }
+}
+
+fn collect_references(blocks: &[Block], reference_regex: &Regex, result: &mut HashSet<String>) {
+    for block in blocks {
+        for captures in reference_regex.captures_iter(&block.text) {
+            result.insert(captures[1].to_string());
+        }
+        collect_references(&block.children, reference_regex, result);
+    }
+}
+
+/// Converts an epoch timestamp in milliseconds to RFC 3339
+fn format_timestamp(millis: Option<i64>) -> YamlValue {
+    millis
+        .and_then(chrono::DateTime::from_timestamp_millis)
+        .map(|date| YamlValue::String(date.format("%Y-%m-%dT%H:%M:%SZ").to_string()))
+        .unwrap_or(YamlValue::Null)
+}
+
+/// Rewrites block references and page links in outliner pages
+struct PageWriter<'a> {
+    pages: &'a HashMap<String, String>,
+    blocks: &'a HashMap<String, String>,
+    referenced: &'a HashSet<String>,
+    reference_regex: Regex,
+    page_link_regex: Regex,
+    warnings: Vec<String>,
+}
+
+impl PageWriter<'_> {
+    fn convert_text(&mut self, note_path: &str, text: &str) -> String {
+        let mut missing: Vec<String> = Vec::new();
+        let text = self
+            .reference_regex
+            .replace_all(text, |captures: &regex::Captures| {
+                let uid = &captures[1];
+                match self.blocks.get(uid) {
+                    Some(page) => {
+                        let target = page.strip_suffix(".md").unwrap_or(page);
+                        format!("[[{}#^{}]]", target, block_id(uid))
+                    }
+                    None => {
+                        missing.push(uid.to_string());
+                        captures[0].to_string()
+                    }
+                }
+            })
+            .to_string();
+        for uid in missing {
+            self.warnings.push(format!(
+                "{}: unknown block reference (({}))",
+                note_path, uid
+            ));
+        }
+
+        self.page_link_regex
+            .replace_all(&text, |captures: &regex::Captures| {
+                match self.pages.get(&captures[1].to_lowercase()) {
+                    Some(page) => wiki_link(page, Some(&captures[1])),
+                    None => captures[0].to_string(),
+                }
+            })
+            .to_string()
+    }
+
+    /// Writes blocks as nested bullets; referenced blocks get a `^id` marker
+    fn write_blocks(
+        &mut self,
+        note_path: &str,
+        blocks: &[Block],
+        depth: usize,
+        output: &mut String,
+    ) {
+        for block in blocks {
+            let indent = "    ".repeat(depth);
+            let text = self.convert_text(note_path, block.text.trim());
+            let mut lines: Vec<String> = text.lines().map(|line| line.to_string()).collect();
+            if lines.is_empty() {
+                lines.push(String::new());
+            }
+            if self.referenced.contains(&block.uid) {
+                if let Some(last) = lines.last_mut() {
+                    last.push_str(&format!(" ^{}", block_id(&block.uid)));
+                }
+            }
+
+            for (index, line) in lines.iter().enumerate() {
+                let marker = if index == 0 { "- " } else { "  " };
+                output.push_str(format!("{}{}{}", indent, marker, line).trim_end());
+                output.push('\n');
+            }
+            self.write_blocks(note_path, &block.children, depth + 1, output);
+        }
+    }
+}
+
+/// Imports a Roam Research or Logseq JSON export
+pub fn import_outliner(
+    source_path: &str,
+    vault_directory: &str,
+    target_folder: Option<&str>,
+    outliner: Outliner,
+) -> Result<ImportReport, String> {
+    let content = fs::read_to_string(source_path)
+        .map_err(|e| format!("Failed to read {}: {}", source_path, e))?;
+    let json: JsonValue =
+        serde_json::from_str(&content).map_err(|e| format!("Invalid JSON export: {}", e))?;
+    let pages = parse_pages(&json, outliner)?;
+    let target = ImportTarget::new(vault_directory, target_folder)?;
+    let mut report = ImportReport::default();
+
+    // Namespaced pages (`Project/Notes`) become folders
+    let mut page_paths: HashMap<String, String> = HashMap::new();
+    let mut paths: Vec<String> = Vec::new();
+    for page in pages.iter() {
+        let (namespace, name) = page.title.rsplit_once('/').unwrap_or(("", &page.title));
+        let subfolder = namespace
+            .split('/')
+            .filter(|segment| !segment.trim().is_empty())
+            .map(sanitize_file_name)
+            .collect::<Vec<String>>()
+            .join("/");
+        let path = target.note_path(&subfolder, name);
+        page_paths.insert(page.title.to_lowercase(), path.clone());
+        paths.push(path);
+    }
+
+    let reference_regex = Regex::new(r"\(\(([A-Za-z0-9_-]+)\)\)").unwrap();
+    let mut block_pages: HashMap<String, String> = HashMap::new();
+    let mut referenced: HashSet<String> = HashSet::new();
+    for (page, path) in pages.iter().zip(paths.iter()) {
+        collect_uids(&page.blocks, path, &mut block_pages);
+        collect_references(&page.blocks, &reference_regex, &mut referenced);
+    }
+
+    let mut writer = PageWriter {
+        pages: &page_paths,
+        blocks: &block_pages,
+        referenced: &referenced,
+        reference_regex,
+        page_link_regex: Regex::new(r"\[\[([^\[\]|#^]+)\]\]").unwrap(),
+        warnings: Vec::new(),
+    };
+
+    for (page, note_path) in pages.iter().zip(paths) {
+        let mut body = String::new();
+        writer.write_blocks(&note_path, &page.blocks, 0, &mut body);
+
+        let frontmatter = frontmatter(vec![
+            ("title", YamlValue::String(page.title.clone())),
+            ("created", format_timestamp(page.created)),
+            ("updated", format_timestamp(page.updated)),
+        ]);
+        match target.write_note(&note_path, &frontmatter, &body) {
+            Ok(_) => report.notes_created.push(note_path),
+            Err(e) => report.skip(&page.title, format!("Could not write note: {}", e)),
+        }
+    }
+
+    report.warnings.extend(writer.warnings);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn import(json: &str, outliner: Outliner) -> (tempfile::TempDir, ImportReport) {
+        let source = tempfile::tempdir().unwrap();
+        let vault = tempfile::tempdir().unwrap();
+        let export = source.path().join("export.json");
+        fs::write(&export, json).unwrap();
+        let report = import_outliner(
+            export.to_str().unwrap(),
+            vault.path().to_str().unwrap(),
+            None,
+            outliner,
+        )
+        .unwrap();
+        (vault, report)
+    }
+
+    #[test]
+    fn imports_roam_pages_with_block_references() {
+        let json = r#"[
+            {"title": "Daily", "create-time": 1704067200000, "children": [
+                {"uid": "abc_1", "string": "Idea worth quoting", "children": [
+                    {"uid": "c2", "string": "detail\nsecond line"}
+                ]},
+                {"uid": "c3", "string": "links to [[Project/Notes]] and [[Nowhere]]"}
+            ]},
+            {"title": "Project/Notes", "children": [
+                {"uid": "d1", "string": "quoting ((abc_1)) and ((missing))"}
+            ]}
+        ]"#;
+        let (vault, report) = import(json, Outliner::Roam);
+        assert_eq!(
+            report.notes_created,
+            vec!["Imported/Daily.md", "Imported/Project/Notes.md"]
+        );
+        assert_eq!(
+            report.warnings,
+            vec!["Imported/Project/Notes.md: unknown block reference ((missing))"]
+        );
+
+        let daily = fs::read_to_string(vault.path().join("Imported/Daily.md")).unwrap();
+        assert!(daily.contains("created: 2024-01-01T00:00:00Z"), "{}", daily);
+        assert!(
+            daily.contains(
+                "- Idea worth quoting ^abc-1\n    - detail\n      second line\n\
+                 - links to [[Imported/Project/Notes|Project/Notes]] and [[Nowhere]]"
+            ),
+            "{}",
+            daily
+        );
+        let notes = fs::read_to_string(vault.path().join("Imported/Project/Notes.md")).unwrap();
+        assert!(
+            notes.contains("- quoting [[Imported/Daily#^abc-1]] and ((missing))"),
+            "{}",
+            notes
+        );
+    }
+
+    #[test]
+    fn imports_logseq_pages() {
+        let json = r#"{"blocks": [
+            {"page-name": "logseq page", "children": [{"id": "x", "content": "hello"}]}
+        ]}"#;
+        let (vault, report) = import(json, Outliner::Logseq);
+        assert_eq!(report.notes_created, vec!["Imported/logseq page.md"]);
+        let page = fs::read_to_string(vault.path().join("Imported/logseq page.md")).unwrap();
+        assert!(page.ends_with("- hello\n"), "{}", page);
+    }
+
+    #[test]
+    fn rejects_the_wrong_layout() {
+        let source = tempfile::tempdir().unwrap();
+        let vault = tempfile::tempdir().unwrap();
+        let export = source.path().join("export.json");
+        fs::write(&export, r#"{"blocks": []}"#).unwrap();
+        let path = export.to_str().unwrap();
+        let vault = vault.path().to_str().unwrap();
+        assert!(import_outliner(path, vault, None, Outliner::Roam).is_err());
+        assert!(import_outliner(path, vault, None, Outliner::Logseq).is_ok());
+    }
+}
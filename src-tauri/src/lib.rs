@@ -1,16 +1,58 @@
 // src-tauri/src/lib.rs
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::http::{Response, StatusCode};
+use tauri::{AppHandle, Emitter, Manager};
+mod db;
+mod export;
+mod import;
 mod notes; // Add this to import our new module
+use notes::ast;
+use notes::attachments;
+use notes::embeds;
+use notes::encryption;
+use notes::flashcards;
+use notes::folders;
+use notes::health;
 use notes::helpers; // Import the helpers module
+use notes::links;
+use notes::lint;
+use notes::outline;
+use notes::scan;
 
 // Define a struct to return note data to the frontend
 #[derive(Debug, Serialize, Deserialize)]
 struct NoteInfo {
+    id: Option<String>,
     title: String,
+    aliases: Vec<String>,
     absolute_path: String,
     relative_path: String,
+    size: i64,
+    mtime: i64,
+    ctime: i64,
+    word_count: i64,
+    tags: Vec<String>,
+}
+
+impl From<db::cache::CachedNote> for NoteInfo {
+    fn from(note: db::cache::CachedNote) -> Self {
+        NoteInfo {
+            id: note.id,
+            title: note.title,
+            aliases: note.aliases,
+            absolute_path: note.absolute_path,
+            relative_path: note.relative_path,
+            size: note.size,
+            mtime: note.mtime,
+            ctime: note.ctime,
+            word_count: note.word_count,
+            tags: note.tags,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -64,36 +106,193 @@ fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
-// Add a new command to get all notes
+/// Lists the notes of a vault from the metadata cache, so large vaults open
+/// instantly. A vault seen for the first time is scanned before returning;
+/// otherwise the cache is reconciled with the disk in the background and
+/// `note-cache-updated` is emitted when anything changed.
 #[tauri::command]
-fn get_notes(vault_directory: &str) -> Vec<NoteInfo> {
-    let notes = helpers::get_all_notes(vault_directory);
-    let mut result = Vec::new();
-
-    for (absolute_path, relative_path) in notes {
-        let title = helpers::get_title(
-            Some(&absolute_path),
-            Some(&relative_path),
-            Some(vault_directory),
-        );
+async fn get_notes(app: AppHandle, vault_directory: String) -> Result<Vec<NoteInfo>, String> {
+    let pool = db::pool(&app).await?;
+    let cached = db::cache::has_vault(&pool, &vault_directory)
+        .await
+        .map_err(|e| format!("Failed to read note cache: {}", e))?;
 
-        result.push(NoteInfo {
-            title,
-            absolute_path,
-            relative_path,
+    if cached {
+        let background_pool = pool.clone();
+        let vault = vault_directory.clone();
+        tauri::async_runtime::spawn(async move {
+            match scan_vault(&app, &background_pool, &vault).await {
+                Ok(summary) if summary.changed() => {
+                    let _ = app.emit("note-cache-updated", summary);
+                }
+                Ok(_) => {}
+                Err(e) => println!("Failed to sync note cache: {}", e),
+            }
         });
+    } else {
+        scan_vault(&app, &pool, &vault_directory).await?;
+    }
+
+    let notes = db::cache::load_notes(&pool, &vault_directory)
+        .await
+        .map_err(|e| format!("Failed to read note cache: {}", e))?;
+    Ok(notes.into_iter().map(NoteInfo::from).collect())
+}
+
+/// One page of the note list, sorted and filtered on the backend so the
+/// webview only holds what it shows
+#[tauri::command]
+async fn list_notes(
+    app: AppHandle,
+    vault_directory: String,
+    query: Option<db::listing::NoteQuery>,
+) -> Result<db::listing::NotePage, String> {
+    let pool = db::pool(&app).await?;
+    if !db::cache::has_vault(&pool, &vault_directory)
+        .await
+        .map_err(|e| format!("Failed to read note cache: {}", e))?
+    {
+        scan_vault(&app, &pool, &vault_directory).await?;
+    }
+    db::listing::list_notes(&pool, &vault_directory, &query.unwrap_or_default()).await
+}
+
+/// Nodes and edges for the graph view. Laying out a large graph takes a
+/// while, so it runs off the async runtime.
+#[tauri::command]
+async fn get_graph(
+    app: AppHandle,
+    vault_directory: String,
+    query: Option<db::graph::GraphQuery>,
+) -> Result<db::graph::Graph, String> {
+    let pool = db::pool(&app).await?;
+    if !db::cache::has_vault(&pool, &vault_directory)
+        .await
+        .map_err(|e| format!("Failed to read note cache: {}", e))?
+    {
+        scan_vault(&app, &pool, &vault_directory).await?;
+    }
+    let data = db::graph::load_graph_data(&pool, &vault_directory).await?;
+    let query = query.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || {
+        db::graph::build_graph(&vault_directory, data, &query)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// The stable ID of a note, which stays the same when the note is renamed
+/// or moved. `None` if there is no such note in the cache.
+#[tauri::command]
+async fn get_note_id(
+    app: AppHandle,
+    vault_directory: String,
+    relative_path: String,
+) -> Result<Option<String>, String> {
+    let pool = db::pool(&app).await?;
+    if !db::cache::has_vault(&pool, &vault_directory)
+        .await
+        .map_err(|e| format!("Failed to read note cache: {}", e))?
+    {
+        scan_vault(&app, &pool, &vault_directory).await?;
+    }
+    db::note_ids::id_for_path(&pool, &vault_directory, &relative_path).await
+}
+
+/// The current relative path of the note with a stable ID, or `None` if the
+/// note is gone
+#[tauri::command]
+async fn resolve_note_id(
+    app: AppHandle,
+    vault_directory: String,
+    note_id: String,
+) -> Result<Option<String>, String> {
+    let pool = db::pool(&app).await?;
+    if !db::cache::has_vault(&pool, &vault_directory)
+        .await
+        .map_err(|e| format!("Failed to read note cache: {}", e))?
+    {
+        scan_vault(&app, &pool, &vault_directory).await?;
     }
+    db::note_ids::path_for_id(&pool, &vault_directory, &note_id).await
+}
+
+/// Reconciles the note cache with the files on disk
+#[tauri::command]
+async fn sync_note_cache(
+    app: AppHandle,
+    vault_directory: String,
+) -> Result<db::cache::CacheSyncSummary, String> {
+    let pool = db::pool(&app).await?;
+    scan_vault(&app, &pool, &vault_directory).await
+}
+
+/// Stops scanning a vault, or every vault when none is given. Returns the
+/// number of scans cancelled.
+#[tauri::command]
+fn cancel_vault_scan(app: AppHandle, vault_directory: Option<String>) -> usize {
+    app.state::<scan::ActiveScans>()
+        .cancel(vault_directory.as_deref())
+}
 
+/// Syncs the note cache on the scan thread pool, streaming progress to the
+/// webview. Scans of other vaults are cancelled: the user has moved on.
+async fn scan_vault(
+    app: &AppHandle,
+    pool: &sqlx::Pool<sqlx::Sqlite>,
+    vault_directory: &str,
+) -> Result<db::cache::CacheSyncSummary, String> {
+    let scans = app.state::<scan::ActiveScans>();
+    let token = scans.start(vault_directory);
+
+    let emitter = app.clone();
+    let progress: db::cache::ProgressCallback = Arc::new(move |progress| {
+        let _ = emitter.emit(db::cache::SCAN_PROGRESS_EVENT, progress);
+    });
+    let result = db::cache::sync_vault(pool, vault_directory, &token, Some(progress)).await;
+    scans.finish(&token);
     result
 }
 
+/// Re-syncs the note cache after the app moved or deleted files, and lets
+/// the webview know
+async fn refresh_after_vault_change(app: &AppHandle, vault_directory: &str) -> Result<(), String> {
+    let pool = db::pool(app).await?;
+    let summary = scan_vault(app, &pool, vault_directory).await?;
+    if summary.changed() {
+        let _ = app.emit("note-cache-updated", summary);
+    }
+    Ok(())
+}
+
+/// The body of a note. Encrypted notes are decrypted here, and only while
+/// their vault is unlocked.
+fn read_note(
+    unlocked: &encryption::UnlockedVaults,
+    relative_path: &str,
+    vault_directory: &str,
+) -> Result<String, String> {
+    let content = helpers::get_content(None, Some(relative_path), Some(vault_directory));
+    if !encryption::is_encrypted(&content) {
+        return Ok(content);
+    }
+    let key = unlocked
+        .key(vault_directory)
+        .ok_or_else(|| "Note is encrypted: unlock the vault to read it".to_string())?;
+    encryption::decrypt_body(&key, &content)
+}
+
 #[tauri::command]
-fn get_note_content(relative_path: &str, vault_directory: &str) -> String {
+fn get_note_content(
+    unlocked: tauri::State<'_, encryption::UnlockedVaults>,
+    relative_path: &str,
+    vault_directory: &str,
+) -> Result<String, String> {
     println!(
         "Called get_note_content with: {}, {}",
         relative_path, vault_directory
     );
-    helpers::get_content(None, Some(relative_path), Some(vault_directory))
+    read_note(&unlocked, relative_path, vault_directory)
 }
 
 #[tauri::command]
@@ -119,50 +318,968 @@ async fn check_and_create_directory(path: &str) -> Result<bool, String> {
 }
 
 #[tauri::command]
-fn update_note_content(
-    absolute_path: Option<&str>,
-    relative_path: Option<&str>,
+async fn update_note_content(
+    app: AppHandle,
+    absolute_path: Option<String>,
+    relative_path: Option<String>,
+    vault_directory: String,
+    new_content: String,
+) -> Result<bool, String> {
+    let current = helpers::get_content(
+        absolute_path.as_deref(),
+        relative_path.as_deref(),
+        Some(&vault_directory),
+    );
+    // An encrypted note stays encrypted, and its edits aren't counted
+    let encrypted = encryption::is_encrypted(&current);
+    let new_content = if encrypted {
+        let key = app
+            .state::<encryption::UnlockedVaults>()
+            .key(&vault_directory)
+            .ok_or_else(|| "Note is encrypted: unlock the vault to edit it".to_string())?;
+        encryption::encrypt_body(&key, &new_content)?
+    } else {
+        new_content
+    };
+    let words_before = db::stats::count_words(&current);
+    helpers::update_note(
+        absolute_path.as_deref(),
+        relative_path.as_deref(),
+        Some(&vault_directory),
+        &new_content,
+    )
+    .map_err(|e| format!("Failed to update note: {}", e))?;
+
+    // Keep the cached title, links and tags in step with the edit, and count
+    // the words written towards today's activity
+    let relative_path = match (relative_path, absolute_path) {
+        (Some(relative_path), _) => Some(relative_path),
+        (None, Some(absolute_path)) => Path::new(&absolute_path)
+            .strip_prefix(&vault_directory)
+            .ok()
+            .map(|p| p.to_string_lossy().replace('\\', "/")),
+        (None, None) => None,
+    };
+    if let Some(relative_path) = relative_path {
+        let pool = db::pool(&app).await?;
+        if let Err(e) = db::cache::refresh_note(&pool, &vault_directory, &relative_path).await {
+            println!("Failed to refresh the cache for {}: {}", relative_path, e);
+        }
+        if encrypted {
+            return Ok(true);
+        }
+        let words_after = db::stats::count_words(&helpers::get_content(
+            None,
+            Some(&relative_path),
+            Some(&vault_directory),
+        ));
+        if let Err(e) = db::stats::record_save(
+            &pool,
+            &vault_directory,
+            &relative_path,
+            words_before,
+            words_after,
+        )
+        .await
+        {
+            println!(
+                "Failed to record writing activity for {}: {}",
+                relative_path, e
+            );
+        }
+    }
+
+    Ok(true)
+}
+
+/// Whether a vault has encryption set up and how long its key stays unlocked
+#[derive(Debug, Serialize, Deserialize)]
+struct EncryptionStatus {
+    set_up: bool,
+    unlocked: bool,
+    /// Seconds of inactivity left before the vault locks
+    locks_in: Option<u64>,
+}
+
+#[tauri::command]
+fn get_encryption_status(
+    unlocked: tauri::State<'_, encryption::UnlockedVaults>,
+    vault_directory: &str,
+) -> EncryptionStatus {
+    let remaining = unlocked.remaining(vault_directory);
+    EncryptionStatus {
+        set_up: encryption::has_key(vault_directory),
+        unlocked: remaining.is_some(),
+        locks_in: remaining.map(|remaining| remaining.as_secs()),
+    }
+}
+
+/// Keeps a vault's key in memory until it goes unused for the configured
+/// timeout; then the lock timer emits `encryption-locked`
+async fn keep_unlocked(
+    app: &AppHandle,
     vault_directory: &str,
-    new_content: &str,
+    key: encryption::VaultKey,
+) -> Result<(), String> {
+    let pool = db::pool(app).await?;
+    let settings = db::settings::effective_settings(&pool, vault_directory).await?;
+    let timeout =
+        Duration::from_secs(settings.settings.encryption.lock_timeout_minutes as u64 * 60);
+    app.state::<encryption::UnlockedVaults>()
+        .unlock(vault_directory, key, timeout);
+    Ok(())
+}
+
+/// Sets the passphrase of a vault without one, and unlocks it
+#[tauri::command]
+async fn set_vault_passphrase(
+    app: AppHandle,
+    vault_directory: String,
+    passphrase: String,
+    confirmation: String,
+) -> Result<(), String> {
+    let vault = vault_directory.clone();
+    let passphrase = zeroize::Zeroizing::new(passphrase);
+    let confirmation = zeroize::Zeroizing::new(confirmation);
+    let key = tauri::async_runtime::spawn_blocking(move || {
+        encryption::set_passphrase(&vault, &passphrase, &confirmation)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    keep_unlocked(&app, &vault_directory, key).await
+}
+
+/// Unlocks a vault's encrypted notes with its passphrase
+#[tauri::command]
+async fn unlock_vault(
+    app: AppHandle,
+    vault_directory: String,
+    passphrase: String,
+) -> Result<(), String> {
+    let vault = vault_directory.clone();
+    let passphrase = zeroize::Zeroizing::new(passphrase);
+    let key =
+        tauri::async_runtime::spawn_blocking(move || encryption::unlock_key(&vault, &passphrase))
+            .await
+            .map_err(|e| e.to_string())??;
+    keep_unlocked(&app, &vault_directory, key).await
+}
+
+/// Forgets the key of a vault, or of every vault when none is given
+#[tauri::command]
+fn lock_vault(app: AppHandle, vault_directory: Option<String>) -> usize {
+    let locked = app
+        .state::<encryption::UnlockedVaults>()
+        .lock(vault_directory.as_deref());
+    for vault in &locked {
+        let _ = app.emit(encryption::LOCKED_EVENT, vault);
+    }
+    locked.len()
+}
+
+/// Encrypts every markdown note at or under a path: a single note, or a
+/// folder of them. Returns the number of notes encrypted.
+#[tauri::command]
+async fn encrypt_notes(
+    app: AppHandle,
+    vault_directory: String,
+    relative_path: String,
+) -> Result<usize, String> {
+    let key = app
+        .state::<encryption::UnlockedVaults>()
+        .key(&vault_directory)
+        .ok_or_else(|| "Unlock the vault before encrypting notes".to_string())?;
+
+    let vault = vault_directory.clone();
+    let encrypted = tauri::async_runtime::spawn_blocking(move || {
+        let path = relative_path.trim_matches('/').to_string();
+        let notes: Vec<String> = if Path::new(&vault).join(&path).is_dir() {
+            let prefix = format!("{}/", path);
+            helpers::get_all_notes(&vault)
+                .into_iter()
+                .map(|(_, rel_path)| rel_path.replace('\\', "/"))
+                .filter(|rel_path| path.is_empty() || rel_path.starts_with(&prefix))
+                .filter(|rel_path| notes::formats::is_markdown_path(rel_path))
+                .collect()
+        } else {
+            vec![path]
+        };
+
+        let mut encrypted = 0;
+        for note in notes {
+            if encryption::encrypt_note(&key, &note, &vault)? {
+                encrypted += 1;
+            }
+        }
+        Ok::<usize, String>(encrypted)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    refresh_after_vault_change(&app, &vault_directory).await?;
+    Ok(encrypted)
+}
+
+/// Decrypts a note for good, leaving it in plain text
+#[tauri::command]
+async fn decrypt_note(
+    app: AppHandle,
+    vault_directory: String,
+    relative_path: String,
 ) -> Result<bool, String> {
-    match helpers::update_note(
-        absolute_path,
+    let key = app
+        .state::<encryption::UnlockedVaults>()
+        .key(&vault_directory)
+        .ok_or_else(|| "Unlock the vault before decrypting notes".to_string())?;
+    let decrypted = encryption::decrypt_note(&key, &relative_path, &vault_directory)?;
+
+    let pool = db::pool(&app).await?;
+    db::cache::refresh_note(&pool, &vault_directory, &relative_path).await?;
+    Ok(decrypted)
+}
+
+#[tauri::command]
+fn get_note_flashcards(relative_path: &str, vault_directory: &str) -> Vec<flashcards::Flashcard> {
+    flashcards::get_note_flashcards(relative_path, vault_directory)
+}
+
+/// Flashcards to review now, from every note in the vault or in one folder
+#[tauri::command]
+async fn get_due_flashcards(
+    app: AppHandle,
+    vault_directory: String,
+    folder: Option<String>,
+    limit: Option<usize>,
+    new_limit: Option<usize>,
+) -> Result<db::flashcards::DueCards, String> {
+    let vault = vault_directory.clone();
+    let cards = tauri::async_runtime::spawn_blocking(move || {
+        flashcards::get_vault_flashcards(&vault, folder.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let pool = db::pool(&app).await?;
+    db::flashcards::due_cards(&pool, &vault_directory, cards, limit, new_limit).await
+}
+
+#[tauri::command]
+async fn record_flashcard_review(
+    app: AppHandle,
+    vault_directory: String,
+    card_id: String,
+    relative_path: String,
+    grade: db::flashcards::ReviewGrade,
+) -> Result<db::flashcards::ReviewState, String> {
+    let pool = db::pool(&app).await?;
+    db::flashcards::record_review(&pool, &vault_directory, &card_id, &relative_path, grade).await
+}
+
+#[tauri::command]
+fn get_note_stats(
+    relative_path: &str,
+    vault_directory: &str,
+) -> Result<db::stats::NoteStats, String> {
+    db::stats::note_stats(relative_path, vault_directory)
+}
+
+/// Totals over the vault, from the note cache
+#[tauri::command]
+async fn get_vault_stats(
+    app: AppHandle,
+    vault_directory: String,
+) -> Result<db::stats::VaultStats, String> {
+    let pool = db::pool(&app).await?;
+    if !db::cache::has_vault(&pool, &vault_directory)
+        .await
+        .map_err(|e| format!("Failed to read note cache: {}", e))?
+    {
+        scan_vault(&app, &pool, &vault_directory).await?;
+    }
+    db::stats::vault_stats(&pool, &vault_directory).await
+}
+
+/// Words written per day, for charting writing activity
+#[tauri::command]
+async fn get_writing_activity(
+    app: AppHandle,
+    vault_directory: String,
+    relative_path: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<Vec<db::stats::WritingDay>, String> {
+    let pool = db::pool(&app).await?;
+    db::stats::writing_activity(
+        &pool,
+        &vault_directory,
+        relative_path.as_deref(),
+        from.as_deref(),
+        to.as_deref(),
+    )
+    .await
+}
+
+#[tauri::command]
+fn get_note_outline(
+    unlocked: tauri::State<'_, encryption::UnlockedVaults>,
+    relative_path: &str,
+    vault_directory: &str,
+) -> Result<Vec<outline::Heading>, String> {
+    if !Path::new(vault_directory).exists() {
+        return Err(format!(
+            "Vault directory does not exist: {}",
+            vault_directory
+        ));
+    }
+
+    let content = read_note(&unlocked, relative_path, vault_directory)?;
+    Ok(outline::parse_headings(&content))
+}
+
+#[tauri::command]
+fn resolve_note_reference(
+    link_target: &str,
+    source_path: Option<&str>,
+    vault_directory: &str,
+) -> Result<outline::ResolvedSection, String> {
+    println!(
+        "Resolving reference '{}' from {:?} in vault: {}",
+        link_target, source_path, vault_directory
+    );
+    outline::resolve_reference(link_target, source_path, vault_directory)
+}
+
+#[tauri::command]
+fn get_note_with_embeds(
+    unlocked: tauri::State<'_, encryption::UnlockedVaults>,
+    relative_path: &str,
+    vault_directory: &str,
+    max_depth: Option<usize>,
+) -> Result<embeds::ExpandedNote, String> {
+    if !Path::new(vault_directory).join(relative_path).exists() {
+        return Err(format!("Note does not exist: {}", relative_path));
+    }
+
+    // Embedded encrypted notes are left unresolved
+    let content = read_note(&unlocked, relative_path, vault_directory)?;
+    Ok(embeds::expand_embeds_in(
+        &content,
         relative_path,
-        Some(vault_directory),
-        new_content,
-    ) {
-        Ok(_) => Ok(true),
-        Err(e) => Err(format!("Failed to update note: {}", e)),
+        vault_directory,
+        max_depth,
+    ))
+}
+
+#[tauri::command]
+fn resolve_link(
+    source_path: &str,
+    link_text: &str,
+    vault_directory: &str,
+) -> Result<links::LinkResolution, String> {
+    if !Path::new(vault_directory).exists() {
+        return Err(format!(
+            "Vault directory does not exist: {}",
+            vault_directory
+        ));
     }
+
+    Ok(links::resolve_link(source_path, link_text, vault_directory))
 }
 
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    // Define migrations
-    let migrations = vec![
-        tauri_plugin_sql::Migration {
-            version: 1,
-            description: "create_settings_table",
-            sql: "CREATE TABLE IF NOT EXISTS settings (id INTEGER PRIMARY KEY AUTOINCREMENT, key TEXT UNIQUE, value TEXT);",
-            kind: tauri_plugin_sql::MigrationKind::Up,
+#[tauri::command]
+fn get_unlinked_mentions(
+    relative_path: &str,
+    vault_directory: &str,
+) -> Result<Vec<links::UnlinkedMention>, String> {
+    if !Path::new(vault_directory).exists() {
+        return Err(format!(
+            "Vault directory does not exist: {}",
+            vault_directory
+        ));
+    }
+
+    let mentions = links::find_unlinked_mentions(relative_path, vault_directory);
+    println!("Found {} unlinked mentions", mentions.len());
+    Ok(mentions)
+}
+
+#[tauri::command]
+fn set_active_vault(
+    vault_directory: &str,
+    active_vault: tauri::State<attachments::ActiveVault>,
+) -> Result<bool, String> {
+    let path = PathBuf::from(vault_directory);
+    if !path.is_dir() {
+        return Err(format!(
+            "Vault directory does not exist: {}",
+            vault_directory
+        ));
+    }
+
+    let mut vault = active_vault
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to set active vault: {}", e))?;
+    *vault = Some(path);
+    Ok(true)
+}
+
+#[tauri::command]
+fn get_attachments(vault_directory: &str) -> Result<Vec<attachments::AttachmentInfo>, String> {
+    if !Path::new(vault_directory).exists() {
+        return Err(format!(
+            "Vault directory does not exist: {}",
+            vault_directory
+        ));
+    }
+
+    Ok(attachments::get_all_attachments(vault_directory))
+}
+
+#[tauri::command]
+fn get_note_attachments(
+    relative_path: &str,
+    vault_directory: &str,
+) -> Vec<attachments::AttachmentReference> {
+    attachments::get_note_attachments(relative_path, vault_directory)
+}
+
+#[tauri::command]
+fn resolve_attachment(
+    source_path: &str,
+    target: &str,
+    vault_directory: &str,
+    wiki_style: Option<bool>,
+) -> attachments::AttachmentResolution {
+    // `![[image.png]]` embeds unless told it's a markdown `![](path)` link
+    let all_attachments = attachments::get_all_attachments(vault_directory);
+    attachments::resolve_attachment(
+        &all_attachments,
+        source_path,
+        target,
+        wiki_style.unwrap_or(true),
+    )
+}
+
+#[tauri::command]
+fn get_unused_attachments(vault_directory: &str) -> Vec<attachments::AttachmentInfo> {
+    attachments::find_unused_attachments(vault_directory)
+}
+
+#[tauri::command]
+async fn import_attachment(
+    app: AppHandle,
+    source_file: String,
+    vault_directory: String,
+    attachments_folder: Option<String>,
+) -> Result<attachments::AttachmentInfo, String> {
+    let folder = attachment_folder(&app, &vault_directory, attachments_folder).await?;
+    attachments::import_attachment(&source_file, &vault_directory, Some(&folder))
+        .map_err(|e| format!("Failed to import attachment: {}", e))
+}
+
+#[tauri::command]
+async fn paste_attachment(
+    app: AppHandle,
+    data: Vec<u8>,
+    file_name: Option<String>,
+    extension: Option<String>,
+    vault_directory: String,
+    attachments_folder: Option<String>,
+) -> Result<attachments::AttachmentInfo, String> {
+    let folder = attachment_folder(&app, &vault_directory, attachments_folder).await?;
+    attachments::save_pasted_attachment(
+        &data,
+        file_name.as_deref(),
+        extension.as_deref(),
+        &vault_directory,
+        Some(&folder),
+    )
+    .map_err(|e| format!("Failed to save pasted attachment: {}", e))
+}
+
+/// The folder new attachments go to: the one asked for, or else the one
+/// in the vault's settings
+async fn attachment_folder(
+    app: &AppHandle,
+    vault_directory: &str,
+    requested: Option<String>,
+) -> Result<String, String> {
+    if let Some(folder) = requested.filter(|folder| !folder.trim().is_empty()) {
+        return Ok(folder);
+    }
+    let pool = db::pool(app).await?;
+    let effective = db::settings::effective_settings(&pool, vault_directory).await?;
+    Ok(effective.settings.attachment_folder)
+}
+
+#[tauri::command]
+fn check_broken_links(vault_directory: &str) -> Result<Vec<health::BrokenLink>, String> {
+    if !Path::new(vault_directory).exists() {
+        return Err(format!(
+            "Vault directory does not exist: {}",
+            vault_directory
+        ));
+    }
+
+    Ok(health::find_broken_links(vault_directory))
+}
+
+#[tauri::command]
+fn fix_broken_link(
+    source_path: &str,
+    vault_directory: &str,
+    raw: &str,
+    replacement: &str,
+    start: Option<usize>,
+) -> Result<usize, String> {
+    health::apply_link_fix(source_path, vault_directory, raw, replacement, start)
+        .map_err(|e| format!("Failed to fix link: {}", e))
+}
+
+#[tauri::command]
+fn lint_vault(
+    vault_directory: &str,
+    config: Option<lint::LintConfig>,
+) -> Result<lint::LintReport, String> {
+    if !Path::new(vault_directory).exists() {
+        return Err(format!(
+            "Vault directory does not exist: {}",
+            vault_directory
+        ));
+    }
+
+    Ok(lint::lint_vault(
+        vault_directory,
+        &config.unwrap_or_default(),
+    ))
+}
+
+#[tauri::command]
+fn export_lint_report(
+    vault_directory: &str,
+    output_path: &str,
+    config: Option<lint::LintConfig>,
+) -> Result<lint::LintReport, String> {
+    let report = lint_vault(vault_directory, config)?;
+    lint::export_report(&report, output_path)
+        .map_err(|e| format!("Failed to export lint report: {}", e))?;
+    Ok(report)
+}
+
+#[tauri::command]
+fn export_to_html(
+    vault_directory: &str,
+    output_directory: &str,
+    relative_path: Option<&str>,
+    folder: Option<&str>,
+) -> Result<export::ExportSummary, String> {
+    export::html::export_html(vault_directory, relative_path, folder, output_directory)
+}
+
+#[tauri::command]
+fn export_to_single_file(
+    vault_directory: &str,
+    relative_path: &str,
+    output_file: &str,
+    include_linked: Option<bool>,
+) -> Result<export::ExportSummary, String> {
+    export::single_file::export_single_file(
+        vault_directory,
+        relative_path,
+        output_file,
+        include_linked.unwrap_or(false),
+    )
+}
+
+#[tauri::command]
+fn export_to_epub(
+    vault_directory: &str,
+    output_file: &str,
+    relative_path: Option<&str>,
+    folder: Option<&str>,
+    include_linked: Option<bool>,
+    title: Option<&str>,
+) -> Result<export::ExportSummary, String> {
+    export::epub::export_epub(
+        vault_directory,
+        relative_path,
+        folder,
+        include_linked.unwrap_or(false),
+        title,
+        output_file,
+    )
+}
+
+#[tauri::command]
+fn import_notes(
+    format: &str,
+    source_path: &str,
+    vault_directory: &str,
+    target_folder: Option<&str>,
+) -> Result<import::ImportReport, String> {
+    import::import_notes(format, source_path, vault_directory, target_folder)
+}
+
+#[tauri::command]
+fn get_note_ast(
+    unlocked: tauri::State<'_, encryption::UnlockedVaults>,
+    relative_path: &str,
+    vault_directory: &str,
+) -> Result<ast::NoteAst, String> {
+    let content = read_note(&unlocked, relative_path, vault_directory)?;
+    Ok(ast::parse(&content))
+}
+
+#[tauri::command]
+async fn get_settings(app: AppHandle) -> Result<db::settings::Settings, String> {
+    let pool = db::pool(&app).await?;
+    db::settings::load_settings(&pool).await
+}
+
+/// Applies a partial settings update and notifies every window
+#[tauri::command]
+async fn update_settings(
+    app: AppHandle,
+    changes: serde_json::Value,
+) -> Result<db::settings::Settings, String> {
+    let pool = db::pool(&app).await?;
+    let settings = db::settings::update_settings(&pool, &changes).await?;
+    app.state::<scan::ActiveScans>()
+        .cancel_others(&settings.vault_path);
+    let _ = app.emit(db::settings::SETTINGS_CHANGED_EVENT, &settings);
+    Ok(settings)
+}
+
+/// Global settings with the vault's `.notemancy/config` overrides applied
+#[tauri::command]
+async fn get_effective_settings(
+    app: AppHandle,
+    vault_directory: String,
+) -> Result<db::settings::EffectiveSettings, String> {
+    let pool = db::pool(&app).await?;
+    db::settings::effective_settings(&pool, &vault_directory).await
+}
+
+/// Updates a vault's overrides; `null` values go back to the global setting
+#[tauri::command]
+async fn update_vault_settings(
+    app: AppHandle,
+    vault_directory: String,
+    changes: serde_json::Value,
+) -> Result<db::settings::EffectiveSettings, String> {
+    db::settings::update_vault_overrides(&vault_directory, &changes)?;
+    let pool = db::pool(&app).await?;
+    let effective = db::settings::effective_settings(&pool, &vault_directory).await?;
+    let _ = app.emit(db::settings::VAULT_SETTINGS_CHANGED_EVENT, &effective);
+    Ok(effective)
+}
+
+/// Stores the open notes, cursor and scroll positions and panel layout
+#[tauri::command]
+async fn save_workspace(
+    app: AppHandle,
+    vault_directory: String,
+    workspace: db::workspace::Workspace,
+) -> Result<(), String> {
+    let pool = db::pool(&app).await?;
+    db::workspace::save_workspace(&pool, &vault_directory, &workspace).await
+}
+
+#[tauri::command]
+async fn restore_workspace(
+    app: AppHandle,
+    vault_directory: String,
+) -> Result<db::workspace::Workspace, String> {
+    let pool = db::pool(&app).await?;
+    db::workspace::restore_workspace(&pool, &vault_directory).await
+}
+
+/// Called by the note view whenever a note is opened
+#[tauri::command]
+async fn record_note_opened(
+    app: AppHandle,
+    vault_directory: String,
+    relative_path: String,
+) -> Result<(), String> {
+    let pool = db::pool(&app).await?;
+    db::workspace::record_note_opened(&pool, &vault_directory, &relative_path).await?;
+    let _ = app.emit(db::workspace::RECENT_NOTES_CHANGED_EVENT, &vault_directory);
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_recent_notes(
+    app: AppHandle,
+    vault_directory: String,
+    limit: Option<usize>,
+) -> Result<Vec<db::workspace::RecentNote>, String> {
+    let pool = db::pool(&app).await?;
+    let limit = limit.unwrap_or(db::workspace::RECENT_NOTES_LIMIT as usize);
+    db::workspace::recent_notes(&pool, &vault_directory, limit).await
+}
+
+/// The vault's folders as a tree, with note counts and saved view state
+#[tauri::command]
+async fn get_folder_tree(
+    app: AppHandle,
+    vault_directory: String,
+) -> Result<folders::FolderNode, String> {
+    let pool = db::pool(&app).await?;
+    let states = db::workspace::load_folder_states(&pool, &vault_directory).await?;
+    folders::folder_tree(&vault_directory, &states)
+        .map_err(|e| format!("Failed to read folders: {}", e))
+}
+
+/// Collapses or expands a folder, or gives it a manual position
+#[tauri::command]
+async fn set_folder_state(
+    app: AppHandle,
+    vault_directory: String,
+    relative_path: String,
+    collapsed: Option<bool>,
+    position: Option<i64>,
+) -> Result<(), String> {
+    let pool = db::pool(&app).await?;
+    let mut states = db::workspace::load_folder_states(&pool, &vault_directory).await?;
+    let mut state = states.remove(&relative_path).unwrap_or_default();
+    if let Some(collapsed) = collapsed {
+        state.collapsed = collapsed;
+    }
+    if position.is_some() {
+        state.position = position;
+    }
+    db::workspace::save_folder_state(&pool, &vault_directory, &relative_path, &state).await
+}
+
+#[tauri::command]
+fn create_folder(vault_directory: &str, relative_path: &str) -> Result<String, String> {
+    folders::create_folder(vault_directory, relative_path)
+        .map_err(|e| format!("Failed to create folder: {}", e))
+}
+
+/// Renames a folder and rewrites the links its move would break
+#[tauri::command]
+async fn rename_folder(
+    app: AppHandle,
+    vault_directory: String,
+    relative_path: String,
+    new_name: String,
+) -> Result<folders::FolderChange, String> {
+    let vault = vault_directory.clone();
+    let change = tauri::async_runtime::spawn_blocking(move || {
+        folders::rename_folder(&vault, &relative_path, &new_name)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| format!("Failed to rename folder: {}", e))?;
+
+    let pool = db::pool(&app).await?;
+    db::workspace::move_folder_paths(
+        &pool,
+        &vault_directory,
+        &change.old_path,
+        Some(&change.new_path),
+    )
+    .await?;
+    refresh_after_vault_change(&app, &vault_directory).await?;
+    Ok(change)
+}
+
+/// Moves a folder under another one (empty for the vault root) and rewrites
+/// the links the move would break
+#[tauri::command]
+async fn move_folder(
+    app: AppHandle,
+    vault_directory: String,
+    relative_path: String,
+    new_parent: String,
+) -> Result<folders::FolderChange, String> {
+    let vault = vault_directory.clone();
+    let change = tauri::async_runtime::spawn_blocking(move || {
+        folders::move_folder(&vault, &relative_path, &new_parent)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| format!("Failed to move folder: {}", e))?;
+
+    let pool = db::pool(&app).await?;
+    db::workspace::move_folder_paths(
+        &pool,
+        &vault_directory,
+        &change.old_path,
+        Some(&change.new_path),
+    )
+    .await?;
+    refresh_after_vault_change(&app, &vault_directory).await?;
+    Ok(change)
+}
+
+/// Deletes a folder; one with contents only when `recursive` is set.
+/// Returns the number of deleted notes.
+#[tauri::command]
+async fn delete_folder(
+    app: AppHandle,
+    vault_directory: String,
+    relative_path: String,
+    recursive: Option<bool>,
+) -> Result<usize, String> {
+    let relative_path = folders::clean_folder_path(&relative_path).map_err(|e| e.to_string())?;
+    let deleted =
+        folders::delete_folder(&vault_directory, &relative_path, recursive.unwrap_or(false))
+            .map_err(|e| format!("Failed to delete folder: {}", e))?;
+
+    let pool = db::pool(&app).await?;
+    db::workspace::move_folder_paths(&pool, &vault_directory, &relative_path, None).await?;
+    refresh_after_vault_change(&app, &vault_directory).await?;
+    Ok(deleted)
+}
+
+/// Serves vault files to the webview as `vault://localhost/<relative path>`
+fn vault_protocol<R: tauri::Runtime>(
+    ctx: tauri::UriSchemeContext<'_, R>,
+    request: tauri::http::Request<Vec<u8>>,
+) -> Response<Vec<u8>> {
+    let not_found = || {
+        Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Vec::new())
+            .unwrap()
+    };
+
+    let vault = match ctx
+        .app_handle()
+        .state::<attachments::ActiveVault>()
+        .0
+        .lock()
+    {
+        Ok(vault) => vault.clone(),
+        Err(_) => None,
+    };
+    let vault = match vault {
+        Some(vault) => vault,
+        None => return not_found(),
+    };
+
+    let path = match attachments::resolve_protocol_path(&vault, request.uri().path()) {
+        Some(path) => path,
+        None => return not_found(),
+    };
+
+    match fs::read(&path) {
+        Ok(data) => {
+            let extension = path
+                .extension()
+                .map(|e| e.to_string_lossy().to_string())
+                .unwrap_or_default();
+            Response::builder()
+                .header("Content-Type", attachments::mime_type(&extension))
+                .body(data)
+                .unwrap_or_else(|_| not_found())
         }
-    ];
+        Err(_) => not_found(),
+    }
+}
 
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
     tauri::Builder::default()
         .plugin(
             tauri_plugin_sql::Builder::default()
-                .add_migrations("sqlite:settings.db", migrations)
+                .add_migrations(db::DB_URL, db::migrations())
                 .build(),
         )
         .plugin(tauri_plugin_opener::init())
+        .manage(attachments::ActiveVault::default())
+        .manage(scan::ActiveScans::default())
+        .manage(encryption::UnlockedVaults::default())
+        .setup(|app| {
+            // Checks the stored settings once, which also makes scanning honor
+            // the saved ignore rules. This blocks so that no command can scan
+            // a vault before the settings are in place; the SQL plugin has
+            // already opened the database by now.
+            let loaded = tauri::async_runtime::block_on(async {
+                let pool = db::pool(app.handle()).await?;
+                db::settings::init_settings(&pool).await.map(|_| ())
+            });
+            if let Err(e) = loaded {
+                println!("Failed to load settings: {}", e);
+            }
+
+            // The one timer that locks vaults as they go unused
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let unlocked = handle.state::<encryption::UnlockedVaults>();
+                unlocked
+                    .run_lock_timer(|vault| {
+                        let _ = handle.emit(encryption::LOCKED_EVENT, vault);
+                    })
+                    .await;
+            });
+            Ok(())
+        })
+        .register_uri_scheme_protocol("vault", vault_protocol)
         .invoke_handler(tauri::generate_handler![
             greet,
             check_and_create_directory,
             get_notes,
+            list_notes,
+            get_graph,
+            get_note_id,
+            resolve_note_id,
             get_note_content, // Add our new function to get note content
             get_note_title,   // Add our new function to get note title
             update_note_content,
-            get_backlinks
+            get_encryption_status,
+            set_vault_passphrase,
+            unlock_vault,
+            lock_vault,
+            encrypt_notes,
+            decrypt_note,
+            get_note_stats,
+            get_note_flashcards,
+            get_due_flashcards,
+            record_flashcard_review,
+            get_vault_stats,
+            get_writing_activity,
+            get_backlinks,
+            get_note_outline,
+            resolve_note_reference,
+            get_note_with_embeds,
+            resolve_link,
+            get_unlinked_mentions,
+            set_active_vault,
+            get_attachments,
+            get_note_attachments,
+            resolve_attachment,
+            get_unused_attachments,
+            import_attachment,
+            paste_attachment,
+            check_broken_links,
+            fix_broken_link,
+            lint_vault,
+            export_lint_report,
+            export_to_html,
+            export_to_single_file,
+            export_to_epub,
+            import_notes,
+            get_note_ast,
+            sync_note_cache,
+            cancel_vault_scan,
+            get_settings,
+            update_settings,
+            get_effective_settings,
+            update_vault_settings,
+            save_workspace,
+            restore_workspace,
+            record_note_opened,
+            get_recent_notes,
+            get_folder_tree,
+            set_folder_state,
+            create_folder,
+            rename_folder,
+            move_folder,
+            delete_folder
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
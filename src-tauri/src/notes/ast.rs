@@ -0,0 +1,612 @@
+// src/notes/ast.rs
+use super::links::WikiLink;
+use super::outline::{self, Heading};
+use pulldown_cmark::{CodeBlockKind, Event, LinkType, Options, Parser, Tag as MarkdownTag, TagEnd};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A node of a note's markdown tree. Offsets are byte offsets into the note
+/// content (frontmatter stripped, end exclusive) and lines are 1-based.
+/// Only the fields that apply to a node's `kind` are set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AstNode {
+    pub kind: String,
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub text: Option<String>,
+    pub level: Option<usize>,
+    pub url: Option<String>,
+    pub title: Option<String>,
+    pub language: Option<String>,
+    pub checked: Option<bool>,
+    pub children: Vec<AstNode>,
+}
+
+/// A regular markdown link or image
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarkdownLink {
+    pub url: String,
+    pub text: String,
+    pub title: String,
+    pub image: bool,
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// An inline `#tag`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tag {
+    pub name: String,
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A `- [ ]` / `- [x]` list item. The range covers the whole item,
+/// including nested items.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Task {
+    pub text: String,
+    pub checked: bool,
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A fenced or indented code block
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodeBlock {
+    pub language: Option<String>,
+    pub content: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A parsed note: the markdown tree plus the elements note features look
+/// for. Wiki links with `embed` set are `![[embeds]]`. Nothing inside code
+/// is reported as a link or tag.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteAst {
+    pub nodes: Vec<AstNode>,
+    pub headings: Vec<Heading>,
+    pub wiki_links: Vec<WikiLink>,
+    pub links: Vec<MarkdownLink>,
+    pub tags: Vec<Tag>,
+    pub tasks: Vec<Task>,
+    pub code_blocks: Vec<CodeBlock>,
+    /// Byte ranges of code blocks and inline code
+    #[serde(skip)]
+    pub code_ranges: Vec<(usize, usize)>,
+}
+
+impl NoteAst {
+    /// Returns true if the byte range lies inside code
+    pub fn in_code(&self, start: usize, end: usize) -> bool {
+        self.code_ranges
+            .iter()
+            .any(|(code_start, code_end)| start >= *code_start && end <= *code_end)
+    }
+}
+
+/// The markdown extensions notes are written with
+pub fn markdown_options() -> Options {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_FOOTNOTES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TASKLISTS);
+    options
+}
+
+/// Maps byte offsets to 1-based line numbers
+struct LineIndex {
+    starts: Vec<usize>,
+}
+
+impl LineIndex {
+    fn new(content: &str) -> Self {
+        let mut starts = vec![0];
+        starts.extend(content.match_indices('\n').map(|(i, _)| i + 1));
+        LineIndex { starts }
+    }
+
+    fn line(&self, offset: usize) -> usize {
+        match self.starts.binary_search(&offset) {
+            Ok(index) => index + 1,
+            Err(index) => index,
+        }
+    }
+}
+
+fn node(kind: &str, start: usize, end: usize, line: usize) -> AstNode {
+    AstNode {
+        kind: kind.to_string(),
+        start,
+        end,
+        line,
+        text: None,
+        level: None,
+        url: None,
+        title: None,
+        language: None,
+        checked: None,
+        children: Vec::new(),
+    }
+}
+
+/// Builds the tree node for a start tag
+fn tag_node(tag: &MarkdownTag, start: usize, end: usize, line: usize) -> AstNode {
+    match tag {
+        MarkdownTag::Paragraph => node("paragraph", start, end, line),
+        MarkdownTag::Heading { level, .. } => AstNode {
+            level: Some(*level as usize),
+            ..node("heading", start, end, line)
+        },
+        MarkdownTag::BlockQuote(_) => node("blockquote", start, end, line),
+        MarkdownTag::CodeBlock(kind) => AstNode {
+            language: match kind {
+                CodeBlockKind::Fenced(info) if !info.is_empty() => {
+                    info.split_whitespace().next().map(|l| l.to_string())
+                }
+                _ => None,
+            },
+            ..node("code_block", start, end, line)
+        },
+        MarkdownTag::HtmlBlock => node("html", start, end, line),
+        MarkdownTag::List(first) => AstNode {
+            level: first.map(|n| n as usize),
+            ..node(
+                if first.is_some() {
+                    "ordered_list"
+                } else {
+                    "list"
+                },
+                start,
+                end,
+                line,
+            )
+        },
+        MarkdownTag::Item => node("item", start, end, line),
+        MarkdownTag::FootnoteDefinition(label) => AstNode {
+            title: Some(label.to_string()),
+            ..node("footnote_definition", start, end, line)
+        },
+        MarkdownTag::Table(_) => node("table", start, end, line),
+        MarkdownTag::TableHead => node("table_head", start, end, line),
+        MarkdownTag::TableRow => node("table_row", start, end, line),
+        MarkdownTag::TableCell => node("table_cell", start, end, line),
+        MarkdownTag::Emphasis => node("emphasis", start, end, line),
+        MarkdownTag::Strong => node("strong", start, end, line),
+        MarkdownTag::Strikethrough => node("strikethrough", start, end, line),
+        MarkdownTag::Link {
+            link_type,
+            dest_url,
+            title,
+            ..
+        }
+        | MarkdownTag::Image {
+            link_type,
+            dest_url,
+            title,
+            ..
+        } => {
+            let image = matches!(tag, MarkdownTag::Image { .. });
+            let kind = match (link_type, image) {
+                (LinkType::WikiLink { .. }, true) => "embed",
+                (LinkType::WikiLink { .. }, false) => "wiki_link",
+                (_, true) => "image",
+                (_, false) => "link",
+            };
+            AstNode {
+                url: Some(dest_url.to_string()),
+                title: Some(title.to_string()).filter(|t| !t.is_empty()),
+                ..node(kind, start, end, line)
+            }
+        }
+        _ => node("other", start, end, line),
+    }
+}
+
+/// Splits the raw text of a wiki link into target and alias
+fn wiki_link(raw: &str, start: usize, end: usize, line: usize) -> WikiLink {
+    let embed = raw.starts_with('!');
+    let inner = raw
+        .trim_start_matches('!')
+        .trim_start_matches("[[")
+        .trim_end_matches("]]");
+    let (target, alias) = match inner.split_once('|') {
+        // Inside tables the pipe is escaped as `\|`
+        Some((target, alias)) => (
+            target.trim_end_matches('\\').trim(),
+            Some(alias.trim().to_string()),
+        ),
+        None => (inner.trim(), None),
+    };
+
+    WikiLink {
+        raw: raw.to_string(),
+        target: target.to_string(),
+        alias,
+        embed,
+        line,
+        start,
+        end,
+    }
+}
+
+/// Numbers headings' section ends and slugs the way `outline` expects
+fn finish_headings(headings: &mut [Heading], line_count: usize) {
+    let mut slug_counts: HashMap<String, usize> = HashMap::new();
+    for heading in headings.iter_mut() {
+        // Duplicate headings get a numeric suffix, like github-slugger does
+        let base_slug = outline::slugify(&heading.text);
+        let count = slug_counts.entry(base_slug.clone()).or_insert(0);
+        heading.slug = if *count == 0 {
+            base_slug
+        } else {
+            format!("{}-{}", base_slug, count)
+        };
+        *count += 1;
+        heading.end_line = line_count;
+    }
+
+    // A section ends right before the next heading of the same or a higher level
+    for i in 0..headings.len() {
+        for j in (i + 1)..headings.len() {
+            if headings[j].level <= headings[i].level {
+                headings[i].end_line = headings[j].start_line - 1;
+                break;
+            }
+        }
+    }
+}
+
+/// Adds text to the heading, link and task being collected
+fn push_text(
+    text: &str,
+    heading_text: &mut Option<String>,
+    link_text: &mut [String],
+    tasks: &mut [Option<(Task, bool)>],
+) {
+    if let Some(heading) = heading_text.as_mut() {
+        heading.push_str(text);
+    }
+    if let Some(link) = link_text.last_mut() {
+        link.push_str(text);
+    }
+    if let Some(Some((task, true))) = tasks.last_mut() {
+        task.text.push_str(text);
+    }
+}
+
+/// Parses a note's markdown content into its tree and the elements note
+/// features look for
+pub fn parse(content: &str) -> NoteAst {
+    let mut options = markdown_options();
+    options.insert(Options::ENABLE_WIKILINKS);
+
+    let lines = LineIndex::new(content);
+    let tag_regex = Regex::new(r"#([\p{L}\p{N}_/-]+)").unwrap();
+
+    let mut ast = NoteAst {
+        nodes: Vec::new(),
+        headings: Vec::new(),
+        wiki_links: Vec::new(),
+        links: Vec::new(),
+        tags: Vec::new(),
+        tasks: Vec::new(),
+        code_blocks: Vec::new(),
+        code_ranges: Vec::new(),
+    };
+
+    // Open tree nodes, and the text collected for headings, links and tasks
+    let mut stack: Vec<AstNode> = Vec::new();
+    let mut heading_text: Option<String> = None;
+    let mut link_text: Vec<String> = Vec::new();
+    let mut tasks: Vec<Option<(Task, bool)>> = Vec::new();
+    let mut in_code_block = false;
+    let mut wiki_depth = 0;
+
+    for (event, range) in Parser::new_ext(content, options).into_offset_iter() {
+        let line = lines.line(range.start);
+        match event {
+            Event::Start(tag) => {
+                match &tag {
+                    MarkdownTag::Heading { level, .. } => {
+                        heading_text = Some(String::new());
+                        ast.headings.push(Heading {
+                            level: *level as usize,
+                            text: String::new(),
+                            slug: String::new(),
+                            start_line: line,
+                            end_line: line,
+                        });
+                    }
+                    MarkdownTag::CodeBlock(_) => {
+                        in_code_block = true;
+                        ast.code_ranges.push((range.start, range.end));
+                    }
+                    MarkdownTag::Item => tasks.push(None),
+                    MarkdownTag::List(_) => {
+                        // Text of nested items doesn't belong to the parent task
+                        if let Some(Some((_, collecting))) = tasks.last_mut() {
+                            *collecting = false;
+                        }
+                    }
+                    MarkdownTag::Link { link_type, .. } | MarkdownTag::Image { link_type, .. } => {
+                        if matches!(link_type, LinkType::WikiLink { .. }) {
+                            wiki_depth += 1;
+                        }
+                        link_text.push(String::new());
+                    }
+                    _ => {}
+                }
+                stack.push(tag_node(&tag, range.start, range.end, line));
+            }
+            Event::End(end) => {
+                let Some(mut finished) = stack.pop() else {
+                    continue;
+                };
+                match end {
+                    TagEnd::Heading(_) => {
+                        if let (Some(heading), Some(text)) =
+                            (ast.headings.last_mut(), heading_text.take())
+                        {
+                            heading.text = text.trim().to_string();
+                        }
+                    }
+                    TagEnd::CodeBlock => {
+                        in_code_block = false;
+                        let code = finished.text.clone().unwrap_or_default();
+                        ast.code_blocks.push(CodeBlock {
+                            language: finished.language.clone(),
+                            content: code,
+                            start_line: line,
+                            end_line: lines.line(range.end.saturating_sub(1).max(range.start)),
+                            start: range.start,
+                            end: range.end,
+                        });
+                    }
+                    TagEnd::Item => {
+                        if let Some(Some((mut task, _))) = tasks.pop() {
+                            task.text = task.text.trim().to_string();
+                            ast.tasks.push(task);
+                        }
+                    }
+                    TagEnd::Link | TagEnd::Image => {
+                        let text = link_text.pop().unwrap_or_default();
+                        let raw = &content[range.start..range.end];
+                        let image = matches!(end, TagEnd::Image);
+                        if finished.kind == "wiki_link" || finished.kind == "embed" {
+                            wiki_depth -= 1;
+                            ast.wiki_links
+                                .push(wiki_link(raw, range.start, range.end, line));
+                        } else {
+                            ast.links.push(MarkdownLink {
+                                url: finished.url.clone().unwrap_or_default(),
+                                text: text.clone(),
+                                title: finished.title.clone().unwrap_or_default(),
+                                image,
+                                line,
+                                start: range.start,
+                                end: range.end,
+                            });
+                        }
+                        finished.text = Some(text);
+                    }
+                    _ => {}
+                }
+
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(finished),
+                    None => ast.nodes.push(finished),
+                }
+            }
+            Event::Text(text) => {
+                if in_code_block {
+                    if let Some(code) = stack.last_mut() {
+                        code.text.get_or_insert_with(String::new).push_str(&text);
+                    }
+                    continue;
+                }
+                push_text(&text, &mut heading_text, &mut link_text, &mut tasks);
+
+                // Tags are read from the raw text so escaped `\#` isn't one
+                if wiki_depth == 0 {
+                    let raw = &content[range.start..range.end];
+                    for found in tag_regex.captures_iter(raw) {
+                        let whole = found.get(0).unwrap();
+                        let start = range.start + whole.start();
+                        let preceded_ok = content[..start]
+                            .chars()
+                            .next_back()
+                            .map(|c| c.is_whitespace() || c == '(')
+                            .unwrap_or(true);
+                        let name = found[1].trim_end_matches('/');
+                        if !preceded_ok || name.chars().all(|c| c.is_ascii_digit()) {
+                            continue;
+                        }
+                        ast.tags.push(Tag {
+                            name: name.to_string(),
+                            line: lines.line(start),
+                            start,
+                            end: start + 1 + name.len(),
+                        });
+                    }
+                }
+
+                let leaf = AstNode {
+                    text: Some(text.to_string()),
+                    ..node("text", range.start, range.end, line)
+                };
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(leaf),
+                    None => ast.nodes.push(leaf),
+                }
+            }
+            Event::TaskListMarker(checked) => {
+                if let Some(item) = stack.last_mut() {
+                    item.checked = Some(checked);
+                }
+                if let Some(task) = tasks.last_mut() {
+                    let (start, end) = stack
+                        .last()
+                        .map(|item| (item.start, item.end))
+                        .unwrap_or((range.start, range.end));
+                    *task = Some((
+                        Task {
+                            text: String::new(),
+                            checked,
+                            line,
+                            start,
+                            end,
+                        },
+                        true,
+                    ));
+                }
+            }
+            other => {
+                let leaf = match other {
+                    Event::Code(code) => {
+                        ast.code_ranges.push((range.start, range.end));
+                        push_text(&code, &mut heading_text, &mut link_text, &mut tasks);
+                        AstNode {
+                            text: Some(code.to_string()),
+                            ..node("code", range.start, range.end, line)
+                        }
+                    }
+                    Event::Html(html) | Event::InlineHtml(html) => AstNode {
+                        text: Some(html.to_string()),
+                        ..node("html", range.start, range.end, line)
+                    },
+                    Event::FootnoteReference(label) => AstNode {
+                        title: Some(label.to_string()),
+                        ..node("footnote_reference", range.start, range.end, line)
+                    },
+                    Event::SoftBreak => {
+                        push_text(" ", &mut heading_text, &mut link_text, &mut tasks);
+                        node("soft_break", range.start, range.end, line)
+                    }
+                    Event::HardBreak => node("hard_break", range.start, range.end, line),
+                    Event::Rule => node("rule", range.start, range.end, line),
+                    _ => continue,
+                };
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(leaf),
+                    None => ast.nodes.push(leaf),
+                }
+            }
+        }
+    }
+
+    // Nested tasks finish before their parents
+    ast.tasks.sort_by_key(|task| task.start);
+    finish_headings(&mut ast.headings, content.lines().count());
+    ast
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collects_headings_with_sections_and_slugs() {
+        let ast = parse("# Top\ntext\n## Sub\nmore\n# Top\nend");
+        let headings: Vec<(usize, &str, &str, usize, usize)> = ast
+            .headings
+            .iter()
+            .map(|h| {
+                (
+                    h.level,
+                    h.text.as_str(),
+                    h.slug.as_str(),
+                    h.start_line,
+                    h.end_line,
+                )
+            })
+            .collect();
+        assert_eq!(
+            headings,
+            vec![
+                (1, "Top", "top", 1, 4),
+                (2, "Sub", "sub", 3, 4),
+                (1, "Top", "top-1", 5, 6)
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_links_and_tags_outside_code() {
+        let content = "See [[Note|alias]], ![[pic.png]] and [site](https://e.com \"T\").\n\
+                       #tag #nested/tag #123 a#not `#code [[code]]`\n\
+                       ```rust\n#no [[no]]\n```";
+        let ast = parse(content);
+
+        let wiki: Vec<(&str, Option<&str>, bool)> = ast
+            .wiki_links
+            .iter()
+            .map(|link| (link.target.as_str(), link.alias.as_deref(), link.embed))
+            .collect();
+        assert_eq!(
+            wiki,
+            vec![("Note", Some("alias"), false), ("pic.png", None, true)]
+        );
+        assert_eq!(
+            &content[ast.wiki_links[0].start..ast.wiki_links[0].end],
+            "[[Note|alias]]"
+        );
+
+        assert_eq!(ast.links.len(), 1);
+        assert_eq!(
+            (
+                ast.links[0].url.as_str(),
+                ast.links[0].text.as_str(),
+                ast.links[0].title.as_str()
+            ),
+            ("https://e.com", "site", "T")
+        );
+
+        let tags: Vec<(&str, usize)> = ast.tags.iter().map(|t| (t.name.as_str(), t.line)).collect();
+        assert_eq!(tags, vec![("tag", 2), ("nested/tag", 2)]);
+        assert_eq!(&content[ast.tags[0].start..ast.tags[0].end], "#tag");
+
+        assert_eq!(ast.code_blocks.len(), 1);
+        assert_eq!(ast.code_blocks[0].language.as_deref(), Some("rust"));
+        assert_eq!(ast.code_blocks[0].content, "#no [[no]]\n");
+        let inline = content.find("`#code").unwrap();
+        assert!(ast.in_code(inline + 1, inline + 6));
+    }
+
+    #[test]
+    fn collects_nested_tasks() {
+        let content = "- [ ] parent\n    - [x] child\n- plain\n- [X] done";
+        let ast = parse(content);
+        let tasks: Vec<(&str, bool, usize)> = ast
+            .tasks
+            .iter()
+            .map(|t| (t.text.as_str(), t.checked, t.line))
+            .collect();
+        assert_eq!(
+            tasks,
+            vec![("parent", false, 1), ("child", true, 2), ("done", true, 4)]
+        );
+        // A task's range covers its nested items
+        let parent = &ast.tasks[0];
+        assert!(content[parent.start..parent.end].contains("child"));
+    }
+
+    #[test]
+    fn builds_the_node_tree() {
+        let ast = parse("> quote\n\n1. one\n\n| a |\n| - |\n| b |");
+        let kinds: Vec<&str> = ast.nodes.iter().map(|n| n.kind.as_str()).collect();
+        assert_eq!(kinds, vec!["blockquote", "ordered_list", "table"]);
+        assert_eq!(ast.nodes[1].level, Some(1));
+        assert_eq!(ast.nodes[1].children[0].kind, "item");
+        assert_eq!(ast.nodes[2].line, 5);
+    }
+}
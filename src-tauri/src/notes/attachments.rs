@@ -0,0 +1,596 @@
+// src/notes/attachments.rs
+use super::ast;
+use super::formats;
+use super::helpers;
+use super::links::{self, LinkResolver};
+use super::scan::VaultFilter;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Folder new attachments go to when none is configured
+pub const DEFAULT_ATTACHMENTS_FOLDER: &str = "attachments";
+
+/// The vault the `vault://` protocol serves files from. Requests for files
+/// outside of it are rejected.
+#[derive(Default)]
+pub struct ActiveVault(pub Mutex<Option<PathBuf>>);
+
+/// A non-note file inside the vault
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttachmentInfo {
+    pub name: String,
+    pub absolute_path: String,
+    pub relative_path: String,
+    pub extension: String,
+    pub kind: String,
+    pub size: u64,
+}
+
+/// A reference to an attachment found in a note. `candidates` lists the
+/// files an ambiguous reference could mean.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttachmentReference {
+    pub raw: String,
+    pub target: String,
+    pub line: usize,
+    pub attachment: Option<AttachmentInfo>,
+    pub candidates: Vec<AttachmentInfo>,
+}
+
+/// The outcome of resolving an attachment reference: `resolved` with a
+/// single file, `ambiguous` with several candidates, or `unresolved`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttachmentResolution {
+    pub status: String,
+    pub attachment: Option<AttachmentInfo>,
+    pub candidates: Vec<AttachmentInfo>,
+}
+
+impl AttachmentResolution {
+    fn from_matches(mut matches: Vec<AttachmentInfo>) -> Self {
+        let (status, attachment) = match matches.len() {
+            0 => ("unresolved", None),
+            1 => ("resolved", matches.pop()),
+            _ => ("ambiguous", None),
+        };
+        AttachmentResolution {
+            status: status.to_string(),
+            attachment,
+            candidates: matches,
+        }
+    }
+}
+
+/// Classifies a file by its extension
+pub fn attachment_kind(extension: &str) -> &'static str {
+    match extension.to_lowercase().as_str() {
+        "png" | "jpg" | "jpeg" | "gif" | "webp" | "svg" | "bmp" | "avif" => "image",
+        "pdf" => "pdf",
+        "mp3" | "wav" | "ogg" | "m4a" | "flac" => "audio",
+        "mp4" | "webm" | "mov" | "mkv" => "video",
+        _ => "other",
+    }
+}
+
+/// Returns the MIME type to serve a file with
+pub fn mime_type(extension: &str) -> &'static str {
+    match extension.to_lowercase().as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "bmp" => "image/bmp",
+        "avif" => "image/avif",
+        "pdf" => "application/pdf",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "ogg" => "audio/ogg",
+        "m4a" => "audio/mp4",
+        "flac" => "audio/flac",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "mov" => "video/quicktime",
+        "mkv" => "video/x-matroska",
+        "txt" => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+fn attachment_info(path: &Path, vault_path: &Path) -> Option<AttachmentInfo> {
+    let relative_path = path.strip_prefix(vault_path).ok()?;
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+
+    Some(AttachmentInfo {
+        name: path.file_name()?.to_string_lossy().to_string(),
+        absolute_path: path.to_string_lossy().to_string(),
+        relative_path: relative_path.to_string_lossy().replace('\\', "/"),
+        kind: attachment_kind(&extension).to_string(),
+        extension,
+        size,
+    })
+}
+
+/// Lists all non-note files in the vault
+pub fn get_all_attachments(vault_directory: &str) -> Vec<AttachmentInfo> {
+    let vault_path = Path::new(vault_directory);
+    if !vault_path.is_dir() {
+        return Vec::new();
+    }
+
+    let filter = VaultFilter::load(vault_directory);
+    let mut result = Vec::new();
+    for path in filter.files() {
+        if filter.is_note(&path) {
+            continue;
+        }
+        if let Some(info) = attachment_info(&path, vault_path) {
+            result.push(info);
+        }
+    }
+    result.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+    result
+}
+
+/// Decodes `%XX` escapes in a link target
+pub fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 3 <= bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+            if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Finds the attachment a reference points to. Wiki embeds match the
+/// shortest unique path like note links do; markdown links are relative to
+/// the note's folder first and to the vault root second.
+pub fn resolve_attachment(
+    attachments: &[AttachmentInfo],
+    source_path: &str,
+    target: &str,
+    wiki_style: bool,
+) -> AttachmentResolution {
+    AttachmentResolution::from_matches(match_attachments(
+        attachments,
+        source_path,
+        target,
+        wiki_style,
+    ))
+}
+
+fn match_attachments(
+    attachments: &[AttachmentInfo],
+    source_path: &str,
+    target: &str,
+    wiki_style: bool,
+) -> Vec<AttachmentInfo> {
+    let target = percent_decode(target.split('|').next().unwrap_or("").trim());
+    let target = target.trim_start_matches("./").replace('\\', "/");
+    if target.is_empty() || target.contains("://") {
+        return Vec::new();
+    }
+
+    if !wiki_style {
+        let folder = Path::new(source_path)
+            .parent()
+            .map(|p| p.to_string_lossy().replace('\\', "/"))
+            .unwrap_or_default();
+        let relative = if folder.is_empty() {
+            target.clone()
+        } else {
+            format!("{}/{}", folder, target)
+        };
+        let relative = normalize_segments(&relative);
+
+        for candidate in [relative, normalize_segments(&target)] {
+            if let Some(found) = attachments.iter().find(|a| a.relative_path == candidate) {
+                return vec![found.clone()];
+            }
+        }
+        return Vec::new();
+    }
+
+    let lowered = target.to_lowercase();
+    let suffix = format!("/{}", lowered);
+    let matches: Vec<&AttachmentInfo> = attachments
+        .iter()
+        .filter(|a| {
+            let path = a.relative_path.to_lowercase();
+            path == lowered || path.ends_with(&suffix)
+        })
+        .collect();
+
+    if matches.len() < 2 {
+        return matches.into_iter().cloned().collect();
+    }
+
+    // Prefer an attachment next to the note when the name is ambiguous, and
+    // report all of them when none is
+    let folder = Path::new(source_path)
+        .parent()
+        .map(|p| p.to_string_lossy().replace('\\', "/"))
+        .unwrap_or_default();
+    let next_to_note = matches.iter().find(|a| {
+        Path::new(&a.relative_path)
+            .parent()
+            .map(|p| p.to_string_lossy().replace('\\', "/"))
+            .unwrap_or_default()
+            == folder
+    });
+    match next_to_note {
+        Some(attachment) => vec![(*attachment).clone()],
+        None => matches.into_iter().cloned().collect(),
+    }
+}
+
+/// Collapses `.` and `..` segments of a relative path
+fn normalize_segments(path: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            other => parts.push(other),
+        }
+    }
+    parts.join("/")
+}
+
+/// Finds the attachment references in a note's content: `![[file.png]]`
+/// embeds and `![alt](path)` / `[text](file.pdf)` markdown links
+pub fn extract_attachment_references(
+    resolver: &LinkResolver,
+    content: &str,
+    source_path: &str,
+    attachments: &[AttachmentInfo],
+) -> Vec<AttachmentReference> {
+    let mut references = Vec::new();
+    let note_ast = ast::parse(content);
+
+    for link in note_ast.wiki_links {
+        if !links::is_attachment_target(resolver, &link.target, Some(source_path), attachments) {
+            continue;
+        }
+        let (target, _) = super::outline::split_link_target(&link.target);
+
+        let resolution = resolve_attachment(attachments, source_path, &target, true);
+        references.push(AttachmentReference {
+            raw: link.raw.clone(),
+            attachment: resolution.attachment,
+            candidates: resolution.candidates,
+            target,
+            line: link.line,
+        });
+    }
+
+    for link in note_ast.links {
+        let target = link.url;
+        if target.contains("://") || target.starts_with('#') || formats::is_note_path(&target) {
+            continue;
+        }
+
+        let resolution = resolve_attachment(attachments, source_path, &target, false);
+        references.push(AttachmentReference {
+            raw: content[link.start..link.end].to_string(),
+            attachment: resolution.attachment,
+            candidates: resolution.candidates,
+            target,
+            line: link.line,
+        });
+    }
+
+    references
+}
+
+/// Lists the attachment references of a single note
+pub fn get_note_attachments(
+    relative_path: &str,
+    vault_directory: &str,
+) -> Vec<AttachmentReference> {
+    let attachments = get_all_attachments(vault_directory);
+    let resolver = LinkResolver::new(vault_directory);
+    let content = helpers::get_content(None, Some(relative_path), Some(vault_directory));
+    extract_attachment_references(&resolver, &content, relative_path, &attachments)
+}
+
+/// Finds attachments no note refers to
+pub fn find_unused_attachments(vault_directory: &str) -> Vec<AttachmentInfo> {
+    let attachments = get_all_attachments(vault_directory);
+    let notes: Vec<String> = helpers::get_all_notes(vault_directory)
+        .into_iter()
+        .map(|(_, rel_path)| rel_path)
+        .collect();
+    let resolver = LinkResolver::with_notes(vault_directory, notes.clone());
+    let mut used: HashSet<String> = HashSet::new();
+
+    for rel_path in notes {
+        let content = helpers::get_content(None, Some(&rel_path), Some(vault_directory));
+        for reference in extract_attachment_references(&resolver, &content, &rel_path, &attachments)
+        {
+            // Any file an ambiguous reference could mean is still in use
+            for attachment in reference.attachment.into_iter().chain(reference.candidates) {
+                used.insert(attachment.relative_path);
+            }
+        }
+    }
+
+    attachments
+        .into_iter()
+        .filter(|a| !used.contains(&a.relative_path))
+        .collect()
+}
+
+/// Returns a path in `folder` for `file_name` that doesn't exist yet, adding
+/// a number to the name if needed: `image.png`, `image 1.png`, ...
+pub fn unique_path(folder: &Path, file_name: &str) -> PathBuf {
+    let candidate = folder.join(file_name);
+    if !candidate.exists() {
+        return candidate;
+    }
+
+    let path = Path::new(file_name);
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| file_name.to_string());
+    let extension = path.extension().map(|e| e.to_string_lossy().to_string());
+
+    let mut counter = 1;
+    loop {
+        let name = match &extension {
+            Some(ext) => format!("{} {}.{}", stem, counter, ext),
+            None => format!("{} {}", stem, counter),
+        };
+        let candidate = folder.join(name);
+        if !candidate.exists() {
+            return candidate;
+        }
+        counter += 1;
+    }
+}
+
+/// Writes a new attachment into the attachments folder under a unique name
+fn store_attachment(
+    vault_directory: &str,
+    attachments_folder: Option<&str>,
+    file_name: &str,
+    write: impl FnOnce(&Path) -> Result<(), io::Error>,
+) -> Result<AttachmentInfo, io::Error> {
+    let vault_path = Path::new(vault_directory);
+    let folder = attachments_folder
+        .map(|f| f.trim_matches('/'))
+        .filter(|f| !f.is_empty())
+        .unwrap_or(DEFAULT_ATTACHMENTS_FOLDER);
+    if folder.split(['/', '\\']).any(|segment| segment == "..") {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Attachments folder must be inside the vault",
+        ));
+    }
+
+    let folder_path = vault_path.join(folder);
+    fs::create_dir_all(&folder_path)?;
+
+    // Only keep the file name, never a path supplied by the caller
+    let file_name = Path::new(file_name)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    if file_name.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Attachment file name is empty",
+        ));
+    }
+
+    let target = unique_path(&folder_path, &file_name);
+    write(&target)?;
+
+    attachment_info(&target, vault_path)
+        .ok_or_else(|| io::Error::other("Stored attachment is outside the vault"))
+}
+
+/// Copies an external file into the vault's attachments folder
+pub fn import_attachment(
+    source_file: &str,
+    vault_directory: &str,
+    attachments_folder: Option<&str>,
+) -> Result<AttachmentInfo, io::Error> {
+    let source = Path::new(source_file);
+    if !source.is_file() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("File not found: {}", source_file),
+        ));
+    }
+
+    let file_name = source
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    store_attachment(vault_directory, attachments_folder, &file_name, |target| {
+        fs::copy(source, target).map(|_| ())
+    })
+}
+
+/// Saves pasted data (e.g. an image from the clipboard) as a new attachment
+pub fn save_pasted_attachment(
+    data: &[u8],
+    file_name: Option<&str>,
+    extension: Option<&str>,
+    vault_directory: &str,
+    attachments_folder: Option<&str>,
+) -> Result<AttachmentInfo, io::Error> {
+    let file_name = match file_name {
+        Some(name) if !name.trim().is_empty() => name.trim().to_string(),
+        _ => {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            format!(
+                "Pasted image {}.{}",
+                timestamp,
+                extension.unwrap_or("png").trim_start_matches('.')
+            )
+        }
+    };
+
+    store_attachment(vault_directory, attachments_folder, &file_name, |target| {
+        fs::write(target, data)
+    })
+}
+
+/// Maps a `vault://localhost/<relative path>` request path to a file inside
+/// the vault, refusing anything that escapes it
+pub fn resolve_protocol_path(vault_path: &Path, request_path: &str) -> Option<PathBuf> {
+    let relative = percent_decode(request_path.trim_start_matches('/'));
+    if relative.is_empty() {
+        return None;
+    }
+
+    let vault_root = fs::canonicalize(vault_path).ok()?;
+    let requested = fs::canonicalize(vault_root.join(relative)).ok()?;
+    if requested.starts_with(&vault_root) && requested.is_file() {
+        Some(requested)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attachment(relative_path: &str) -> AttachmentInfo {
+        let path = Path::new(relative_path);
+        let extension = path.extension().unwrap().to_string_lossy().to_string();
+        AttachmentInfo {
+            name: path.file_name().unwrap().to_string_lossy().to_string(),
+            absolute_path: format!("/vault/{}", relative_path),
+            relative_path: relative_path.to_string(),
+            kind: attachment_kind(&extension).to_string(),
+            extension,
+            size: 0,
+        }
+    }
+
+    #[test]
+    fn resolves_wiki_embeds_by_unique_suffix() {
+        let all = vec![attachment("a/image.png"), attachment("b/other.png")];
+        let resolution = resolve_attachment(&all, "note.md", "image.png", true);
+        assert_eq!(resolution.status, "resolved");
+        assert_eq!(resolution.attachment.unwrap().relative_path, "a/image.png");
+        assert_eq!(
+            resolve_attachment(&all, "note.md", "missing.png", true).status,
+            "unresolved"
+        );
+    }
+
+    #[test]
+    fn prefers_the_attachment_next_to_the_note() {
+        let all = vec![attachment("a/image.png"), attachment("b/image.png")];
+        let resolution = resolve_attachment(&all, "b/note.md", "image.png", true);
+        assert_eq!(resolution.attachment.unwrap().relative_path, "b/image.png");
+    }
+
+    #[test]
+    fn reports_ambiguous_attachments() {
+        let all = vec![attachment("a/image.png"), attachment("b/image.png")];
+        let resolution = resolve_attachment(&all, "note.md", "image.png", true);
+        assert_eq!(resolution.status, "ambiguous");
+        assert!(resolution.attachment.is_none());
+        assert_eq!(resolution.candidates.len(), 2);
+    }
+
+    #[test]
+    fn resolves_markdown_links_relative_to_the_note() {
+        let all = vec![attachment("notes/img/a%b.png"), attachment("img/x y.png")];
+        let resolution = resolve_attachment(&all, "notes/n.md", "img/a%25b.png", false);
+        assert_eq!(
+            resolution.attachment.unwrap().relative_path,
+            "notes/img/a%b.png"
+        );
+        let resolution = resolve_attachment(&all, "notes/n.md", "../img/x%20y.png", false);
+        assert_eq!(resolution.attachment.unwrap().relative_path, "img/x y.png");
+    }
+
+    #[test]
+    fn skips_notes_with_dots_in_their_names() {
+        let vault = tempfile::tempdir().unwrap();
+        let vault_directory = vault.path().to_string_lossy();
+        fs::write(
+            vault.path().join("a.md"),
+            "![[v1.2 release]] ![[image.png]]",
+        )
+        .unwrap();
+        fs::write(vault.path().join("v1.2 release.md"), "").unwrap();
+        fs::write(vault.path().join("image.png"), b"png").unwrap();
+        fs::write(vault.path().join("unused.png"), b"png").unwrap();
+
+        let references = get_note_attachments("a.md", &vault_directory);
+        let targets: Vec<&str> = references.iter().map(|r| r.target.as_str()).collect();
+        assert_eq!(targets, ["image.png"]);
+        let unused: Vec<String> = find_unused_attachments(&vault_directory)
+            .into_iter()
+            .map(|a| a.relative_path)
+            .collect();
+        assert_eq!(unused, ["unused.png"]);
+    }
+
+    #[test]
+    fn rejects_attachment_folders_outside_the_vault() {
+        let vault = tempfile::tempdir().unwrap();
+        let vault_directory = vault.path().to_string_lossy();
+        for folder in [
+            "../outside",
+            "files/../../outside",
+            "files\\..\\..\\outside",
+        ] {
+            let stored = store_attachment(&vault_directory, Some(folder), "a.png", |path| {
+                fs::write(path, b"png")
+            });
+            assert_eq!(
+                stored.unwrap_err().kind(),
+                io::ErrorKind::InvalidInput,
+                "{}",
+                folder
+            );
+        }
+
+        let stored = store_attachment(&vault_directory, Some("files"), "../a.png", |path| {
+            fs::write(path, b"png")
+        })
+        .unwrap();
+        assert_eq!(stored.relative_path, "files/a.png");
+        let again = store_attachment(&vault_directory, None, "a.png", |path| {
+            fs::write(path, b"png")
+        })
+        .unwrap();
+        assert_eq!(again.relative_path, "attachments/a.png");
+    }
+}
@@ -0,0 +1,263 @@
+// src/notes/embeds.rs
+use super::attachments::{self, AttachmentInfo};
+use super::helpers;
+use super::links::{self, LinkResolver};
+use super::outline;
+use serde::{Deserialize, Serialize};
+use std::cell::OnceCell;
+
+/// Maximum nesting of embeds when the caller doesn't provide one
+pub const DEFAULT_MAX_DEPTH: usize = 5;
+
+/// A range of the expanded text (byte offsets, end exclusive) and the note it
+/// was copied from. `source_line` is the 1-based line in that note's content
+/// where the range starts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceRange {
+    pub start: usize,
+    pub end: usize,
+    pub relative_path: String,
+    pub source_line: usize,
+    pub depth: usize,
+}
+
+/// A note's content with all `![[embeds]]` expanded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpandedNote {
+    pub relative_path: String,
+    pub content: String,
+    pub source_map: Vec<SourceRange>,
+    pub unresolved: Vec<String>,
+}
+
+struct Expander<'a> {
+    vault_directory: &'a str,
+    resolver: LinkResolver,
+    attachments: OnceCell<Vec<AttachmentInfo>>,
+    max_depth: usize,
+    content: String,
+    source_map: Vec<SourceRange>,
+    unresolved: Vec<String>,
+}
+
+impl Expander<'_> {
+    /// Appends a piece of text that was copied verbatim from a note
+    fn push_text(&mut self, text: &str, relative_path: &str, source_line: usize, depth: usize) {
+        if text.is_empty() {
+            return;
+        }
+
+        let start = self.content.len();
+        self.content.push_str(text);
+        self.source_map.push(SourceRange {
+            start,
+            end: self.content.len(),
+            relative_path: relative_path.to_string(),
+            source_line,
+            depth,
+        });
+    }
+
+    fn expand(
+        &mut self,
+        text: &str,
+        relative_path: &str,
+        first_line: usize,
+        depth: usize,
+        stack: &mut Vec<String>,
+    ) {
+        let mut last_index = 0;
+        let mut line = first_line;
+        let embeds = links::extract_wiki_links(text)
+            .into_iter()
+            .filter(|link| link.embed);
+
+        for link in embeds {
+            let target = link.target.as_str();
+
+            let before = &text[last_index..link.start];
+            self.push_text(before, relative_path, line, depth);
+            line += before.matches('\n').count();
+            last_index = link.end;
+
+            // Attachments like ![[image.png]] are left for the renderer
+            let all_attachments = self
+                .attachments
+                .get_or_init(|| attachments::get_all_attachments(self.vault_directory));
+            if links::is_attachment_target(
+                &self.resolver,
+                target,
+                Some(relative_path),
+                all_attachments,
+            ) {
+                self.push_text(link.raw.as_str(), relative_path, line, depth);
+                continue;
+            }
+
+            let section = match outline::resolve_reference_with(
+                &self.resolver,
+                target,
+                Some(relative_path),
+                self.vault_directory,
+            ) {
+                Ok(section) => section,
+                Err(e) => {
+                    println!("Could not resolve embed '{}': {}", target, e);
+                    self.unresolved.push(target.to_string());
+                    self.push_text(link.raw.as_str(), relative_path, line, depth);
+                    continue;
+                }
+            };
+
+            // Keep the raw embed when we'd loop or nest too deep
+            let key = format!(
+                "{}#{}:{}",
+                section.relative_path, section.start_line, section.end_line
+            );
+            if stack.contains(&key) || depth >= self.max_depth {
+                println!("Skipping embed '{}' (cycle or depth limit reached)", target);
+                self.push_text(link.raw.as_str(), relative_path, line, depth);
+                continue;
+            }
+
+            stack.push(key);
+            self.expand(
+                &section.text,
+                &section.relative_path,
+                section.start_line,
+                depth + 1,
+                stack,
+            );
+            stack.pop();
+        }
+
+        self.push_text(&text[last_index..], relative_path, line, depth);
+    }
+}
+
+/// Expands `![[note]]`, `![[note#section]]` and `![[note^block]]` embeds in a
+/// note's content recursively, stopping at cycles and at `max_depth`
+pub fn expand_embeds(
+    relative_path: &str,
+    vault_directory: &str,
+    max_depth: Option<usize>,
+) -> ExpandedNote {
+    let content = helpers::get_content(None, Some(relative_path), Some(vault_directory));
+    expand_embeds_in(&content, relative_path, vault_directory, max_depth)
+}
+
+/// Expands embeds in a piece of text that belongs to the given note
+pub fn expand_embeds_in(
+    content: &str,
+    relative_path: &str,
+    vault_directory: &str,
+    max_depth: Option<usize>,
+) -> ExpandedNote {
+    let mut expander = Expander {
+        vault_directory,
+        resolver: LinkResolver::new(vault_directory),
+        attachments: OnceCell::new(),
+        max_depth: max_depth.unwrap_or(DEFAULT_MAX_DEPTH),
+        content: String::new(),
+        source_map: Vec::new(),
+        unresolved: Vec::new(),
+    };
+
+    // The root note uses the same key a whole-note embed of it would get
+    let line_count = content.lines().count().max(1);
+    let mut stack = vec![format!("{}#1:{}", relative_path, line_count)];
+    expander.expand(content, relative_path, 1, 0, &mut stack);
+
+    ExpandedNote {
+        relative_path: relative_path.to_string(),
+        content: expander.content,
+        source_map: expander.source_map,
+        unresolved: expander.unresolved,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn vault(notes: &[(&str, &str)]) -> tempfile::TempDir {
+        let vault = tempfile::tempdir().unwrap();
+        for (relative_path, content) in notes {
+            fs::write(vault.path().join(relative_path), content).unwrap();
+        }
+        vault
+    }
+
+    #[test]
+    fn expands_sections_with_a_source_map() {
+        let vault = vault(&[
+            ("root.md", "Intro\n![[part#Second]]\nOutro"),
+            ("part.md", "# First\none\n# Second\ntwo"),
+        ]);
+        let expanded = expand_embeds("root.md", vault.path().to_str().unwrap(), None);
+
+        assert_eq!(expanded.content, "Intro\n# Second\ntwo\nOutro");
+        assert!(expanded.unresolved.is_empty());
+        let embedded = expanded
+            .source_map
+            .iter()
+            .find(|range| range.relative_path == "part.md")
+            .unwrap();
+        assert_eq!(
+            &expanded.content[embedded.start..embedded.end],
+            "# Second\ntwo"
+        );
+        assert_eq!((embedded.source_line, embedded.depth), (3, 1));
+        let outro = expanded.source_map.last().unwrap();
+        assert_eq!(
+            (outro.relative_path.as_str(), outro.source_line),
+            ("root.md", 2)
+        );
+    }
+
+    #[test]
+    fn keeps_cycles_missing_notes_and_attachments_raw() {
+        let vault = vault(&[
+            ("a.md", "A ![[b]] ![[missing]] ![[image.png]]"),
+            ("b.md", "B ![[a]]"),
+        ]);
+        let expanded = expand_embeds("a.md", vault.path().to_str().unwrap(), None);
+
+        assert_eq!(expanded.content, "A B ![[a]] ![[missing]] ![[image.png]]");
+        assert_eq!(expanded.unresolved, vec!["missing".to_string()]);
+    }
+
+    #[test]
+    fn expands_notes_with_dots_in_their_names() {
+        let vault = vault(&[
+            ("a.md", "![[v1.2 release]] ![[Dr. Smith]]"),
+            ("v1.2 release.md", "notes"),
+            ("Dr. Smith.md", "contact"),
+        ]);
+        let expanded = expand_embeds("a.md", vault.path().to_str().unwrap(), None);
+        assert_eq!(expanded.content, "notes contact");
+        assert!(expanded.unresolved.is_empty());
+    }
+
+    #[test]
+    fn stops_at_the_depth_limit() {
+        let vault = vault(&[("a.md", "a ![[b]]"), ("b.md", "b ![[c]]"), ("c.md", "c")]);
+        let expanded = expand_embeds("a.md", vault.path().to_str().unwrap(), Some(1));
+        assert_eq!(expanded.content, "a b ![[c]]");
+    }
+
+    #[test]
+    fn leaves_encrypted_notes_unresolved() {
+        let vault = vault(&[
+            ("a.md", "![[secret]]"),
+            (
+                "secret.md",
+                "---\nencrypted: true\n---\n-----BEGIN ENCRYPTED NOTE-----\nAQ==\n-----END ENCRYPTED NOTE-----\n",
+            ),
+        ]);
+        let expanded = expand_embeds("a.md", vault.path().to_str().unwrap(), None);
+        assert_eq!(expanded.content, "![[secret]]");
+        assert_eq!(expanded.unresolved, vec!["secret".to_string()]);
+    }
+}
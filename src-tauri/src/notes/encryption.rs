@@ -0,0 +1,752 @@
+// src/notes/encryption.rs
+use super::formats;
+use super::helpers;
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use zeroize::Zeroizing;
+
+/// Salt, key derivation parameters and passphrase check of a vault's key,
+/// relative to the vault root. Kept inside the vault so its encrypted notes
+/// can be opened wherever it is synced to.
+pub const KEY_FILE_PATH: &str = ".notemancy/encryption";
+
+/// Frontmatter key marking encrypted notes
+pub const ENCRYPTED_KEY: &str = "encrypted";
+
+/// Event emitted with the vault directory when its key is locked
+pub const LOCKED_EVENT: &str = "encryption-locked";
+
+const BEGIN_MARKER: &str = "-----BEGIN ENCRYPTED NOTE-----";
+const END_MARKER: &str = "-----END ENCRYPTED NOTE-----";
+
+/// Layout of key files and encrypted bodies
+const FORMAT_VERSION: u8 = 1;
+
+/// Argon2id memory cost in KiB. Key files outside these bounds are
+/// rejected: below them the passphrase is cheap to guess, above them a
+/// tampered file could make unlocking exhaust the machine.
+const MEMORY_KIB: RangeInclusive<u32> = 19_456..=1_048_576;
+/// Argon2id passes
+const ITERATIONS: RangeInclusive<u32> = 2..=16;
+/// Argon2id lanes
+const PARALLELISM: RangeInclusive<u32> = 1..=8;
+
+/// Argon2id parameters for new keys
+const DEFAULT_PARAMS: KdfParams = KdfParams {
+    memory_kib: 65_536,
+    iterations: 3,
+    parallelism: 1,
+};
+
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+const TAG_LENGTH: usize = 16;
+
+/// Base64 is wrapped at this width, like PEM
+const LINE_WIDTH: usize = 64;
+
+/// Message sealed in the key file to check passphrases against
+const CHECK_MESSAGE: &[u8] = b"notemancy vault key";
+
+/// Argon2id parameters of a key
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct KdfParams {
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+}
+
+impl KdfParams {
+    fn check(&self) -> Result<(), String> {
+        let bounded = |name: &str, value: u32, range: RangeInclusive<u32>| {
+            if range.contains(&value) {
+                Ok(())
+            } else {
+                Err(format!(
+                    "Invalid {} in {}: {} is outside {}..={}",
+                    name,
+                    KEY_FILE_PATH,
+                    value,
+                    range.start(),
+                    range.end()
+                ))
+            }
+        };
+        bounded("memory_kib", self.memory_kib, MEMORY_KIB)?;
+        bounded("iterations", self.iterations, ITERATIONS)?;
+        bounded("parallelism", self.parallelism, PARALLELISM)
+    }
+}
+
+/// A vault's key file
+#[derive(Debug, Serialize, Deserialize)]
+struct KeyFile {
+    version: u8,
+    #[serde(flatten)]
+    params: KdfParams,
+    salt: String,
+    /// Nonce and sealed `CHECK_MESSAGE`, in base64
+    check: String,
+}
+
+/// The key derived from a vault's passphrase. It is wiped from memory when
+/// dropped.
+pub struct VaultKey {
+    salt: [u8; SALT_LENGTH],
+    key: Zeroizing<[u8; 32]>,
+}
+
+impl VaultKey {
+    /// Derives the key from a passphrase with Argon2id. Slow on purpose.
+    fn derive(
+        passphrase: &str,
+        salt: [u8; SALT_LENGTH],
+        params: KdfParams,
+    ) -> Result<Self, String> {
+        params.check()?;
+        let params = Params::new(
+            params.memory_kib,
+            params.iterations,
+            params.parallelism,
+            Some(32),
+        )
+        .map_err(|e| format!("Invalid key parameters: {}", e))?;
+        let mut key = Zeroizing::new([0u8; 32]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), &salt, key.as_mut_slice())
+            .map_err(|e| format!("Failed to derive the vault key: {}", e))?;
+        Ok(VaultKey { salt, key })
+    }
+
+    fn cipher(&self) -> ChaCha20Poly1305 {
+        ChaCha20Poly1305::new(Key::from_slice(self.key.as_slice()))
+    }
+
+    /// Encrypts with a fresh random nonce. Returns the nonce followed by
+    /// the ciphertext and its tag.
+    fn seal(&self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, String> {
+        let mut nonce = [0u8; NONCE_LENGTH];
+        getrandom::getrandom(&mut nonce)
+            .map_err(|e| format!("Failed to generate a nonce: {}", e))?;
+        let mut sealed = nonce.to_vec();
+        sealed.extend(seal(&self.cipher(), &nonce, plaintext, aad)?);
+        Ok(sealed)
+    }
+
+    /// Decrypts what `seal` returned, or fails if it was tampered with or
+    /// sealed with another key
+    fn open(&self, sealed: &[u8], aad: &[u8]) -> Option<Zeroizing<Vec<u8>>> {
+        if sealed.len() < NONCE_LENGTH + TAG_LENGTH {
+            return None;
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LENGTH);
+        self.cipher()
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad,
+                },
+            )
+            .ok()
+            .map(Zeroizing::new)
+    }
+}
+
+/// ChaCha20-Poly1305 (RFC 8439) with a given nonce
+fn seal(
+    cipher: &ChaCha20Poly1305,
+    nonce: &[u8; NONCE_LENGTH],
+    plaintext: &[u8],
+    aad: &[u8],
+) -> Result<Vec<u8>, String> {
+    cipher
+        .encrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .map_err(|_| "Failed to encrypt".to_string())
+}
+
+/// Associated data of a key file's check and of encrypted bodies: the
+/// format version and salt, so neither can be swapped out
+fn header(salt: &[u8]) -> Vec<u8> {
+    let mut header = vec![FORMAT_VERSION];
+    header.extend_from_slice(salt);
+    header
+}
+
+fn key_file_path(vault_directory: &str) -> std::path::PathBuf {
+    Path::new(vault_directory).join(KEY_FILE_PATH)
+}
+
+/// Returns true if encryption has been set up for a vault
+pub fn has_key(vault_directory: &str) -> bool {
+    key_file_path(vault_directory).is_file()
+}
+
+/// Derives a vault's key from its passphrase, checking the passphrase
+/// against the vault's key file. Fails if the vault has no passphrase yet.
+/// Takes a while: call it off the async runtime.
+pub fn unlock_key(vault_directory: &str, passphrase: &str) -> Result<VaultKey, String> {
+    if passphrase.is_empty() {
+        return Err("The passphrase can't be empty".to_string());
+    }
+
+    let path = key_file_path(vault_directory);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(
+                "Encryption isn't set up for this vault: set a passphrase first".to_string(),
+            )
+        }
+        Err(e) => return Err(format!("Failed to read {}: {}", KEY_FILE_PATH, e)),
+    };
+
+    let key_file: KeyFile =
+        serde_json::from_str(&content).map_err(|e| format!("Invalid {}: {}", KEY_FILE_PATH, e))?;
+    if key_file.version != FORMAT_VERSION {
+        return Err(format!(
+            "{} was written by a newer version of the app",
+            KEY_FILE_PATH
+        ));
+    }
+    let salt: [u8; SALT_LENGTH] = BASE64
+        .decode(&key_file.salt)
+        .ok()
+        .and_then(|salt| salt.try_into().ok())
+        .ok_or_else(|| format!("Invalid salt in {}", KEY_FILE_PATH))?;
+    let check = BASE64
+        .decode(&key_file.check)
+        .map_err(|_| format!("Invalid check in {}", KEY_FILE_PATH))?;
+
+    let key = VaultKey::derive(passphrase, salt, key_file.params)?;
+    match key.open(&check, &header(&salt)) {
+        Some(message) if message.as_slice() == CHECK_MESSAGE => Ok(key),
+        _ => Err("Wrong passphrase".to_string()),
+    }
+}
+
+/// Sets the passphrase of a vault that has none yet, writing its key file.
+/// The passphrase is typed twice so a typo can't lock the notes away.
+/// Takes a while: call it off the async runtime.
+pub fn set_passphrase(
+    vault_directory: &str,
+    passphrase: &str,
+    confirmation: &str,
+) -> Result<VaultKey, String> {
+    create_key(vault_directory, passphrase, confirmation, DEFAULT_PARAMS)
+}
+
+fn create_key(
+    vault_directory: &str,
+    passphrase: &str,
+    confirmation: &str,
+    params: KdfParams,
+) -> Result<VaultKey, String> {
+    if passphrase.is_empty() {
+        return Err("The passphrase can't be empty".to_string());
+    }
+    if passphrase != confirmation {
+        return Err("The passphrases don't match".to_string());
+    }
+    if has_key(vault_directory) {
+        return Err("This vault already has a passphrase".to_string());
+    }
+
+    let mut salt = [0u8; SALT_LENGTH];
+    getrandom::getrandom(&mut salt).map_err(|e| format!("Failed to generate a salt: {}", e))?;
+    let key = VaultKey::derive(passphrase, salt, params)?;
+
+    let key_file = KeyFile {
+        version: FORMAT_VERSION,
+        params,
+        salt: BASE64.encode(salt),
+        check: BASE64.encode(key.seal(CHECK_MESSAGE, &header(&salt))?),
+    };
+    let path = key_file_path(vault_directory);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to write {}: {}", KEY_FILE_PATH, e))?;
+    }
+    let content = serde_json::to_string_pretty(&key_file).map_err(|e| e.to_string())?;
+    // Never replace a key file written in the meantime: notes encrypted
+    // with it would be lost
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .map_err(|e| format!("Failed to write {}: {}", KEY_FILE_PATH, e))?;
+    Ok(key)
+}
+
+/// Returns true if a note body (frontmatter stripped) is encrypted
+pub fn is_encrypted(body: &str) -> bool {
+    body.trim_start().starts_with(BEGIN_MARKER)
+}
+
+/// Returns true if a note in the vault is encrypted
+pub fn is_encrypted_note(relative_path: &str, vault_directory: &str) -> bool {
+    is_encrypted(&helpers::get_content(
+        None,
+        Some(relative_path),
+        Some(vault_directory),
+    ))
+}
+
+/// Encrypts a note body into an armored block: version, salt, nonce and
+/// ciphertext with its tag, in base64 between markers
+pub fn encrypt_body(key: &VaultKey, body: &str) -> Result<String, String> {
+    let header = header(&key.salt);
+    let sealed = key.seal(body.as_bytes(), &header)?;
+    let mut blob = header;
+    blob.extend(sealed);
+    let encoded = BASE64.encode(blob);
+
+    let mut armored = format!("{}\n", BEGIN_MARKER);
+    for line in encoded.as_bytes().chunks(LINE_WIDTH) {
+        armored.push_str(&String::from_utf8_lossy(line));
+        armored.push('\n');
+    }
+    armored.push_str(END_MARKER);
+    armored.push('\n');
+    Ok(armored)
+}
+
+/// Decrypts an armored note body, checking it wasn't tampered with
+pub fn decrypt_body(key: &VaultKey, body: &str) -> Result<String, String> {
+    let inner = body
+        .trim()
+        .strip_prefix(BEGIN_MARKER)
+        .and_then(|rest| rest.strip_suffix(END_MARKER))
+        .ok_or_else(|| "Note is not an encrypted note".to_string())?;
+    let encoded: String = inner.split_whitespace().collect();
+    let blob = BASE64
+        .decode(encoded)
+        .map_err(|_| "Encrypted note is damaged".to_string())?;
+
+    let header_length = 1 + SALT_LENGTH;
+    if blob.len() < header_length + NONCE_LENGTH + TAG_LENGTH {
+        return Err("Encrypted note is damaged".to_string());
+    }
+    if blob[0] != FORMAT_VERSION {
+        return Err("Note was encrypted by a newer version of the app".to_string());
+    }
+    let (header, sealed) = blob.split_at(header_length);
+    if header[1..] != key.salt {
+        return Err("Note was encrypted with another vault key".to_string());
+    }
+    let plaintext = key
+        .open(sealed, header)
+        .ok_or_else(|| "Encrypted note is damaged or was tampered with".to_string())?;
+    String::from_utf8(plaintext.to_vec()).map_err(|_| "Encrypted note is damaged".to_string())
+}
+
+/// Returns true if a frontmatter line sets the encrypted flag
+fn is_flag_line(line: &str) -> bool {
+    !line.starts_with([' ', '\t'])
+        && line
+            .split_once(':')
+            .is_some_and(|(key, _)| key.trim() == ENCRYPTED_KEY)
+}
+
+/// Encrypts a markdown note in place and flags it in its frontmatter.
+/// Returns false if it was encrypted already.
+pub fn encrypt_note(
+    key: &VaultKey,
+    relative_path: &str,
+    vault_directory: &str,
+) -> Result<bool, String> {
+    let path = helpers::resolve_note_path(None, Some(relative_path), Some(vault_directory))
+        .map_err(|e| format!("Failed to read note: {}", e))?;
+    if !formats::is_markdown_path(&path) {
+        return Err("Only markdown notes can be encrypted".to_string());
+    }
+    let content =
+        helpers::read_file_content(&path).map_err(|e| format!("Failed to read note: {}", e))?;
+    let format = formats::format_for_path(&path);
+    let (frontmatter, body) = format.split(&content);
+    if is_encrypted(&body) {
+        return Ok(false);
+    }
+
+    let mut lines: Vec<&str> = frontmatter
+        .as_deref()
+        .unwrap_or_default()
+        .lines()
+        .filter(|line| !is_flag_line(line))
+        .collect();
+    let flag = format!("{}: true", ENCRYPTED_KEY);
+    lines.push(&flag);
+
+    let encrypted = format.join(&lines.join("\n"), &encrypt_body(key, &body)?);
+    fs::write(&path, encrypted).map_err(|e| format!("Failed to write note: {}", e))?;
+    Ok(true)
+}
+
+/// Decrypts a note in place for good and drops its flag. Returns false if
+/// it wasn't encrypted.
+pub fn decrypt_note(
+    key: &VaultKey,
+    relative_path: &str,
+    vault_directory: &str,
+) -> Result<bool, String> {
+    let path = helpers::resolve_note_path(None, Some(relative_path), Some(vault_directory))
+        .map_err(|e| format!("Failed to read note: {}", e))?;
+    let content =
+        helpers::read_file_content(&path).map_err(|e| format!("Failed to read note: {}", e))?;
+    let format = formats::format_for_path(&path);
+    let (frontmatter, body) = format.split(&content);
+    if !is_encrypted(&body) {
+        return Ok(false);
+    }
+
+    let body = decrypt_body(key, &body)?;
+    let frontmatter: Vec<&str> = frontmatter
+        .as_deref()
+        .unwrap_or_default()
+        .lines()
+        .filter(|line| !is_flag_line(line))
+        .collect();
+    let decrypted = if frontmatter.iter().all(|line| line.trim().is_empty()) {
+        body
+    } else {
+        format.join(&frontmatter.join("\n"), &body)
+    };
+    fs::write(&path, decrypted).map_err(|e| format!("Failed to write note: {}", e))?;
+    Ok(true)
+}
+
+struct Session {
+    key: Arc<VaultKey>,
+    timeout: Duration,
+    last_used: Instant,
+}
+
+impl Session {
+    fn remaining(&self) -> Duration {
+        self.timeout.saturating_sub(self.last_used.elapsed())
+    }
+}
+
+/// Keys of the unlocked vaults, by vault. A key is dropped once its vault
+/// has gone unused for the lock timeout.
+#[derive(Default)]
+pub struct UnlockedVaults {
+    sessions: Mutex<HashMap<String, Session>>,
+    /// Wakes the lock timer when a vault is unlocked
+    unlocked: Notify,
+}
+
+impl UnlockedVaults {
+    /// Keeps a vault's key until it has gone unused for `timeout`
+    pub fn unlock(&self, vault_directory: &str, key: VaultKey, timeout: Duration) {
+        if let Ok(mut sessions) = self.sessions.lock() {
+            sessions.insert(
+                vault_directory.to_string(),
+                Session {
+                    key: Arc::new(key),
+                    timeout,
+                    last_used: Instant::now(),
+                },
+            );
+        }
+        self.unlocked.notify_one();
+    }
+
+    /// Drops the key of one vault, or of all vaults. Returns the vaults
+    /// that were locked.
+    pub fn lock(&self, vault_directory: Option<&str>) -> Vec<String> {
+        self.lock_where(|vault, _| vault_directory.is_none_or(|v| v == vault))
+    }
+
+    fn lock_where(&self, locks: impl Fn(&str, &Session) -> bool) -> Vec<String> {
+        let Ok(mut sessions) = self.sessions.lock() else {
+            return Vec::new();
+        };
+        let locked: Vec<String> = sessions
+            .iter()
+            .filter(|(vault, session)| locks(vault, session))
+            .map(|(vault, _)| vault.clone())
+            .collect();
+        for vault in &locked {
+            sessions.remove(vault);
+        }
+        locked
+    }
+
+    /// Returns the key of an unlocked vault and restarts its timeout
+    pub fn key(&self, vault_directory: &str) -> Option<Arc<VaultKey>> {
+        let mut sessions = self.sessions.lock().ok()?;
+        let session = sessions.get_mut(vault_directory)?;
+        if session.remaining().is_zero() {
+            sessions.remove(vault_directory);
+            return None;
+        }
+        session.last_used = Instant::now();
+        Some(session.key.clone())
+    }
+
+    /// Time left before an unlocked vault locks, or `None` if it is locked
+    pub fn remaining(&self, vault_directory: &str) -> Option<Duration> {
+        let sessions = self.sessions.lock().ok()?;
+        sessions.get(vault_directory).map(Session::remaining)
+    }
+
+    /// Locks vaults as they time out, calling `on_lock` with each one. Runs
+    /// for the life of the app as its single lock timer: it sleeps until the
+    /// next vault is due, and an unlock wakes it to pick up the new deadline.
+    pub async fn run_lock_timer(&self, on_lock: impl Fn(&str)) {
+        loop {
+            let next = self
+                .sessions
+                .lock()
+                .ok()
+                .and_then(|sessions| sessions.values().map(Session::remaining).min());
+            match next {
+                Some(wait) => {
+                    let _ = tokio::time::timeout(wait, self.unlocked.notified()).await;
+                }
+                None => self.unlocked.notified().await,
+            }
+            for vault in self.lock_where(|_, session| session.remaining().is_zero()) {
+                on_lock(&vault);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The cheapest parameters allowed, to keep the tests quick
+    const TEST_PARAMS: KdfParams = KdfParams {
+        memory_kib: *MEMORY_KIB.start(),
+        iterations: *ITERATIONS.start(),
+        parallelism: 1,
+    };
+
+    fn test_key() -> VaultKey {
+        VaultKey {
+            salt: [7; SALT_LENGTH],
+            key: Zeroizing::new([9; 32]),
+        }
+    }
+
+    fn vault_with_key(passphrase: &str) -> tempfile::TempDir {
+        let vault = tempfile::tempdir().unwrap();
+        let path = vault.path().to_str().unwrap();
+        create_key(path, passphrase, passphrase, TEST_PARAMS).unwrap();
+        vault
+    }
+
+    fn hex(text: &str) -> Vec<u8> {
+        let digits: Vec<char> = text.chars().filter(|c| c.is_ascii_hexdigit()).collect();
+        digits
+            .chunks(2)
+            .map(|pair| u8::from_str_radix(&pair.iter().collect::<String>(), 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn seals_the_rfc_8439_test_vector() {
+        // RFC 8439, section 2.8.2
+        let key: [u8; 32] = std::array::from_fn(|i| 0x80 + i as u8);
+        let nonce: [u8; NONCE_LENGTH] = hex("07000000 4041424344454647").try_into().unwrap();
+        let aad = hex("50515253 c0c1c2c3c4c5c6c7");
+        let plaintext = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.";
+
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+        let sealed = seal(&cipher, &nonce, plaintext, &aad).unwrap();
+        let (ciphertext, tag) = sealed.split_at(plaintext.len());
+        assert_eq!(
+            ciphertext,
+            hex(
+                "d31a8d34648e60db7b86afbc53ef7ec2 a4aded51296e08fea9e2b5a736ee62d6
+                 3dbea45e8ca9671282fafb69da92728b 1a71de0a9e060b2905d6a5b67ecd3b36
+                 92ddbd7f2d778b8c9803aee328091b58 fab324e4fad675945585808b4831d7bc
+                 3ff4def08e4b7a9de576d26586cec64b 6116"
+            )
+        );
+        assert_eq!(tag, hex("1ae10b594f09e26a7e902ecbd0600691"));
+    }
+
+    #[test]
+    fn unlocks_with_the_passphrase_it_was_set_with() {
+        let vault = vault_with_key("correct horse");
+        let path = vault.path().to_str().unwrap();
+
+        let key = unlock_key(path, "correct horse").unwrap();
+        let encrypted = encrypt_body(&key, "# Secret\n\nbody").unwrap();
+        assert!(is_encrypted(&encrypted));
+        assert!(!encrypted.contains("Secret"));
+
+        let again = unlock_key(path, "correct horse").unwrap();
+        assert_eq!(
+            decrypt_body(&again, &encrypted).unwrap(),
+            "# Secret\n\nbody"
+        );
+    }
+
+    #[test]
+    fn rejects_a_wrong_passphrase() {
+        let vault = vault_with_key("correct horse");
+        let path = vault.path().to_str().unwrap();
+        assert_eq!(
+            unlock_key(path, "battery staple").err().unwrap(),
+            "Wrong passphrase"
+        );
+        assert!(unlock_key(path, "").is_err());
+    }
+
+    #[test]
+    fn unlocking_needs_a_passphrase_set_first() {
+        let vault = tempfile::tempdir().unwrap();
+        let path = vault.path().to_str().unwrap();
+        assert!(unlock_key(path, "anything").is_err());
+        assert!(!has_key(path));
+
+        assert_eq!(
+            create_key(path, "one", "two", TEST_PARAMS).err().unwrap(),
+            "The passphrases don't match"
+        );
+        assert!(!has_key(path));
+
+        create_key(path, "one", "one", TEST_PARAMS).unwrap();
+        assert!(create_key(path, "two", "two", TEST_PARAMS).is_err());
+        assert!(unlock_key(path, "one").is_ok());
+    }
+
+    #[test]
+    fn rejects_key_parameters_out_of_bounds() {
+        let vault = vault_with_key("correct horse");
+        let path = key_file_path(vault.path().to_str().unwrap());
+        let original: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+
+        for (field, value) in [
+            ("memory_kib", 8u32),
+            ("memory_kib", u32::MAX),
+            ("iterations", 1),
+            ("iterations", 1_000_000),
+            ("parallelism", 0),
+            ("parallelism", 64),
+        ] {
+            let mut key_file = original.clone();
+            key_file[field] = value.into();
+            fs::write(&path, key_file.to_string()).unwrap();
+            let error = unlock_key(vault.path().to_str().unwrap(), "correct horse")
+                .err()
+                .unwrap();
+            assert!(error.contains(field), "{}", error);
+        }
+    }
+
+    #[test]
+    fn detects_tampering() {
+        let key = test_key();
+        let encrypted = encrypt_body(&key, "a secret worth keeping").unwrap();
+        let encoded: String = encrypted
+            .lines()
+            .filter(|line| !line.starts_with("-----"))
+            .collect();
+        let blob = BASE64.decode(encoded).unwrap();
+        let armor = |blob: &[u8]| {
+            format!(
+                "{}\n{}\n{}\n",
+                BEGIN_MARKER,
+                BASE64.encode(blob),
+                END_MARKER
+            )
+        };
+
+        // Every byte after the salt is covered by the tag
+        for index in [1 + SALT_LENGTH, blob.len() - TAG_LENGTH - 1, blob.len() - 1] {
+            let mut tampered = blob.clone();
+            tampered[index] ^= 1;
+            assert_eq!(
+                decrypt_body(&key, &armor(&tampered)).err().unwrap(),
+                "Encrypted note is damaged or was tampered with"
+            );
+        }
+        assert!(decrypt_body(&key, &armor(&blob[..blob.len() - 1])).is_err());
+
+        let other = VaultKey {
+            salt: key.salt,
+            key: Zeroizing::new([1; 32]),
+        };
+        assert!(decrypt_body(&other, &encrypted).is_err());
+        assert_eq!(
+            decrypt_body(&key, &encrypted).unwrap(),
+            "a secret worth keeping"
+        );
+    }
+
+    #[test]
+    fn encrypts_notes_in_place_keeping_their_frontmatter() {
+        let vault = tempfile::tempdir().unwrap();
+        let path = vault.path().to_str().unwrap();
+        let note = "---\ntitle: Diary\n---\nDear diary";
+        fs::write(vault.path().join("diary.md"), note).unwrap();
+        let key = test_key();
+
+        assert!(encrypt_note(&key, "diary.md", path).unwrap());
+        assert!(!encrypt_note(&key, "diary.md", path).unwrap());
+        let encrypted = fs::read_to_string(vault.path().join("diary.md")).unwrap();
+        assert!(encrypted.contains("title: Diary"));
+        assert!(encrypted.contains("encrypted: true"));
+        assert!(!encrypted.contains("Dear diary"));
+        assert!(is_encrypted_note("diary.md", path));
+
+        assert!(decrypt_note(&key, "diary.md", path).unwrap());
+        let decrypted = fs::read_to_string(vault.path().join("diary.md")).unwrap();
+        assert!(decrypted.contains("title: Diary"));
+        assert!(!decrypted.contains("encrypted"));
+        assert!(decrypted.ends_with("Dear diary"));
+    }
+
+    #[test]
+    fn lock_timer_wakes_for_a_nearer_deadline() {
+        let unlocked = Arc::new(UnlockedVaults::default());
+        let locked = Arc::new(Mutex::new(Vec::new()));
+        let timer = {
+            let (unlocked, locked) = (unlocked.clone(), locked.clone());
+            tauri::async_runtime::spawn(async move {
+                unlocked
+                    .run_lock_timer(|vault| locked.lock().unwrap().push(vault.to_string()))
+                    .await;
+            })
+        };
+
+        tauri::async_runtime::block_on(async {
+            unlocked.unlock("slow", test_key(), Duration::from_secs(60));
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            unlocked.unlock("fast", test_key(), Duration::from_millis(50));
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        });
+        timer.abort();
+
+        assert_eq!(*locked.lock().unwrap(), vec!["fast".to_string()]);
+        assert!(unlocked.key("fast").is_none());
+        assert!(unlocked.key("slow").is_some());
+        assert_eq!(unlocked.lock(None), vec!["slow".to_string()]);
+    }
+}
@@ -0,0 +1,403 @@
+// src/notes/flashcards.rs
+use super::ast;
+use super::encryption;
+use super::helpers;
+use super::outline;
+use crate::db::note_ids;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Placeholder shown in place of a cloze deletion
+pub const CLOZE_PLACEHOLDER: &str = "[...]";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CardKind {
+    /// `Question :: Answer` on one line
+    Basic,
+    /// Question lines, a line with only `?`, then answer lines
+    MultiLine,
+    /// A `==deletion==` hidden from its paragraph
+    Cloze,
+}
+
+impl CardKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            CardKind::Basic => "basic",
+            CardKind::MultiLine => "multi_line",
+            CardKind::Cloze => "cloze",
+        }
+    }
+}
+
+/// A card found in a note
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Flashcard {
+    /// Derived from the note's ID, the kind and either the card's `^block-id`
+    /// or its question (the hidden text for cloze cards), so a card keeps
+    /// its review state when its note moves or its answer is edited. Cards
+    /// that would share an ID in a note are told apart by their order.
+    pub id: String,
+    pub kind: CardKind,
+    pub relative_path: String,
+    pub question: String,
+    pub answer: String,
+    /// 1-based line the card starts on, in the content without frontmatter
+    pub line: usize,
+}
+
+/// A card with the key its ID is made from, which is filled in once all the
+/// cards of the note are known
+fn card(
+    kind: CardKind,
+    key: String,
+    relative_path: &str,
+    question: String,
+    answer: String,
+    line: usize,
+) -> (String, Flashcard) {
+    let card = Flashcard {
+        id: String::new(),
+        kind,
+        relative_path: relative_path.to_string(),
+        question,
+        answer,
+        line,
+    };
+    (format!("{}\n{}", kind.as_str(), key), card)
+}
+
+/// Drops a list marker from the start of a line
+fn strip_list_marker(line: &str) -> &str {
+    let trimmed = line.trim_start();
+    for marker in ["- ", "* ", "+ "] {
+        if let Some(rest) = trimmed.strip_prefix(marker) {
+            return rest;
+        }
+    }
+    match trimmed.split_once(". ") {
+        Some((number, rest)) if number.chars().all(|c| c.is_ascii_digit()) => rest,
+        _ => trimmed,
+    }
+}
+
+/// Finds the flashcards in a note's content (frontmatter stripped). Blocks
+/// are separated by blank lines; nothing inside code counts. `note_id` is
+/// the note's stable ID, which card IDs are made from.
+pub fn extract_flashcards(note_id: &str, relative_path: &str, content: &str) -> Vec<Flashcard> {
+    let note_ast = ast::parse(content);
+    let separator_regex = Regex::new(r"\s+::\s+").unwrap();
+    let cloze_regex = Regex::new(r"==([^=\n]+)==").unwrap();
+    let block_id_regex = Regex::new(r"(?:^|\s)\^([A-Za-z0-9-]+)\s*$").unwrap();
+    let block_id = |text: &str| {
+        block_id_regex
+            .captures(text)
+            .map(|captures| format!("^{}", &captures[1]))
+    };
+
+    // Split into blocks of (offset, line, text) lines, leaving out code
+    let mut blocks: Vec<Vec<(usize, usize, &str)>> = Vec::new();
+    let mut current = Vec::new();
+    let mut offset = 0;
+    for (index, line) in content.split_inclusive('\n').enumerate() {
+        let text = line.trim_end_matches(['\n', '\r']);
+        if text.trim().is_empty() {
+            if !current.is_empty() {
+                blocks.push(std::mem::take(&mut current));
+            }
+        } else if !note_ast.in_code(offset, offset + text.len()) {
+            current.push((offset, index + 1, text));
+        }
+        offset += line.len();
+    }
+    if !current.is_empty() {
+        blocks.push(current);
+    }
+
+    let mut cards = Vec::new();
+    for block in blocks {
+        // A `?` line splits a block into question and answer
+        if let Some(split) = block.iter().position(|(_, _, text)| text.trim() == "?") {
+            let join = |lines: &[(usize, usize, &str)]| {
+                let text = lines
+                    .iter()
+                    .map(|(_, _, text)| *text)
+                    .collect::<Vec<&str>>()
+                    .join("\n");
+                outline::strip_block_ids(&text).trim().to_string()
+            };
+            let question = join(&block[..split]);
+            let answer = join(&block[split + 1..]);
+            if !question.is_empty() && !answer.is_empty() {
+                let key = block
+                    .iter()
+                    .find_map(|(_, _, text)| block_id(text))
+                    .unwrap_or_else(|| question.clone());
+                cards.push(card(
+                    CardKind::MultiLine,
+                    key,
+                    relative_path,
+                    question,
+                    answer,
+                    block[0].1,
+                ));
+            }
+            continue;
+        }
+
+        for (offset, line, text) in &block {
+            let stripped = strip_list_marker(text);
+            let start = offset + text.len() - stripped.len();
+            let Some(separator) = separator_regex
+                .find_iter(stripped)
+                .find(|found| !note_ast.in_code(start + found.start(), start + found.end()))
+            else {
+                continue;
+            };
+            let question = stripped[..separator.start()].trim();
+            let answer = outline::strip_block_ids(&stripped[separator.end()..]);
+            let answer = answer.trim();
+            if !question.is_empty() && !answer.is_empty() {
+                let key = block_id(stripped).unwrap_or_else(|| question.to_string());
+                cards.push(card(
+                    CardKind::Basic,
+                    key,
+                    relative_path,
+                    question.to_string(),
+                    answer.to_string(),
+                    *line,
+                ));
+            }
+        }
+
+        // Each deletion in a paragraph is a card of its own
+        let mut paragraph = String::new();
+        let mut line_starts = Vec::new();
+        for (offset, line, text) in &block {
+            if !paragraph.is_empty() {
+                paragraph.push('\n');
+            }
+            line_starts.push((paragraph.len(), *offset, *line));
+            paragraph.push_str(text);
+        }
+        // Where a paragraph position is in the content, and on which line
+        let locate = |position: usize| {
+            line_starts
+                .iter()
+                .rev()
+                .find(|(start, _, _)| *start <= position)
+                .map(|(start, offset, line)| (offset + position - start, *line))
+                .unwrap_or((position, block[0].1))
+        };
+        let deletions: Vec<(usize, usize, String)> = cloze_regex
+            .captures_iter(&paragraph)
+            .filter_map(|captures| {
+                let whole = captures.get(0)?;
+                let (start, _) = locate(whole.start());
+                if note_ast.in_code(start, start + whole.len()) {
+                    return None;
+                }
+                Some((whole.start(), whole.end(), captures[1].to_string()))
+            })
+            .collect();
+
+        let paragraph_id = block.iter().find_map(|(_, _, text)| block_id(text));
+        for (index, (deletion_start, _, answer)) in deletions.iter().enumerate() {
+            let (_, line) = locate(*deletion_start);
+
+            let mut question = String::new();
+            let mut last = 0;
+            for (other, (from, to, text)) in deletions.iter().enumerate() {
+                question.push_str(&paragraph[last..*from]);
+                if other == index {
+                    question.push_str(CLOZE_PLACEHOLDER);
+                } else {
+                    question.push_str(text);
+                }
+                last = *to;
+            }
+            question.push_str(&paragraph[last..]);
+
+            // The hidden text, not the whole paragraph, so editing the rest
+            // of the paragraph keeps the card
+            let key = match &paragraph_id {
+                Some(paragraph_id) => format!("{}\n{}", paragraph_id, answer),
+                None => answer.clone(),
+            };
+            cards.push(card(
+                CardKind::Cloze,
+                key,
+                relative_path,
+                outline::strip_block_ids(&question).trim().to_string(),
+                answer.trim().to_string(),
+                line,
+            ));
+        }
+    }
+
+    // Cards with the same key in a note are told apart by their order
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    cards
+        .into_iter()
+        .map(|(key, mut card)| {
+            let count = seen.entry(key.clone()).or_default();
+            let key = match *count {
+                0 => key,
+                count => format!("{}\n{}", key, count),
+            };
+            *count += 1;
+            card.id = format!("{:x}", md5::compute(format!("{}\n{}", note_id, key)));
+            card
+        })
+        .collect()
+}
+
+/// Reads the flashcards of a note in the vault. The note is known by its
+/// frontmatter `id`, or by its path until it has one. Encrypted notes have
+/// none.
+pub fn get_note_flashcards(relative_path: &str, vault_directory: &str) -> Vec<Flashcard> {
+    let content = helpers::resolve_note_path(None, Some(relative_path), Some(vault_directory))
+        .and_then(|path| helpers::read_file_content(&path))
+        .unwrap_or_default();
+    let note = helpers::parse_note(relative_path, &content);
+    if encryption::is_encrypted(&note.body) {
+        return Vec::new();
+    }
+    let note_id = note_ids::frontmatter_id(note.frontmatter.as_ref())
+        .unwrap_or_else(|| relative_path.to_string());
+    extract_flashcards(&note_id, relative_path, &note.body)
+}
+
+/// Reads the flashcards of every note in the vault, or in one folder of it
+pub fn get_vault_flashcards(vault_directory: &str, folder: Option<&str>) -> Vec<Flashcard> {
+    let prefix = folder
+        .map(|folder| folder.trim_matches('/'))
+        .filter(|folder| !folder.is_empty())
+        .map(|folder| format!("{}/", folder));
+
+    helpers::get_all_notes(vault_directory)
+        .into_iter()
+        .map(|(_, rel_path)| rel_path.replace('\\', "/"))
+        .filter(|rel_path| {
+            prefix
+                .as_ref()
+                .is_none_or(|prefix| rel_path.starts_with(prefix))
+        })
+        .flat_map(|rel_path| get_note_flashcards(&rel_path, vault_directory))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn summary(cards: &[Flashcard]) -> Vec<(CardKind, &str, &str, usize)> {
+        cards
+            .iter()
+            .map(|card| {
+                (
+                    card.kind,
+                    card.question.as_str(),
+                    card.answer.as_str(),
+                    card.line,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn extracts_basic_and_multi_line_cards() {
+        let content = "- Capital of France :: Paris\n1. Two :: 2\nno::spaces\n\n\
+                       What are the\nprimary colours?\n?\nRed, yellow\nand blue\n";
+        let cards = extract_flashcards("a", "a.md", content);
+        assert_eq!(
+            summary(&cards),
+            vec![
+                (CardKind::Basic, "Capital of France", "Paris", 1),
+                (CardKind::Basic, "Two", "2", 2),
+                (
+                    CardKind::MultiLine,
+                    "What are the\nprimary colours?",
+                    "Red, yellow\nand blue",
+                    5
+                ),
+            ]
+        );
+        assert!(cards.iter().all(|card| card.relative_path == "a.md"));
+    }
+
+    #[test]
+    fn makes_a_card_per_cloze_deletion() {
+        let cards = extract_flashcards("a", "a.md", "Intro\n\nThe ==sun== is\na ==star==.");
+        assert_eq!(
+            summary(&cards),
+            vec![
+                (CardKind::Cloze, "The [...] is\na star.", "sun", 3),
+                (CardKind::Cloze, "The sun is\na [...].", "star", 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_cards_in_code() {
+        let content = "```\nQ :: A\n==hidden==\n```\n\nuse `a :: b` here\n\n`==x==` and ==y==";
+        let cards = extract_flashcards("a", "a.md", content);
+        assert_eq!(
+            summary(&cards),
+            vec![(CardKind::Cloze, "`==x==` and [...]", "y", 8)]
+        );
+    }
+
+    #[test]
+    fn ids_follow_the_note_and_question() {
+        let first = &extract_flashcards("a", "a.md", "Q :: A")[0];
+        let edited = &extract_flashcards("a", "moved/b.md", "Q :: Another answer")[0];
+        let other_note = &extract_flashcards("b", "b.md", "Q :: A")[0];
+        let multi_line = &extract_flashcards("a", "a.md", "Q\n?\nA")[0];
+        assert_eq!(first.id, edited.id);
+        assert_ne!(first.id, other_note.id);
+        assert_ne!(first.id, multi_line.id);
+
+        let cloze = &extract_flashcards("a", "a.md", "The ==sun== is hot")[0];
+        let reworded = &extract_flashcards("a", "a.md", "The ==sun== is very hot")[0];
+        assert_eq!(cloze.id, reworded.id);
+
+        let marked = &extract_flashcards("a", "a.md", "Q :: A ^card")[0];
+        let renamed = &extract_flashcards("a", "a.md", "New question :: A ^card")[0];
+        assert_eq!(marked.answer, "A");
+        assert_eq!(marked.id, renamed.id);
+
+        let repeated = extract_flashcards("a", "a.md", "Q :: A\nQ :: B");
+        assert_eq!(repeated.len(), 2);
+        assert_ne!(repeated[0].id, repeated[1].id);
+    }
+
+    #[test]
+    fn collects_every_vault_card() {
+        let vault = tempfile::tempdir().unwrap();
+        fs::create_dir_all(vault.path().join("deck")).unwrap();
+        fs::write(vault.path().join("deck/a.md"), "Q :: A\n\nOnly here :: yes").unwrap();
+        fs::write(vault.path().join("b.md"), "---\nid: b1\n---\nQ :: A").unwrap();
+        fs::write(
+            vault.path().join("secret.md"),
+            "---\nencrypted: true\n---\n-----BEGIN ENCRYPTED NOTE-----\nS :: T\n-----END ENCRYPTED NOTE-----\n",
+        )
+        .unwrap();
+        let vault_directory = vault.path().to_str().unwrap();
+
+        let cards = get_vault_flashcards(vault_directory, None);
+        assert_eq!(cards.len(), 3);
+        assert_eq!(
+            get_note_flashcards("b.md", vault_directory)[0].id,
+            extract_flashcards("b1", "renamed.md", "Q :: A")[0].id
+        );
+        assert!(get_note_flashcards("secret.md", vault_directory).is_empty());
+
+        let deck = get_vault_flashcards(vault_directory, Some("/deck/"));
+        assert_eq!(deck.len(), 2);
+        assert!(deck.iter().all(|card| card.relative_path == "deck/a.md"));
+    }
+}
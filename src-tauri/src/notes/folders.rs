@@ -0,0 +1,593 @@
+// src/notes/folders.rs
+use super::formats;
+use super::health;
+use super::helpers;
+use super::links::{self, LinkResolver, WikiLink};
+use super::outline::{self, Subpath};
+use super::scan::VaultFilter;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Persisted per-folder view state
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct FolderState {
+    pub collapsed: bool,
+    /// Manual position among its siblings; unset folders sort by name after
+    /// positioned ones
+    pub position: Option<i64>,
+}
+
+/// A folder in the vault tree
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FolderNode {
+    pub name: String,
+    /// Path from the vault root with forward slashes, empty for the root
+    pub relative_path: String,
+    /// Notes directly in this folder
+    pub note_count: usize,
+    /// Notes in this folder and all its subfolders
+    pub total_notes: usize,
+    pub collapsed: bool,
+    pub position: Option<i64>,
+    pub folders: Vec<FolderNode>,
+    /// Relative paths of the notes directly in this folder, sorted by name
+    pub notes: Vec<String>,
+}
+
+/// What moving or renaming a folder changed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FolderChange {
+    pub old_path: String,
+    pub new_path: String,
+    pub moved_notes: usize,
+    pub updated_links: usize,
+    /// Notes whose links were rewritten, by their path after the move
+    pub updated_notes: Vec<String>,
+}
+
+fn invalid_input(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+/// Normalizes a folder path from the webview and makes sure it stays inside
+/// the vault
+pub fn clean_folder_path(path: &str) -> Result<String, io::Error> {
+    let path = path.replace('\\', "/");
+    if Path::new(&path).is_absolute() {
+        return Err(invalid_input(format!(
+            "Folder path must be relative to the vault: {}",
+            path
+        )));
+    }
+
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    if segments.is_empty() {
+        return Err(invalid_input("Folder path is empty".to_string()));
+    }
+    if segments.iter().any(|s| *s == "." || *s == "..") {
+        return Err(invalid_input(format!(
+            "Folder path must stay inside the vault: {}",
+            path
+        )));
+    }
+
+    Ok(segments.join("/"))
+}
+
+/// Returns the path with `from` replaced by `to` if it lies inside `from`
+pub fn moved_path(path: &str, from: &str, to: &str) -> Option<String> {
+    if path == from {
+        return Some(to.to_string());
+    }
+    path.strip_prefix(from)
+        .filter(|rest| rest.starts_with('/'))
+        .map(|rest| format!("{}{}", to, rest))
+}
+
+/// Org notes link to files by path rather than by wiki link
+fn is_org_path(path: &str) -> bool {
+    path.rsplit_once('.')
+        .is_some_and(|(_, extension)| extension.eq_ignore_ascii_case("org"))
+}
+
+/// Path of a vault file relative to the folder of another one
+fn relative_path(from_file: &str, to_file: &str) -> String {
+    let from_dirs: Vec<&str> = from_file.split('/').collect();
+    let from_dirs = &from_dirs[..from_dirs.len() - 1];
+    let to_parts: Vec<&str> = to_file.split('/').collect();
+
+    let common = from_dirs
+        .iter()
+        .zip(to_parts.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut parts = vec![".."; from_dirs.len() - common];
+    parts.extend(&to_parts[common..]);
+    parts.join("/")
+}
+
+/// Writes a link to its new target the way the note's format writes links:
+/// wiki links name the target from the vault root, org `file:` links give
+/// its path relative to the note
+fn rewritten_link(note_path: &str, link: &WikiLink, wiki_target: &str, file_path: &str) -> String {
+    if !is_org_path(note_path) {
+        return health::replacement_link(link, wiki_target);
+    }
+
+    let (_, subpath) = outline::split_link_target(&link.target);
+    let search = match subpath {
+        Subpath::Heading(heading) => format!("::*{}", heading),
+        _ => String::new(),
+    };
+    let description = link
+        .alias
+        .as_ref()
+        .map(|alias| format!("[{}]", alias))
+        .unwrap_or_default();
+    format!(
+        "[[file:{}{}]{}]",
+        relative_path(note_path, file_path),
+        search,
+        description
+    )
+}
+
+/// Walks one folder. `visited` holds the canonical folders on the current
+/// path, so a symlink back up the tree isn't followed.
+fn build_node(
+    vault_path: &Path,
+    dir: &Path,
+    relative_path: String,
+    states: &HashMap<String, FolderState>,
+    filter: &VaultFilter,
+    visited: &mut HashSet<PathBuf>,
+) -> io::Result<FolderNode> {
+    let mut folders = Vec::new();
+    let mut notes = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        let is_dir = path.is_dir();
+        if filter.is_ignored(&path, is_dir) {
+            continue;
+        }
+
+        if is_dir {
+            let Ok(canonical) = fs::canonicalize(&path) else {
+                continue;
+            };
+            if !visited.insert(canonical.clone()) {
+                continue;
+            }
+            let child_path = if relative_path.is_empty() {
+                name
+            } else {
+                format!("{}/{}", relative_path, name)
+            };
+            let child = build_node(vault_path, &path, child_path, states, filter, visited);
+            visited.remove(&canonical);
+            folders.push(child?);
+        } else if filter.is_note(&path) {
+            if let Ok(rel_path) = path.strip_prefix(vault_path) {
+                notes.push(rel_path.to_string_lossy().replace('\\', "/"));
+            }
+        }
+    }
+
+    folders.sort_by(|a, b| match (a.position, b.position) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+    });
+    notes.sort_by_key(|note| note.to_lowercase());
+
+    let state = states.get(&relative_path).cloned().unwrap_or_default();
+    let name = if relative_path.is_empty() {
+        vault_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    } else {
+        relative_path
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .to_string()
+    };
+
+    Ok(FolderNode {
+        name,
+        relative_path,
+        note_count: notes.len(),
+        total_notes: notes.len() + folders.iter().map(|f| f.total_notes).sum::<usize>(),
+        collapsed: state.collapsed,
+        position: state.position,
+        folders,
+        notes,
+    })
+}
+
+/// Builds the folder hierarchy of a vault, applying the saved view state
+pub fn folder_tree(
+    vault_directory: &str,
+    states: &HashMap<String, FolderState>,
+) -> Result<FolderNode, io::Error> {
+    let vault_path = Path::new(vault_directory);
+    if !vault_path.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Vault directory does not exist: {}", vault_directory),
+        ));
+    }
+    let filter = VaultFilter::load(vault_directory);
+    let mut visited = HashSet::new();
+    if let Ok(canonical) = fs::canonicalize(vault_path) {
+        visited.insert(canonical);
+    }
+    build_node(
+        vault_path,
+        vault_path,
+        String::new(),
+        states,
+        &filter,
+        &mut visited,
+    )
+}
+
+/// Creates an empty folder. Fails if it already exists.
+pub fn create_folder(vault_directory: &str, relative_path: &str) -> Result<String, io::Error> {
+    let relative_path = clean_folder_path(relative_path)?;
+    let path = Path::new(vault_directory).join(&relative_path);
+    if path.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", relative_path),
+        ));
+    }
+    fs::create_dir_all(&path)?;
+    Ok(relative_path)
+}
+
+/// Deletes a folder. A folder with anything in it is only deleted when
+/// `recursive` is set. Returns the number of notes deleted with it.
+pub fn delete_folder(
+    vault_directory: &str,
+    relative_path: &str,
+    recursive: bool,
+) -> Result<usize, io::Error> {
+    let relative_path = clean_folder_path(relative_path)?;
+    let path = Path::new(vault_directory).join(&relative_path);
+    if !path.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Folder not found: {}", relative_path),
+        ));
+    }
+
+    if !recursive {
+        fs::remove_dir(&path).map_err(|_| {
+            invalid_input(format!(
+                "{} is not empty; delete it recursively to remove its contents",
+                relative_path
+            ))
+        })?;
+        return Ok(0);
+    }
+
+    let prefix = format!("{}/", relative_path);
+    let deleted = helpers::get_all_notes(vault_directory)
+        .into_iter()
+        .filter(|(_, rel_path)| rel_path.replace('\\', "/").starts_with(&prefix))
+        .count();
+    fs::remove_dir_all(&path)?;
+    Ok(deleted)
+}
+
+/// Renames a folder in place
+pub fn rename_folder(
+    vault_directory: &str,
+    relative_path: &str,
+    new_name: &str,
+) -> Result<FolderChange, io::Error> {
+    let relative_path = clean_folder_path(relative_path)?;
+    let new_name = new_name.trim();
+    if new_name.is_empty() || new_name.contains(['/', '\\']) || new_name == "." || new_name == ".."
+    {
+        return Err(invalid_input(format!("Invalid folder name: {}", new_name)));
+    }
+
+    let new_path = match relative_path.rsplit_once('/') {
+        Some((parent, _)) => format!("{}/{}", parent, new_name),
+        None => new_name.to_string(),
+    };
+    relocate_folder(vault_directory, &relative_path, &new_path)
+}
+
+/// Moves a folder into another folder; an empty parent is the vault root
+pub fn move_folder(
+    vault_directory: &str,
+    relative_path: &str,
+    new_parent: &str,
+) -> Result<FolderChange, io::Error> {
+    let relative_path = clean_folder_path(relative_path)?;
+    let name = relative_path.rsplit('/').next().unwrap_or_default();
+    let new_path = if new_parent.trim_matches(['/', '\\']).is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", clean_folder_path(new_parent)?, name)
+    };
+    relocate_folder(vault_directory, &relative_path, &new_path)
+}
+
+/// A link that has to be rewritten after a move
+struct LinkEdit {
+    start: usize,
+    end: usize,
+    replacement: String,
+}
+
+/// Moves a folder and rewrites every wiki link whose meaning the move would
+/// change: links into the folder, and relative or shortest-path links from
+/// notes inside it.
+fn relocate_folder(vault_directory: &str, from: &str, to: &str) -> Result<FolderChange, io::Error> {
+    let vault_path = Path::new(vault_directory);
+    let source = vault_path.join(from);
+    let destination = vault_path.join(to);
+
+    if !source.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Folder not found: {}", from),
+        ));
+    }
+    if from == to {
+        return Err(invalid_input(format!("{} is already there", from)));
+    }
+    if moved_path(to, from, "").is_some() {
+        return Err(invalid_input(format!("Can't move {} into itself", from)));
+    }
+    if destination.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", to),
+        ));
+    }
+
+    // Work out every edit before touching anything: links resolve against
+    // the vault as it is now, and against the same notes at their new paths
+    let before = LinkResolver::new(vault_directory);
+    let after = before.with_moved_folder(from, to);
+    let notes: Vec<String> = helpers::get_all_notes(vault_directory)
+        .into_iter()
+        .map(|(_, rel_path)| rel_path.replace('\\', "/"))
+        .collect();
+    let moved_notes = notes
+        .iter()
+        .filter(|note| moved_path(note, from, to).is_some())
+        .count();
+
+    let mut rewrites = Vec::new();
+    let mut updated_links = 0;
+    for note in &notes {
+        let content = match fs::read_to_string(vault_path.join(note)) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+        let note_now = moved_path(note, from, to).unwrap_or_else(|| note.clone());
+        let format = formats::format_for_path(note);
+        let (_, body) = format.split(&content);
+        // Link offsets are relative to the content after the metadata block
+        let body_offset = content.len() - body.len();
+        let mut edits = Vec::new();
+
+        for link in format.links(&body) {
+            if link.target.starts_with('#') {
+                continue;
+            }
+            let (note_target, _) = outline::split_link_target(&link.target);
+            let expected = match before.resolve_path(&link.target, Some(note)) {
+                Some(target) => moved_path(&target, from, to).unwrap_or(target),
+                None => {
+                    // Not a note; an attachment inside the moved folder keeps
+                    // its path relative to the folder
+                    let target = note_target.replace('\\', "/");
+                    let relative = target.starts_with("./") || target.starts_with("../");
+                    let path = if relative {
+                        links::join_relative(note, &target)
+                    } else {
+                        target.clone()
+                    };
+                    match moved_path(&path, from, to) {
+                        Some(moved)
+                            if vault_path.join(&path).exists()
+                                && !(relative
+                                    && links::join_relative(&note_now, &target) == moved) =>
+                        {
+                            edits.push(LinkEdit {
+                                start: body_offset + link.start,
+                                end: body_offset + link.end,
+                                replacement: rewritten_link(&note_now, &link, &moved, &moved),
+                            });
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+            };
+
+            // Org links are file paths, which only keep working when they
+            // still lead to the note itself
+            let still_resolves = if is_org_path(note) {
+                links::join_relative(&note_now, &note_target) == expected
+            } else {
+                after.resolve_path(&link.target, Some(&note_now)).as_deref() == Some(&expected)
+            };
+            if still_resolves {
+                continue;
+            }
+            edits.push(LinkEdit {
+                start: body_offset + link.start,
+                end: body_offset + link.end,
+                replacement: rewritten_link(
+                    &note_now,
+                    &link,
+                    formats::strip_note_extension(&expected),
+                    &expected,
+                ),
+            });
+        }
+
+        if edits.is_empty() {
+            continue;
+        }
+
+        let mut updated = content.clone();
+        edits.sort_by_key(|edit| edit.start);
+        for edit in edits.iter().rev() {
+            updated.replace_range(edit.start..edit.end, &edit.replacement);
+        }
+        updated_links += edits.len();
+        rewrites.push((note_now, content, updated));
+    }
+
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(&source, &destination)?;
+
+    // A note that can't be written undoes the whole move, so links never
+    // end up half rewritten
+    for (index, (note, _, updated)) in rewrites.iter().enumerate() {
+        if let Err(e) = fs::write(vault_path.join(note), updated) {
+            let restored = rewrites[..index]
+                .iter()
+                .try_for_each(|(note, original, _)| fs::write(vault_path.join(note), original))
+                .and_then(|_| fs::rename(&destination, &source));
+            return Err(match restored {
+                Ok(()) => io::Error::new(
+                    e.kind(),
+                    format!("Failed to update links in {}: {}", note, e),
+                ),
+                Err(undo) => io::Error::new(
+                    e.kind(),
+                    format!(
+                        "Failed to update links in {}: {}; undoing the move failed too: {}",
+                        note, e, undo
+                    ),
+                ),
+            });
+        }
+    }
+    let updated_notes = rewrites.into_iter().map(|(note, _, _)| note).collect();
+
+    Ok(FolderChange {
+        old_path: from.to_string(),
+        new_path: to.to_string(),
+        moved_notes,
+        updated_links,
+        updated_notes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(vault: &Path, relative_path: &str, content: &str) {
+        let path = vault.join(relative_path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn read(vault: &Path, relative_path: &str) -> String {
+        fs::read_to_string(vault.join(relative_path)).unwrap()
+    }
+
+    #[test]
+    fn cleans_folder_paths() {
+        assert_eq!(clean_folder_path("a\\b/").unwrap(), "a/b");
+        assert!(clean_folder_path("/a").is_err());
+        assert!(clean_folder_path("a/../../b").is_err());
+        assert_eq!(
+            moved_path("a/b/c.md", "a/b", "x"),
+            Some("x/c.md".to_string())
+        );
+        assert_eq!(moved_path("a/bc.md", "a/b", "x"), None);
+    }
+
+    #[test]
+    fn renaming_a_folder_rewrites_links_into_it() {
+        let vault = tempfile::tempdir().unwrap();
+        write(
+            vault.path(),
+            "index.md",
+            "---\ntitle: Index\n---\n[[drafts/plan#Goals|the plan]] ![[drafts/img.png]]\n`[[drafts/plan]]`",
+        );
+        write(vault.path(), "drafts/plan.md", "# Goals\n[[./notes]]");
+        write(vault.path(), "drafts/notes.md", "");
+        write(vault.path(), "drafts/img.png", "png");
+        let vault_directory = vault.path().to_string_lossy();
+
+        let change = rename_folder(&vault_directory, "drafts", "final").unwrap();
+        assert_eq!(change.new_path, "final");
+        assert_eq!(change.moved_notes, 2);
+        assert_eq!(change.updated_links, 2);
+        assert_eq!(change.updated_notes, vec!["index.md"]);
+        assert_eq!(
+            read(vault.path(), "index.md"),
+            "---\ntitle: Index\n---\n[[final/plan#Goals|the plan]] ![[final/img.png]]\n`[[drafts/plan]]`"
+        );
+        assert_eq!(read(vault.path(), "final/plan.md"), "# Goals\n[[./notes]]");
+    }
+
+    #[test]
+    fn moving_a_folder_rewrites_relative_links_out_of_it() {
+        let vault = tempfile::tempdir().unwrap();
+        write(vault.path(), "index.md", "[[work/plan]]");
+        write(vault.path(), "work/plan.md", "[[../index]] [[./tasks]]");
+        write(vault.path(), "work/tasks.md", "");
+        write(
+            vault.path(),
+            "log.org",
+            "#+TITLE: Log\n\n[[file:work/plan.md::*Goals][The plan]] [[work/tasks]]",
+        );
+        write(
+            vault.path(),
+            "work/journal.org",
+            "[[file:../log.org]] [[file:tasks.md]]",
+        );
+        let vault_directory = vault.path().to_string_lossy();
+
+        let change = move_folder(&vault_directory, "work", "home").unwrap();
+        assert_eq!(change.new_path, "home/work");
+        let mut updated_notes = change.updated_notes.clone();
+        updated_notes.sort();
+        assert_eq!(
+            updated_notes,
+            vec!["home/work/journal.org", "home/work/plan.md", "log.org"]
+        );
+        assert_eq!(
+            read(vault.path(), "home/work/plan.md"),
+            "[[index]] [[./tasks]]"
+        );
+        assert_eq!(
+            read(vault.path(), "log.org"),
+            "#+TITLE: Log\n\n[[file:home/work/plan.md::*Goals][The plan]] [[work/tasks]]"
+        );
+        assert_eq!(
+            read(vault.path(), "home/work/journal.org"),
+            "[[file:../../log.org]] [[file:tasks.md]]"
+        );
+        assert_eq!(read(vault.path(), "index.md"), "[[work/plan]]");
+
+        assert!(move_folder(&vault_directory, "home", "home/work").is_err());
+        assert!(move_folder(&vault_directory, "missing", "").is_err());
+    }
+}
@@ -0,0 +1,480 @@
+// src/notes/formats.rs
+use super::ast;
+use super::helpers;
+use super::links::WikiLink;
+use super::scan;
+use regex::Regex;
+use serde_json::{json, Map, Value as JsonValue};
+use serde_yaml::Value as YamlValue;
+use std::path::Path;
+
+/// Extensions scanned as notes unless the settings say otherwise
+pub const DEFAULT_NOTE_EXTENSIONS: &[&str] = &["md", "markdown", "mdx", "txt", "org"];
+
+/// What the cache keeps from a note's body
+#[derive(Debug, Clone, Default)]
+pub struct ParsedBody {
+    pub links: Vec<WikiLink>,
+    /// Inline tags, in the order they appear
+    pub tags: Vec<String>,
+    pub headings: usize,
+}
+
+/// How notes of one file type are read. A note is split into its metadata
+/// block (YAML frontmatter, org `#+KEYWORD:` lines...) and its body; link
+/// offsets are byte offsets into the body.
+pub trait NoteFormat: Sync {
+    /// Splits a note into its raw metadata block, if it has one, and its body
+    fn split(&self, content: &str) -> (Option<String>, String);
+
+    /// Parses a raw metadata block into a JSON object. Keys that mean the
+    /// same thing across formats use the frontmatter names (`title`,
+    /// `aliases`, `tags`).
+    fn metadata(&self, raw: &str) -> JsonValue;
+
+    /// Puts a metadata block back in front of a body
+    fn join(&self, raw: &str, body: &str) -> String;
+
+    /// Finds the links to other notes and the inline tags in a body
+    fn parse(&self, body: &str) -> ParsedBody;
+
+    /// Finds the links to other notes in a body
+    fn links(&self, body: &str) -> Vec<WikiLink> {
+        self.parse(body).links
+    }
+}
+
+/// Markdown and its variants: YAML frontmatter and `[[wiki links]]`
+pub struct Markdown;
+
+/// Plain text: no metadata, `[[wiki links]]` anywhere in the text
+pub struct PlainText;
+
+/// Org mode: `#+KEYWORD:` lines and a property drawer at the top,
+/// `[[file:note.org][description]]` links and `:tags:` on headlines
+pub struct OrgMode;
+
+fn lowercase_extension(path: &Path) -> String {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+/// Picks the parser for a note from its extension. Extensions configured as
+/// notes without a parser of their own are read as markdown.
+pub fn format_for_path(path: impl AsRef<Path>) -> &'static dyn NoteFormat {
+    match lowercase_extension(path.as_ref()).as_str() {
+        "org" => &OrgMode,
+        "txt" => &PlainText,
+        _ => &Markdown,
+    }
+}
+
+/// Returns true if a note is read as markdown and so can have frontmatter
+pub fn is_markdown_path(path: impl AsRef<Path>) -> bool {
+    !matches!(lowercase_extension(path.as_ref()).as_str(), "org" | "txt")
+}
+
+/// Returns true if a path has one of the configured note extensions
+pub fn is_note_path(path: &str) -> bool {
+    match path.rsplit_once('.') {
+        Some((_, ext)) => !ext.contains('/') && scan::is_note_extension(ext),
+        None => false,
+    }
+}
+
+/// Removes a note extension from a path, leaving other extensions alone
+pub fn strip_note_extension(path: &str) -> &str {
+    match path.rsplit_once('.') {
+        Some((stem, ext)) if !ext.contains('/') && scan::is_note_extension(ext) => stem,
+        _ => path,
+    }
+}
+
+/// Helper function to convert YAML Value to JSON Value
+fn yaml_to_json(yaml: YamlValue) -> JsonValue {
+    match yaml {
+        YamlValue::Null => JsonValue::Null,
+        YamlValue::Bool(b) => JsonValue::Bool(b),
+        YamlValue::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                JsonValue::Number(i.into())
+            } else if let Some(f) = n.as_f64() {
+                if let Some(n) = serde_json::Number::from_f64(f) {
+                    JsonValue::Number(n)
+                } else {
+                    JsonValue::Null
+                }
+            } else {
+                JsonValue::Null
+            }
+        }
+        YamlValue::String(s) => JsonValue::String(s),
+        YamlValue::Sequence(seq) => JsonValue::Array(seq.into_iter().map(yaml_to_json).collect()),
+        YamlValue::Mapping(map) => {
+            let mut obj = serde_json::Map::new();
+            for (k, v) in map {
+                if let Some(key) = k.as_str() {
+                    obj.insert(key.to_string(), yaml_to_json(v));
+                }
+            }
+            JsonValue::Object(obj)
+        }
+        YamlValue::Tagged(tagged) => {
+            // For tagged values, just convert the value part and ignore the tag
+            yaml_to_json(tagged.value)
+        }
+    }
+}
+
+/// 1-based line of a byte offset
+fn line_at(text: &str, offset: usize) -> usize {
+    text[..offset].matches('\n').count() + 1
+}
+
+impl NoteFormat for Markdown {
+    fn split(&self, content: &str) -> (Option<String>, String) {
+        helpers::extract_frontmatter_and_content(content)
+    }
+
+    fn metadata(&self, raw: &str) -> JsonValue {
+        // Frontmatter that isn't valid YAML still counts as present
+        match serde_yaml::from_str::<YamlValue>(raw) {
+            Ok(yaml_value) => yaml_to_json(yaml_value),
+            Err(_) => json!({}),
+        }
+    }
+
+    fn join(&self, raw: &str, body: &str) -> String {
+        format!("---\n{}\n---\n\n{}", raw, body)
+    }
+
+    fn parse(&self, body: &str) -> ParsedBody {
+        let note_ast = ast::parse(body);
+        ParsedBody {
+            links: note_ast.wiki_links,
+            tags: note_ast.tags.into_iter().map(|tag| tag.name).collect(),
+            headings: note_ast.headings.len(),
+        }
+    }
+}
+
+impl NoteFormat for PlainText {
+    fn split(&self, content: &str) -> (Option<String>, String) {
+        (None, content.to_string())
+    }
+
+    fn metadata(&self, _raw: &str) -> JsonValue {
+        json!({})
+    }
+
+    fn join(&self, _raw: &str, body: &str) -> String {
+        body.to_string()
+    }
+
+    fn parse(&self, body: &str) -> ParsedBody {
+        let wiki_link = Regex::new(r"(!?)\[\[([^\[\]|]+)(?:\|([^\[\]]*))?\]\]").unwrap();
+        let links = wiki_link
+            .captures_iter(body)
+            .filter_map(|captures| {
+                let whole = captures.get(0)?;
+                Some(WikiLink {
+                    raw: whole.as_str().to_string(),
+                    target: captures[2].trim().to_string(),
+                    alias: captures
+                        .get(3)
+                        .map(|alias| alias.as_str().trim().to_string()),
+                    embed: !captures[1].is_empty(),
+                    line: line_at(body, whole.start()),
+                    start: whole.start(),
+                    end: whole.end(),
+                })
+            })
+            .collect();
+
+        ParsedBody {
+            links,
+            tags: Vec::new(),
+            headings: 0,
+        }
+    }
+}
+
+/// Matches an org `#+KEYWORD: value` line
+fn org_keyword() -> Regex {
+    Regex::new(r"^#\+([A-Za-z_]+):\s*(.*)$").unwrap()
+}
+
+/// Splits org `:tag1:tag2:` (or space separated) tags
+fn org_tags(value: &str) -> Vec<JsonValue> {
+    value
+        .split([':', ' '])
+        .filter(|tag| !tag.is_empty())
+        .map(|tag| JsonValue::String(tag.to_string()))
+        .collect()
+}
+
+/// Splits org-roam style `"Quoted alias" word` lists
+fn org_list(value: &str) -> Vec<JsonValue> {
+    let item_regex = Regex::new(r#""([^"]*)"|(\S+)"#).unwrap();
+    item_regex
+        .captures_iter(value)
+        .filter_map(|captures| captures.get(1).or_else(|| captures.get(2)))
+        .map(|item| JsonValue::String(item.as_str().to_string()))
+        .collect()
+}
+
+/// Turns an org link into a wiki link target. Only `file:` links point to
+/// notes; their paths are relative to the linking note, as in org mode.
+fn org_link_target(link: &str) -> Option<String> {
+    let path = link.strip_prefix("file:")?;
+    let (path, search) = match path.split_once("::") {
+        Some((path, search)) => (path, Some(search)),
+        None => (path, None),
+    };
+    if path.is_empty() {
+        return None;
+    }
+
+    let mut target = if path.starts_with('/') || path.starts_with("./") || path.starts_with("../") {
+        path.to_string()
+    } else {
+        format!("./{}", path)
+    };
+    if let Some(heading) = search.and_then(|search| search.strip_prefix('*')) {
+        target.push('#');
+        target.push_str(heading.trim());
+    }
+    Some(target)
+}
+
+impl NoteFormat for OrgMode {
+    fn split(&self, content: &str) -> (Option<String>, String) {
+        // The header is the run of keyword lines, blank lines and property
+        // drawers a file starts with
+        let keyword = org_keyword();
+        let mut header_end = 0;
+        let mut in_drawer = false;
+        for line in content.split_inclusive('\n') {
+            let trimmed = line.trim();
+            let in_header = if in_drawer {
+                in_drawer = !trimmed.eq_ignore_ascii_case(":END:");
+                true
+            } else if trimmed.eq_ignore_ascii_case(":PROPERTIES:") {
+                in_drawer = true;
+                true
+            } else {
+                trimmed.is_empty() || keyword.is_match(trimmed)
+            };
+            if !in_header {
+                break;
+            }
+            header_end += line.len();
+        }
+
+        let header = content[..header_end].trim();
+        if header.is_empty() {
+            (None, content.to_string())
+        } else {
+            (Some(header.to_string()), content[header_end..].to_string())
+        }
+    }
+
+    fn metadata(&self, raw: &str) -> JsonValue {
+        let keyword = org_keyword();
+        let property = Regex::new(r"^:([A-Za-z_]+):\s*(.*)$").unwrap();
+        let mut metadata = Map::new();
+        for line in raw.lines().map(str::trim) {
+            let Some(captures) = keyword.captures(line).or_else(|| property.captures(line)) else {
+                continue;
+            };
+            let key = captures[1].to_lowercase();
+            let value = captures[2].trim();
+
+            match key.as_str() {
+                "properties" | "end" => {}
+                "filetags" | "tags" => {
+                    metadata.insert("tags".to_string(), JsonValue::Array(org_tags(value)));
+                }
+                "roam_aliases" | "aliases" => {
+                    metadata.insert("aliases".to_string(), JsonValue::Array(org_list(value)));
+                }
+                _ => {
+                    metadata.insert(key, JsonValue::String(value.to_string()));
+                }
+            }
+        }
+        JsonValue::Object(metadata)
+    }
+
+    fn join(&self, raw: &str, body: &str) -> String {
+        format!("{}\n\n{}", raw, body)
+    }
+
+    fn parse(&self, body: &str) -> ParsedBody {
+        // Nothing inside #+BEGIN_... / #+END_... blocks is a link, tag or
+        // headline
+        let mut blocks = Vec::new();
+        let mut offset = 0;
+        let mut block_start = None;
+        let mut headings = 0;
+        for line in body.split_inclusive('\n') {
+            let trimmed = line.trim().to_lowercase();
+            if block_start.is_none() && trimmed.starts_with("#+begin_") {
+                block_start = Some(offset);
+            } else if trimmed.starts_with("#+end_") {
+                if let Some(start) = block_start.take() {
+                    blocks.push((start, offset + line.len()));
+                }
+            } else if block_start.is_none()
+                && line.starts_with('*')
+                && line.trim_start_matches('*').starts_with([' ', '\t'])
+            {
+                headings += 1;
+            }
+            offset += line.len();
+        }
+        if let Some(start) = block_start {
+            blocks.push((start, body.len()));
+        }
+        let in_block = |at: usize| blocks.iter().any(|(start, end)| at >= *start && at < *end);
+
+        let link_regex = Regex::new(r"\[\[([^\[\]]+)\](?:\[([^\[\]]*)\])?\]").unwrap();
+        let links = link_regex
+            .captures_iter(body)
+            .filter_map(|captures| {
+                let whole = captures.get(0)?;
+                if in_block(whole.start()) {
+                    return None;
+                }
+                Some(WikiLink {
+                    raw: whole.as_str().to_string(),
+                    target: org_link_target(captures[1].trim())?,
+                    alias: captures
+                        .get(2)
+                        .map(|alias| alias.as_str().trim().to_string()),
+                    embed: false,
+                    line: line_at(body, whole.start()),
+                    start: whole.start(),
+                    end: whole.end(),
+                })
+            })
+            .collect();
+
+        let headline_tags = Regex::new(r"(?m)^\*+\s.*?\s(:[\w@#%:]+:)\s*$").unwrap();
+        let tags = headline_tags
+            .captures_iter(body)
+            .filter_map(|captures| captures.get(1))
+            .filter(|tags| !in_block(tags.start()))
+            .flat_map(|tags| org_tags(tags.as_str()))
+            .filter_map(|tag| tag.as_str().map(|tag| tag.to_string()))
+            .collect();
+
+        ParsedBody {
+            links,
+            tags,
+            headings,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_formats_by_extension() {
+        assert!(is_markdown_path("a/b.MD"));
+        assert!(is_markdown_path("a/b.mdx"));
+        assert!(!is_markdown_path("a/b.Org"));
+        assert!(!is_markdown_path("a/b.txt"));
+
+        assert!(is_note_path("notes/a.md"));
+        assert!(!is_note_path("notes/a.pdf"));
+        assert!(!is_note_path("notes.d/readme"));
+        assert_eq!(strip_note_extension("notes/a.md"), "notes/a");
+        assert_eq!(strip_note_extension("notes/a.png"), "notes/a.png");
+        assert_eq!(strip_note_extension("v1.0/readme"), "v1.0/readme");
+    }
+
+    #[test]
+    fn reads_markdown_frontmatter() {
+        let format = format_for_path("a.md");
+        let (raw, body) = format.split("---\ntitle: A\ncount: 2\nratio: 0.5\n---\nbody [[b]]");
+        let raw = raw.unwrap();
+        assert_eq!(body.trim(), "body [[b]]");
+        assert_eq!(
+            format.metadata(&raw),
+            json!({"title": "A", "count": 2, "ratio": 0.5})
+        );
+        assert_eq!(format.metadata("title: [unclosed"), json!({}));
+        assert_eq!(format.links(&body)[0].target, "b");
+        assert!(format.join(&raw, "body").starts_with("---\ntitle: A"));
+    }
+
+    #[test]
+    fn finds_links_in_plain_text() {
+        let format = format_for_path("a.txt");
+        assert_eq!(format.split("---\ntitle: A\n---").0, None);
+        assert_eq!(format.metadata("title: A"), json!({}));
+
+        let body = "see [[b|the b]]\nand ![[c.png]] #notatag";
+        let parsed = format.parse(body);
+        assert!(parsed.tags.is_empty());
+        let links = parsed.links;
+        assert_eq!(links.len(), 2);
+        assert_eq!(
+            (links[0].target.as_str(), links[0].alias.as_deref()),
+            ("b", Some("the b"))
+        );
+        assert!(links[1].embed);
+        assert_eq!(links[1].line, 2);
+        assert_eq!(&body[links[1].start..links[1].end], "![[c.png]]");
+    }
+
+    #[test]
+    fn reads_org_keywords_and_property_drawers() {
+        let content = "#+TITLE: Org note\n#+FILETAGS: :work:home:\n:PROPERTIES:\n\
+                       :ID: 1234\n:ROAM_ALIASES: \"Long alias\" short\n:END:\n\n* Heading\ntext";
+        let format = format_for_path("note.org");
+        let (raw, body) = format.split(content);
+        let raw = raw.unwrap();
+        assert_eq!(body, "* Heading\ntext");
+        assert_eq!(
+            format.metadata(&raw),
+            json!({
+                "title": "Org note",
+                "tags": ["work", "home"],
+                "id": "1234",
+                "aliases": ["Long alias", "short"],
+            })
+        );
+        assert_eq!(format.join(&raw, &body), format!("{}\n\n{}", raw, body));
+        assert_eq!(format.split("* Only a heading").0, None);
+    }
+
+    #[test]
+    fn finds_org_links_tags_and_headlines_outside_blocks() {
+        let body = "* Plans :work:urgent:\n\
+                    [[file:other.org::*Some heading][Other]] and [[https://example.com][web]]\n\
+                    #+BEGIN_SRC sh\n* not a heading :skip:\n[[file:skipped.org]]\n#+END_SRC\n\
+                    ** Sub\n[[file:../up.org]]";
+        let parsed = format_for_path("a.org").parse(body);
+        assert_eq!(parsed.headings, 2);
+        assert_eq!(parsed.tags, vec!["work", "urgent"]);
+
+        let targets: Vec<(&str, Option<&str>)> = parsed
+            .links
+            .iter()
+            .map(|link| (link.target.as_str(), link.alias.as_deref()))
+            .collect();
+        assert_eq!(
+            targets,
+            vec![
+                ("./other.org#Some heading", Some("Other")),
+                ("../up.org", None)
+            ]
+        );
+        assert_eq!(parsed.links[1].line, 8);
+    }
+}
@@ -0,0 +1,448 @@
+// src/notes/health.rs
+use super::attachments;
+use super::encryption;
+use super::formats;
+use super::helpers;
+use super::links::{self, LinkResolver, WikiLink};
+use super::outline::{self, Subpath};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+
+/// How many fix suggestions are returned per broken link
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Suggestions scoring below this are not worth showing
+const MIN_SUGGESTION_SCORE: f64 = 0.4;
+
+/// A note a broken link could point to instead
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkSuggestion {
+    pub relative_path: String,
+    pub title: String,
+    pub score: f64,
+    pub replacement: String,
+}
+
+/// A `[[link]]` that doesn't resolve, with where it is and how to fix it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrokenLink {
+    pub source_path: String,
+    pub source_title: String,
+    pub raw: String,
+    pub target: String,
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+    pub reason: String,
+    pub suggestions: Vec<LinkSuggestion>,
+}
+
+/// Levenshtein distance between two strings, counted in characters
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for i in 1..=a.len() {
+        current[0] = i;
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+/// Case-insensitive similarity between 0.0 and 1.0, where substring matches
+/// score higher than their edit distance alone would give them
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let a = a.to_lowercase();
+    let b = b.to_lowercase();
+    let longest = a.chars().count().max(b.chars().count());
+    if longest == 0 {
+        return 0.0;
+    }
+
+    let score = 1.0 - edit_distance(&a, &b) as f64 / longest as f64;
+    if !a.is_empty() && !b.is_empty() && (a.contains(&b) || b.contains(&a)) {
+        score.max(0.7)
+    } else {
+        score
+    }
+}
+
+/// Rewrites a link to point to a new target, keeping its subpath and alias
+pub fn replacement_link(link: &WikiLink, new_target: &str) -> String {
+    let (_, subpath) = outline::split_link_target(&link.target);
+    let subpath = match subpath {
+        Subpath::None => String::new(),
+        Subpath::Heading(heading) => format!("#{}", heading),
+        Subpath::Block(block_id) => format!("#^{}", block_id),
+    };
+    let alias = link
+        .alias
+        .as_ref()
+        .map(|alias| format!("|{}", alias))
+        .unwrap_or_default();
+
+    format!(
+        "{}[[{}{}{}]]",
+        if link.embed { "!" } else { "" },
+        new_target,
+        subpath,
+        alias
+    )
+}
+
+/// Ranks existing notes by how closely their path, file name or title
+/// resembles a broken link's target
+fn suggest_fixes(
+    link: &WikiLink,
+    notes: &[(String, String)],
+    vault_directory: &str,
+) -> Vec<LinkSuggestion> {
+    let (wanted, _) = outline::split_link_target(&link.target);
+    let wanted = formats::strip_note_extension(&wanted);
+    let wanted_name = wanted.rsplit('/').next().unwrap_or(wanted);
+
+    let mut suggestions: Vec<LinkSuggestion> = notes
+        .iter()
+        .map(|(rel_path, title)| {
+            let path = formats::strip_note_extension(rel_path);
+            let name = path.rsplit('/').next().unwrap_or(path);
+            let mut score = similarity(wanted, path)
+                .max(similarity(wanted_name, name))
+                .max(similarity(wanted, title));
+            for alias in helpers::get_aliases(None, Some(rel_path), Some(vault_directory)) {
+                score = score.max(similarity(wanted, &alias));
+            }
+
+            LinkSuggestion {
+                relative_path: rel_path.clone(),
+                title: title.clone(),
+                score,
+                replacement: replacement_link(link, path),
+            }
+        })
+        .filter(|suggestion| suggestion.score >= MIN_SUGGESTION_SCORE)
+        .collect();
+
+    suggestions.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    suggestions.truncate(MAX_SUGGESTIONS);
+    suggestions
+}
+
+/// Ranks the headings of the linked note by how closely they resemble the
+/// heading a link asks for
+fn suggest_heading_fixes(
+    resolver: &LinkResolver,
+    link: &WikiLink,
+    source_path: &str,
+    vault_directory: &str,
+) -> Vec<LinkSuggestion> {
+    let (note, subpath) = outline::split_link_target(&link.target);
+    let wanted = match subpath {
+        Subpath::Heading(heading) => heading,
+        _ => return Vec::new(),
+    };
+    let target_path = match resolver.resolve_path(&link.target, Some(source_path)) {
+        Some(path) => path,
+        None => return Vec::new(),
+    };
+    let title = helpers::get_title(None, Some(&target_path), Some(vault_directory));
+
+    let mut suggestions: Vec<LinkSuggestion> =
+        outline::get_outline(None, Some(&target_path), Some(vault_directory))
+            .into_iter()
+            .map(|heading| {
+                // Keep the note part as written and only swap the heading
+                let without_heading = WikiLink {
+                    target: note.clone(),
+                    ..link.clone()
+                };
+                LinkSuggestion {
+                    relative_path: target_path.clone(),
+                    title: title.clone(),
+                    score: similarity(&wanted, &heading.text),
+                    replacement: replacement_link(
+                        &without_heading,
+                        &format!("{}#{}", note, heading.text),
+                    ),
+                }
+            })
+            .filter(|suggestion| suggestion.score >= MIN_SUGGESTION_SCORE)
+            .collect();
+
+    suggestions.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    suggestions.truncate(MAX_SUGGESTIONS);
+    suggestions
+}
+
+/// Returns why a link doesn't resolve, or None if it's fine
+fn check_link(
+    resolver: &LinkResolver,
+    link: &WikiLink,
+    source_path: &str,
+    all_attachments: &[attachments::AttachmentInfo],
+    vault_directory: &str,
+) -> Option<String> {
+    let (note, subpath) = outline::split_link_target(&link.target);
+
+    // Links to attachments only need the file to exist
+    if links::is_attachment_target(resolver, &link.target, Some(source_path), all_attachments) {
+        let resolution = attachments::resolve_attachment(all_attachments, source_path, &note, true);
+        return match resolution.status.as_str() {
+            "resolved" => None,
+            "ambiguous" => Some("ambiguous attachment".to_string()),
+            _ => Some("missing attachment".to_string()),
+        };
+    }
+
+    let resolution = resolver.resolve(&link.target, Some(source_path));
+    match resolution.status.as_str() {
+        "unresolved" => return Some("unresolved".to_string()),
+        "ambiguous" => return Some("ambiguous".to_string()),
+        _ => {}
+    }
+
+    // The note exists; make sure the heading or block does too
+    match subpath {
+        Subpath::None => None,
+        // The headings and blocks of an encrypted note can't be checked
+        Subpath::Heading(_) | Subpath::Block(_)
+            if resolution.note.as_ref().is_some_and(|note| {
+                encryption::is_encrypted_note(&note.relative_path, vault_directory)
+            }) =>
+        {
+            None
+        }
+        Subpath::Heading(_) | Subpath::Block(_) => {
+            match outline::resolve_reference_with(
+                resolver,
+                &link.target,
+                Some(source_path),
+                vault_directory,
+            ) {
+                Ok(_) => None,
+                Err(_) if matches!(subpath, Subpath::Heading(_)) => {
+                    Some("missing heading".to_string())
+                }
+                Err(_) => Some("missing block".to_string()),
+            }
+        }
+    }
+}
+
+/// Scans every note in the vault for links that don't resolve
+pub fn find_broken_links(vault_directory: &str) -> Vec<BrokenLink> {
+    let resolver = LinkResolver::new(vault_directory);
+    let all_attachments = attachments::get_all_attachments(vault_directory);
+    let notes: Vec<(String, String)> = helpers::get_all_notes(vault_directory)
+        .into_iter()
+        .map(|(abs_path, rel_path)| {
+            let title = helpers::get_title(Some(&abs_path), Some(&rel_path), Some(vault_directory));
+            (rel_path.replace('\\', "/"), title)
+        })
+        .collect();
+
+    let mut broken = Vec::new();
+
+    for (rel_path, title) in notes.iter() {
+        let content = helpers::get_content(None, Some(rel_path), Some(vault_directory));
+
+        for link in formats::format_for_path(rel_path).links(&content) {
+            let reason = match check_link(
+                &resolver,
+                &link,
+                rel_path,
+                &all_attachments,
+                vault_directory,
+            ) {
+                Some(reason) => reason,
+                None => continue,
+            };
+
+            let suggestions = match reason.as_str() {
+                "missing attachment" | "ambiguous attachment" | "missing block" => Vec::new(),
+                "missing heading" => {
+                    suggest_heading_fixes(&resolver, &link, rel_path, vault_directory)
+                }
+                _ => suggest_fixes(&link, &notes, vault_directory),
+            };
+
+            broken.push(BrokenLink {
+                source_path: rel_path.clone(),
+                source_title: title.clone(),
+                raw: link.raw.clone(),
+                target: link.target.clone(),
+                line: link.line,
+                start: link.start,
+                end: link.end,
+                reason,
+                suggestions,
+            });
+        }
+    }
+
+    println!("Found {} broken links", broken.len());
+    broken
+}
+
+/// Replaces a broken link in its source note. Only the links the broken
+/// link check reports are rewritten: every link written as `raw`, or just
+/// the one at byte offset `start` of the content when given, so the same
+/// text inside code or elsewhere stays as it is. Returns the number of
+/// replaced links.
+pub fn apply_link_fix(
+    source_path: &str,
+    vault_directory: &str,
+    raw: &str,
+    replacement: &str,
+    start: Option<usize>,
+) -> Result<usize, io::Error> {
+    if raw.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Link to replace is empty",
+        ));
+    }
+
+    let path = helpers::resolve_note_path(None, Some(source_path), Some(vault_directory))?;
+    let content = helpers::read_file_content(&path)?;
+    let format = formats::format_for_path(&path);
+    let (_, body) = format.split(&content);
+    // Link offsets are relative to the content after the metadata block
+    let body_offset = content.len() - body.len();
+
+    let mut ranges: Vec<(usize, usize)> = format
+        .links(&body)
+        .into_iter()
+        .filter(|link| link.raw == raw && start.is_none_or(|start| link.start == start))
+        .map(|link| (body_offset + link.start, body_offset + link.end))
+        .collect();
+    if ranges.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Link {} not found in {}", raw, source_path),
+        ));
+    }
+
+    ranges.sort_unstable();
+    let mut updated = content;
+    for (start, end) in ranges.iter().rev() {
+        updated.replace_range(start..end, replacement);
+    }
+    fs::write(&path, updated)?;
+    Ok(ranges.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn write(vault: &Path, relative_path: &str, content: &str) {
+        let path = vault.join(relative_path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn scores_similar_names() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(similarity("Note", "note"), 1.0);
+        assert!(similarity("project plan", "plan") >= 0.7);
+        assert!(similarity("abc", "xyz") < MIN_SUGGESTION_SCORE);
+    }
+
+    #[test]
+    fn finds_broken_links_with_reasons() {
+        let vault = tempfile::tempdir().unwrap();
+        write(vault.path(), "target.md", "# Intro\ntext");
+        write(vault.path(), "a/dup.md", "");
+        write(vault.path(), "b/dup.md", "");
+        write(
+            vault.path(),
+            "source.md",
+            "[[target]] [[targt]] [[target#Missing]] [[dup]] ![[gone.png]]",
+        );
+
+        let vault_directory = vault.path().to_string_lossy();
+        let mut found: Vec<(String, String)> = find_broken_links(&vault_directory)
+            .into_iter()
+            .map(|link| (link.raw, link.reason))
+            .collect();
+        found.sort();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "![[gone.png]]".to_string(),
+                    "missing attachment".to_string()
+                ),
+                ("[[dup]]".to_string(), "ambiguous".to_string()),
+                (
+                    "[[target#Missing]]".to_string(),
+                    "missing heading".to_string()
+                ),
+                ("[[targt]]".to_string(), "unresolved".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn treats_dotted_names_as_notes() {
+        let vault = tempfile::tempdir().unwrap();
+        write(vault.path(), "v1.2 release.md", "");
+        write(vault.path(), "source.md", "[[v1.2 release]] [[v2.0 plan]]");
+
+        let broken = find_broken_links(&vault.path().to_string_lossy());
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].raw, "[[v2.0 plan]]");
+        assert_eq!(broken[0].reason, "unresolved");
+    }
+
+    #[test]
+    fn link_fixes_only_touch_the_reported_links() {
+        let vault = tempfile::tempdir().unwrap();
+        let content = "---\nsee: \"[[old]]\"\n---\n[[old]] and `[[old]]` and [[old|alias]] [[old]]";
+        write(vault.path(), "source.md", content);
+        let vault_directory = vault.path().to_string_lossy();
+
+        let fixed = apply_link_fix("source.md", &vault_directory, "[[old]]", "[[new]]", None);
+        assert_eq!(fixed.unwrap(), 2);
+        assert_eq!(
+            fs::read_to_string(vault.path().join("source.md")).unwrap(),
+            "---\nsee: \"[[old]]\"\n---\n[[new]] and `[[old]]` and [[old|alias]] [[new]]"
+        );
+
+        let broken = find_broken_links(&vault_directory);
+        let alias = broken
+            .iter()
+            .find(|link| link.raw == "[[old|alias]]")
+            .unwrap();
+        let fixed = apply_link_fix(
+            "source.md",
+            &vault_directory,
+            &alias.raw,
+            "[[new|alias]]",
+            Some(alias.start),
+        );
+        assert_eq!(fixed.unwrap(), 1);
+        assert!(apply_link_fix("source.md", &vault_directory, "[[old]]", "x", None).is_err());
+    }
+}
@@ -1,7 +1,10 @@
 // src/notes/helpers.rs (updated version)
+use super::formats;
+use super::links;
+use super::scan::{CancelToken, VaultFilter};
+use rayon::prelude::*;
 use regex::Regex;
-use serde_json::{json, Value as JsonValue};
-use serde_yaml::Value as YamlValue;
+use serde_json::Value as JsonValue;
 use std::fs;
 use std::io::{self, Read};
 use std::path::{Path, PathBuf};
@@ -14,79 +17,92 @@ pub fn find_backlinks(relative_path: &str, vault_directory: &str) -> Vec<(String
     );
 
     // Try using the ripgrep method first
-    if let Some(links) = try_ripgrep_search(relative_path, vault_directory) {
-        return links;
-    }
+    let candidates = match try_ripgrep_search(relative_path, vault_directory) {
+        Some(links) => links,
+        None => {
+            // If ripgrep failed, use the manual search fallback
+            println!("Using fallback method to find backlinks");
+            let backlinks = find_backlinks_fallback(relative_path, vault_directory);
+
+            println!("Fallback method found {} backlinks", backlinks.len());
+            backlinks
+        }
+    };
 
-    // If ripgrep failed, use the manual search fallback
-    println!("Using fallback method to find backlinks");
-    let backlinks = find_backlinks_fallback(relative_path, vault_directory);
+    // The searches above only match path variants, so keep the notes whose
+    // links actually resolve to the target (e.g. not an ambiguous basename)
+    let resolver = links::LinkResolver::new(vault_directory);
+    candidates
+        .into_iter()
+        .filter(|(rel_path, _)| {
+            let content = get_content(None, Some(rel_path), Some(vault_directory));
+            links::links_to(&resolver, &content, rel_path, relative_path)
+        })
+        .collect()
+}
 
-    println!("Fallback method found {} backlinks", backlinks.len());
-    backlinks
+/// The ways a link can name a note: its path and file name, with and
+/// without the extension, and its frontmatter title and aliases
+fn link_variants(relative_path: &str, vault_directory: &str) -> Vec<String> {
+    let file_name = relative_path.rsplit('/').next().unwrap_or(relative_path);
+    let mut variants = vec![
+        relative_path.to_string(),
+        formats::strip_note_extension(relative_path).to_string(),
+        file_name.to_string(),
+        formats::strip_note_extension(file_name).to_string(),
+    ];
+
+    // Links may also use the note's title and frontmatter aliases
+    let frontmatter = get_frontmatter(None, Some(relative_path), Some(vault_directory));
+    variants.push(title_from(frontmatter.as_ref(), relative_path));
+    variants.extend(aliases_from(frontmatter.as_ref()));
+
+    let mut seen = std::collections::HashSet::new();
+    variants.retain(|v| !v.is_empty() && seen.insert(v.clone()));
+    variants
+}
+
+/// Pattern for links to a variant: `[[path]]`, `[[path#heading]]` and
+/// `[[path | alias]]` with optional whitespace around the pipe, and org
+/// `[[file:folder/path::*heading][description]]` links
+fn link_pattern(variant: &str) -> String {
+    format!(
+        r"\[\[(?:file:(?:[^\]]*/)?)?{}(?:[#^][^\]|]*|::[^\]]*)?(?:\s*\|[^\]]+|\]\[[^\]]*)?\]\]",
+        regex::escape(variant)
+    )
 }
 
 fn try_ripgrep_search(relative_path: &str, vault_directory: &str) -> Option<Vec<(String, String)>> {
     let mut all_matches: Vec<(String, String)> = Vec::new();
+    let filter = VaultFilter::load(vault_directory);
 
     // Get the title of the target note
     let title = get_title(None, Some(relative_path), Some(vault_directory));
     println!("Target note title: '{}'", title);
 
-    // Create variations of the path to search for
-    let path_variants = vec![
-        relative_path.to_string(),
-        if relative_path.ends_with(".md") {
-            relative_path.trim_end_matches(".md").to_string()
-        } else {
-            String::new()
-        },
-        if relative_path.contains('/') {
-            let parts: Vec<&str> = relative_path.split('/').collect();
-            if let Some(last_part) = parts.last() {
-                last_part.to_string()
-            } else {
-                String::new()
-            }
-        } else {
-            String::new()
-        },
-        if relative_path.contains('/') && relative_path.ends_with(".md") {
-            let parts: Vec<&str> = relative_path.split('/').collect();
-            if let Some(last_part) = parts.last() {
-                last_part.trim_end_matches(".md").to_string()
-            } else {
-                String::new()
-            }
-        } else {
-            String::new()
-        },
-    ];
-
-    // Filter out empty variants
-    let path_variants: Vec<String> = path_variants
-        .into_iter()
-        .filter(|v| !v.is_empty())
-        .collect();
+    let path_variants = link_variants(relative_path, vault_directory);
 
     println!("Will search for these path variants: {:?}", path_variants);
 
     for variant in path_variants {
-        // Create pattern that handles both [[path]] and [[path | alias]] formats
-        // with optional whitespace around the pipe
-        let pattern = format!(r"\[\[{}(?:\s*\|[^\]]+)?\]\]", regex::escape(&variant));
+        let pattern = link_pattern(&variant);
 
         println!("Trying ripgrep with pattern: '{}'", pattern);
 
-        let output = Command::new("rg")
+        let mut command = Command::new("rg");
+        command
             .arg("--files-with-matches")
             .arg("--no-heading")
             .arg("-U") // Multiline mode
-            .arg("--glob")
-            .arg("*.md")
-            .arg(&pattern)
-            .arg(vault_directory)
-            .output();
+            .arg("-i") // Links resolve case-insensitively
+            // The vault's own ignore rules apply instead of ripgrep's
+            .arg("--no-ignore")
+            .arg("--hidden")
+            .arg("--follow");
+        for extension in filter.note_extensions() {
+            command.arg("--glob").arg(format!("*.{}", extension));
+        }
+        let output = command.arg(&pattern).arg(vault_directory).output();
 
         match output {
             Ok(o) => {
@@ -105,7 +121,7 @@ fn try_ripgrep_search(relative_path: &str, vault_directory: &str) -> Option<Vec<
                 // Process each line which represents a matching file
                 for line in output_str.lines() {
                     let file_path = Path::new(line.trim());
-                    if !file_path.exists() {
+                    if !file_path.exists() || filter.is_ignored(file_path, false) {
                         continue;
                     }
 
@@ -164,46 +180,9 @@ fn find_backlinks_fallback(relative_path: &str, vault_directory: &str) -> Vec<(S
     let all_notes = get_all_notes(vault_directory);
     println!("Scanning {} notes for backlinks", all_notes.len());
 
-    // Create multiple patterns to try
-    let patterns = vec![
-        // Standard pattern [[path]]
-        format!(r"\[\[{}\]\]", regex::escape(relative_path)),
-        // Aliased pattern [[path|alias]]
-        format!(r"\[\[{}\|", regex::escape(relative_path)),
-    ];
-
-    // Also try without extension if it has one
-    let no_ext_patterns = if relative_path.ends_with(".md") {
-        let no_ext = relative_path.trim_end_matches(".md");
-        vec![
-            format!(r"\[\[{}\]\]", regex::escape(no_ext)),
-            format!(r"\[\[{}\|", regex::escape(no_ext)),
-        ]
-    } else {
-        Vec::new()
-    };
-
-    // Also try just the filename without path if it's in a subfolder
-    let filename_patterns = if relative_path.contains('/') {
-        let parts: Vec<&str> = relative_path.split('/').collect();
-        if let Some(last_part) = parts.last() {
-            vec![
-                format!(r"\[\[{}\]\]", regex::escape(last_part)),
-                format!(r"\[\[{}\|", regex::escape(last_part)),
-            ]
-        } else {
-            Vec::new()
-        }
-    } else {
-        Vec::new()
-    };
-
-    // Combine all patterns
-    let all_patterns: Vec<Regex> = patterns
-        .into_iter()
-        .chain(no_ext_patterns.into_iter())
-        .chain(filename_patterns.into_iter())
-        .filter_map(|p| Regex::new(&p).ok())
+    let all_patterns: Vec<Regex> = link_variants(relative_path, vault_directory)
+        .iter()
+        .filter_map(|variant| Regex::new(&format!("(?i){}", link_pattern(variant))).ok())
         .collect();
 
     println!("Using {} regex patterns for matching", all_patterns.len());
@@ -215,7 +194,7 @@ fn find_backlinks_fallback(relative_path: &str, vault_directory: &str) -> Vec<(S
         }
 
         // Read file content
-        if let Ok(content) = read_file_content(&Path::new(&abs_path)) {
+        if let Ok(content) = read_file_content(Path::new(&abs_path)) {
             // Check if this file has any matches with any pattern
             let has_match = all_patterns.iter().any(|regex| regex.is_match(&content));
 
@@ -249,11 +228,12 @@ pub fn update_note(
     let current_content = read_file_content(&path)?;
 
     // Extract frontmatter from the current content
-    let (frontmatter, _) = extract_frontmatter_and_content(&current_content);
+    let format = formats::format_for_path(&path);
+    let (frontmatter, _) = format.split(&current_content);
 
     // Create the new content with frontmatter preserved
     let updated_content = if let Some(frontmatter) = frontmatter {
-        format!("---\n{}\n---\n\n{}", frontmatter, new_content)
+        format.join(&frontmatter, new_content)
     } else {
         // If there was no frontmatter, just use the new content
         new_content.to_string()
@@ -265,60 +245,51 @@ pub fn update_note(
     Ok(())
 }
 
-/// Recursively scans a directory for markdown files and returns them as (absolute_path, relative_path) pairs
+/// Scans the vault for notes and returns them as (absolute_path, relative_path) pairs.
+/// Files excluded by the vault's ignore rules are left out.
 pub fn get_all_notes(vault_directory: &str) -> Vec<(String, String)> {
+    get_all_notes_until(vault_directory, &CancelToken::default()).unwrap_or_default()
+}
+
+/// Like `get_all_notes`, walking and resolving paths on a thread pool.
+/// Returns `None` if the scan was cancelled.
+pub fn get_all_notes_until(
+    vault_directory: &str,
+    cancel: &CancelToken,
+) -> Option<Vec<(String, String)>> {
     let vault_path = Path::new(vault_directory);
     if !vault_path.exists() || !vault_path.is_dir() {
-        return Vec::new();
-    }
-
-    let mut result = Vec::new();
-    if let Err(_) = collect_markdown_files(vault_path, vault_path, &mut result) {
-        return Vec::new();
+        return Some(Vec::new());
     }
-    result
-}
 
-/// Helper function to recursively collect markdown files
-fn collect_markdown_files(
-    base_dir: &Path,
-    current_dir: &Path,
-    result: &mut Vec<(String, String)>,
-) -> Result<(), io::Error> {
-    for entry in fs::read_dir(current_dir)? {
-        let entry = entry?;
-        let path = entry.path();
-
-        if path.is_dir() {
-            collect_markdown_files(base_dir, &path, result)?;
-        } else if path.is_file() {
-            if let Some(ext) = path.extension() {
-                if ext == "md" {
-                    // Get absolute path
-                    let absolute_path = match fs::canonicalize(&path) {
-                        Ok(p) => p.to_string_lossy().to_string(),
-                        Err(_) => path.to_string_lossy().to_string(),
-                    };
-
-                    // Calculate relative path
-                    let relative_path = match path.strip_prefix(base_dir) {
-                        Ok(rel_path) => rel_path.to_string_lossy().to_string(),
-                        Err(_) => {
-                            // Fallback: use the filename if we can't get the relative path
-                            path.file_name()
-                                .unwrap_or_default()
-                                .to_string_lossy()
-                                .to_string()
-                        }
-                    };
-
-                    result.push((absolute_path, relative_path));
+    let filter = VaultFilter::load(vault_directory);
+    let files = filter.files_until(cancel)?;
+    let result = files
+        .into_par_iter()
+        .filter(|path| filter.is_note(path))
+        .map(|path| {
+            // Get absolute path
+            let absolute_path = match fs::canonicalize(&path) {
+                Ok(p) => p.to_string_lossy().to_string(),
+                Err(_) => path.to_string_lossy().to_string(),
+            };
+
+            // Calculate relative path
+            let relative_path = match path.strip_prefix(vault_path) {
+                Ok(rel_path) => rel_path.to_string_lossy().to_string(),
+                Err(_) => {
+                    // Fallback: use the filename if we can't get the relative path
+                    path.file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .to_string()
                 }
-            }
-        }
-    }
+            };
 
-    Ok(())
+            (absolute_path, relative_path)
+        })
+        .collect();
+    Some(result)
 }
 
 /// Resolves a path to a note, given either an absolute path or a relative path and the vault directory
@@ -367,9 +338,9 @@ pub fn read_file_content(path: &Path) -> Result<String, io::Error> {
 /// Extracts frontmatter and content from a markdown file
 pub fn extract_frontmatter_and_content(content: &str) -> (Option<String>, String) {
     // Check if content starts with frontmatter delimiter
-    if content.starts_with("---") {
+    if let Some(after_delimiter) = content.strip_prefix("---") {
         // Find the end of the frontmatter (second occurrence of ---)
-        if let Some(end_index) = content[3..].find("---") {
+        if let Some(end_index) = after_delimiter.find("---") {
             let frontmatter = &content[3..(end_index + 3)].trim();
             let content_start = end_index + 6; // Skip past the second "---"
 
@@ -384,7 +355,8 @@ pub fn extract_frontmatter_and_content(content: &str) -> (Option<String>, String
     (None, content.to_string())
 }
 
-/// Strips frontmatter from a markdown file's content
+/// Strips frontmatter (or the metadata block of the note's format) from a
+/// note's content
 pub fn strip_frontmatter(
     absolute_path: Option<&str>,
     relative_path: Option<&str>,
@@ -393,7 +365,8 @@ pub fn strip_frontmatter(
     match resolve_note_path(absolute_path, relative_path, vault_directory) {
         Ok(path) => match read_file_content(&path) {
             Ok(content) => {
-                let (_, content_without_frontmatter) = extract_frontmatter_and_content(&content);
+                let (_, content_without_frontmatter) =
+                    formats::format_for_path(&path).split(&content);
                 content_without_frontmatter
             }
             Err(_) => String::new(),
@@ -402,75 +375,18 @@ pub fn strip_frontmatter(
     }
 }
 
-/// Extracts and parses the YAML frontmatter from a markdown file into a JSON object
+/// Extracts and parses a note's frontmatter (or the metadata block of its
+/// format) into a JSON object
 pub fn get_frontmatter(
     absolute_path: Option<&str>,
     relative_path: Option<&str>,
     vault_directory: Option<&str>,
 ) -> Option<JsonValue> {
-    match resolve_note_path(absolute_path, relative_path, vault_directory) {
-        Ok(path) => {
-            match read_file_content(&path) {
-                Ok(content) => {
-                    let (frontmatter, _) = extract_frontmatter_and_content(&content);
-
-                    if let Some(frontmatter_content) = frontmatter {
-                        match serde_yaml::from_str::<YamlValue>(&frontmatter_content) {
-                            Ok(yaml_value) => {
-                                // Convert YAML value to JSON value
-                                Some(yaml_to_json(yaml_value))
-                            }
-                            Err(_) => {
-                                // If we can't parse as YAML, return an empty object
-                                Some(json!({}))
-                            }
-                        }
-                    } else {
-                        // No frontmatter found
-                        None
-                    }
-                }
-                Err(_) => None,
-            }
-        }
-        Err(_) => None,
-    }
-}
-
-/// Helper function to convert YAML Value to JSON Value
-fn yaml_to_json(yaml: YamlValue) -> JsonValue {
-    match yaml {
-        YamlValue::Null => JsonValue::Null,
-        YamlValue::Bool(b) => JsonValue::Bool(b),
-        YamlValue::Number(n) => {
-            if let Some(i) = n.as_i64() {
-                JsonValue::Number(i.into())
-            } else if let Some(f) = n.as_f64() {
-                if let Some(n) = serde_json::Number::from_f64(f) {
-                    JsonValue::Number(n)
-                } else {
-                    JsonValue::Null
-                }
-            } else {
-                JsonValue::Null
-            }
-        }
-        YamlValue::String(s) => JsonValue::String(s),
-        YamlValue::Sequence(seq) => JsonValue::Array(seq.into_iter().map(yaml_to_json).collect()),
-        YamlValue::Mapping(map) => {
-            let mut obj = serde_json::Map::new();
-            for (k, v) in map {
-                if let Some(key) = k.as_str() {
-                    obj.insert(key.to_string(), yaml_to_json(v));
-                }
-            }
-            JsonValue::Object(obj)
-        }
-        YamlValue::Tagged(tagged) => {
-            // For tagged values, just convert the value part and ignore the tag
-            yaml_to_json(tagged.value)
-        }
-    }
+    let path = resolve_note_path(absolute_path, relative_path, vault_directory).ok()?;
+    let content = read_file_content(&path).ok()?;
+    let format = formats::format_for_path(&path);
+    let (metadata, _) = format.split(&content);
+    metadata.map(|raw| format.metadata(&raw))
 }
 
 /// Gets the title of a markdown file from its frontmatter or filename
@@ -479,26 +395,91 @@ pub fn get_title(
     relative_path: Option<&str>,
     vault_directory: Option<&str>,
 ) -> String {
-    // Try to get title from frontmatter
-    if let Some(frontmatter) = get_frontmatter(absolute_path, relative_path, vault_directory) {
-        if let Some(title) = frontmatter.get("title") {
-            if let Some(title_str) = title.as_str() {
-                return title_str.to_string();
-            }
-        }
+    let frontmatter = get_frontmatter(absolute_path, relative_path, vault_directory);
+    match resolve_note_path(absolute_path, relative_path, vault_directory) {
+        Ok(path) => title_from(frontmatter.as_ref(), &path.to_string_lossy()),
+        // Fallback to empty string if we couldn't extract a title
+        Err(_) => String::new(),
     }
+}
 
-    // If no title in frontmatter, use filename
-    if let Ok(path) = resolve_note_path(absolute_path, relative_path, vault_directory) {
-        if let Some(filename) = path.file_stem() {
-            if let Some(filename_str) = filename.to_str() {
-                return filename_str.to_string();
-            }
-        }
+/// The title of a note given its frontmatter: the `title` key, or else the
+/// file name without its extension
+pub fn title_from(frontmatter: Option<&JsonValue>, path: &str) -> String {
+    if let Some(title) = frontmatter
+        .and_then(|frontmatter| frontmatter.get("title"))
+        .and_then(|title| title.as_str())
+    {
+        return title.to_string();
+    }
+
+    Path::new(path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// A note's frontmatter, title, aliases and body, from one read of the file
+#[derive(Debug, Clone)]
+pub struct ParsedNote {
+    pub frontmatter: Option<JsonValue>,
+    pub title: String,
+    pub aliases: Vec<String>,
+    pub body: String,
+}
+
+/// Splits and parses a note's content the way its format writes it
+pub fn parse_note(path: &str, content: &str) -> ParsedNote {
+    let format = formats::format_for_path(path);
+    let (metadata, body) = format.split(content);
+    let frontmatter = metadata.map(|raw| format.metadata(&raw));
+    ParsedNote {
+        title: title_from(frontmatter.as_ref(), path),
+        aliases: aliases_from(frontmatter.as_ref()),
+        frontmatter,
+        body,
     }
+}
+
+/// Gets the aliases of a markdown file from the `aliases` (or `alias`) frontmatter key,
+/// which may be a single string or a list
+pub fn get_aliases(
+    absolute_path: Option<&str>,
+    relative_path: Option<&str>,
+    vault_directory: Option<&str>,
+) -> Vec<String> {
+    aliases_from(get_frontmatter(absolute_path, relative_path, vault_directory).as_ref())
+}
+
+/// The aliases listed in a note's frontmatter
+pub fn aliases_from(frontmatter: Option<&JsonValue>) -> Vec<String> {
+    let frontmatter = match frontmatter {
+        Some(frontmatter) => frontmatter,
+        None => return Vec::new(),
+    };
+
+    let value = match frontmatter
+        .get("aliases")
+        .or_else(|| frontmatter.get("alias"))
+    {
+        Some(value) => value,
+        None => return Vec::new(),
+    };
+
+    let aliases: Vec<String> = match value {
+        JsonValue::String(alias) => vec![alias.clone()],
+        JsonValue::Array(items) => items
+            .iter()
+            .filter_map(|item| item.as_str().map(|a| a.to_string()))
+            .collect(),
+        _ => Vec::new(),
+    };
 
-    // Fallback to empty string if we couldn't extract a title
-    String::new()
+    aliases
+        .into_iter()
+        .map(|alias| alias.trim().to_string())
+        .filter(|alias| !alias.is_empty())
+        .collect()
 }
 
 /// Gets the content of a markdown file with frontmatter stripped
@@ -509,3 +490,52 @@ pub fn get_content(
 ) -> String {
     strip_frontmatter(absolute_path, relative_path, vault_directory)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(vault: &Path, relative_path: &str, content: &str) {
+        let path = vault.join(relative_path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn finds_backlinks_by_path_and_title() {
+        let vault = tempfile::tempdir().unwrap();
+        write(vault.path(), "target.md", "---\ntitle: Big Idea\n---\nbody");
+        write(vault.path(), "by-path.md", "see [[target]]");
+        write(vault.path(), "by-title.md", "see [[Big Idea|the idea]]");
+        write(vault.path(), "unrelated.md", "see [[elsewhere]]");
+
+        let vault_directory = vault.path().to_string_lossy();
+        let mut sources: Vec<String> = find_backlinks("target.md", &vault_directory)
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        sources.sort();
+        assert_eq!(sources, vec!["by-path.md", "by-title.md"]);
+    }
+
+    #[test]
+    fn reads_titles_and_aliases_from_frontmatter() {
+        let frontmatter = serde_json::json!({"title": "Named", "aliases": ["One", " Two "]});
+        assert_eq!(title_from(Some(&frontmatter), "folder/file.md"), "Named");
+        assert_eq!(title_from(None, "folder/file.md"), "file");
+        assert_eq!(aliases_from(Some(&frontmatter)), vec!["One", "Two"]);
+        assert!(aliases_from(None).is_empty());
+    }
+
+    #[test]
+    fn parses_notes_by_format() {
+        let note = parse_note("a/note.md", "---\ntitle: Named\nalias: One\n---\nbody");
+        assert_eq!(note.title, "Named");
+        assert_eq!(note.aliases, vec!["One"]);
+        assert_eq!(note.body, "body");
+
+        let org = parse_note("a/agenda.org", "#+title: Agenda\n* Today");
+        assert_eq!(org.title, "Agenda");
+        assert_eq!(org.body.trim(), "* Today");
+    }
+}
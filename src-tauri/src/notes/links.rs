@@ -0,0 +1,750 @@
+// src/notes/links.rs
+use super::ast;
+use super::attachments;
+use super::encryption;
+use super::formats;
+use super::helpers;
+use super::outline::{self, Subpath};
+use super::scan;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::cell::OnceCell;
+use std::path::Path;
+
+/// A `[[wiki link]]` found in a note's content
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WikiLink {
+    pub raw: String,
+    pub target: String,
+    pub alias: Option<String>,
+    pub embed: bool,
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A note a link resolved to
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LinkCandidate {
+    pub relative_path: String,
+    pub title: String,
+}
+
+/// The outcome of resolving a link: `resolved` with a single note,
+/// `ambiguous` with several candidates, or `unresolved`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkResolution {
+    pub status: String,
+    pub note: Option<LinkCandidate>,
+    pub candidates: Vec<LinkCandidate>,
+    pub heading: Option<String>,
+    pub block_id: Option<String>,
+}
+
+/// Extracts all `[[wiki links]]` and `![[embeds]]` from a piece of markdown,
+/// skipping code. Offsets are byte offsets and lines are 1-based.
+pub fn extract_wiki_links(content: &str) -> Vec<WikiLink> {
+    ast::parse(content).wiki_links
+}
+
+/// Normalizes a note path for comparison: forward slashes, no note extension
+fn normalize(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let path = path.trim_start_matches("./").trim_matches('/');
+    formats::strip_note_extension(path).to_string()
+}
+
+/// Joins a `./` or `../` link onto the folder of the note containing it
+pub fn join_relative(source_path: &str, link: &str) -> String {
+    let mut parts: Vec<&str> = source_path.split('/').collect();
+    parts.pop();
+
+    for segment in link.split('/') {
+        match segment {
+            "." | "" => {}
+            ".." => {
+                parts.pop();
+            }
+            other => parts.push(other),
+        }
+    }
+
+    parts.join("/")
+}
+
+/// Resolves links against a snapshot of the vault's notes. Build it once and
+/// reuse it when resolving many links.
+pub struct LinkResolver {
+    vault_directory: String,
+    notes: Vec<String>,
+    names: OnceCell<Vec<NoteNames>>,
+}
+
+/// The title and frontmatter aliases a note can be linked by
+#[derive(Clone)]
+struct NoteNames {
+    title: String,
+    aliases: Vec<String>,
+}
+
+impl LinkResolver {
+    pub fn new(vault_directory: &str) -> Self {
+        let notes = helpers::get_all_notes(vault_directory)
+            .into_iter()
+            .map(|(_, rel_path)| rel_path)
+            .collect();
+        Self::with_notes(vault_directory, notes)
+    }
+
+    /// Builds a resolver over notes that are already known, such as the
+    /// ones in the note cache, without scanning the vault
+    pub fn with_notes(vault_directory: &str, notes: Vec<String>) -> Self {
+        LinkResolver {
+            vault_directory: vault_directory.to_string(),
+            notes: notes
+                .into_iter()
+                .map(|rel_path| rel_path.replace('\\', "/"))
+                .collect(),
+            names: OnceCell::new(),
+        }
+    }
+
+    /// Builds a resolver whose titles and aliases are already known, as
+    /// `(path, title, aliases)`, so title links never read the notes
+    pub fn with_names(vault_directory: &str, notes: Vec<(String, String, Vec<String>)>) -> Self {
+        let mut paths = Vec::with_capacity(notes.len());
+        let mut names = Vec::with_capacity(notes.len());
+        for (rel_path, title, aliases) in notes {
+            paths.push(rel_path);
+            names.push(NoteNames { title, aliases });
+        }
+        let resolver = Self::with_notes(vault_directory, paths);
+        let _ = resolver.names.set(names);
+        resolver
+    }
+
+    /// The resolver as it will be once the folder `from` has moved to `to`,
+    /// for working out what links will mean before moving anything
+    pub fn with_moved_folder(&self, from: &str, to: &str) -> Self {
+        let notes = self
+            .notes
+            .iter()
+            .map(|rel_path| {
+                super::folders::moved_path(rel_path, from, to).unwrap_or_else(|| rel_path.clone())
+            })
+            .collect();
+        LinkResolver {
+            vault_directory: self.vault_directory.clone(),
+            notes,
+            names: OnceCell::from(self.names().clone()),
+        }
+    }
+
+    /// Titles and aliases of all notes, read from their frontmatter the
+    /// first time a link needs them
+    fn names(&self) -> &Vec<NoteNames> {
+        self.names.get_or_init(|| {
+            self.notes
+                .iter()
+                .map(|rel_path| {
+                    let frontmatter =
+                        helpers::get_frontmatter(None, Some(rel_path), Some(&self.vault_directory));
+                    NoteNames {
+                        title: helpers::title_from(frontmatter.as_ref(), rel_path),
+                        aliases: helpers::aliases_from(frontmatter.as_ref()),
+                    }
+                })
+                .collect()
+        })
+    }
+
+    fn candidate(&self, index: usize) -> LinkCandidate {
+        LinkCandidate {
+            relative_path: self.notes[index].clone(),
+            title: self.names()[index].title.clone(),
+        }
+    }
+
+    /// Finds the notes whose path ends with the link path, on a folder boundary
+    fn suffix_matches(&self, link: &str, case_sensitive: bool) -> Vec<usize> {
+        let fold = |s: &str| {
+            if case_sensitive {
+                s.to_string()
+            } else {
+                s.to_lowercase()
+            }
+        };
+        let wanted = fold(link);
+        let wanted_suffix = format!("/{}", wanted);
+
+        self.notes
+            .iter()
+            .enumerate()
+            .filter(|(_, rel_path)| {
+                let path = fold(&normalize(rel_path));
+                path == wanted || path.ends_with(&wanted_suffix)
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Picks one of several matching notes if the link can only mean one of
+    /// them: an exact path match, then a note in the same folder as the source
+    fn disambiguate(
+        &self,
+        matches: &[usize],
+        link: &str,
+        source_path: Option<&str>,
+    ) -> Option<usize> {
+        if matches.len() == 1 {
+            return Some(matches[0]);
+        }
+
+        let lowered = link.to_lowercase();
+        let exact: Vec<usize> = matches
+            .iter()
+            .copied()
+            .filter(|&index| normalize(&self.notes[index]).to_lowercase() == lowered)
+            .collect();
+        if exact.len() == 1 {
+            return Some(exact[0]);
+        }
+
+        if let Some(source) = source_path {
+            let folder = Path::new(source)
+                .parent()
+                .map(|p| p.to_string_lossy().replace('\\', "/"))
+                .unwrap_or_default();
+            let siblings: Vec<usize> = matches
+                .iter()
+                .copied()
+                .filter(|&index| {
+                    let note_folder = Path::new(&self.notes[index])
+                        .parent()
+                        .map(|p| p.to_string_lossy().replace('\\', "/"))
+                        .unwrap_or_default();
+                    note_folder == folder
+                })
+                .collect();
+            if siblings.len() == 1 {
+                return Some(siblings[0]);
+            }
+        }
+
+        None
+    }
+
+    /// Finds the notes a link target (without subpath) may point to. Returns
+    /// a single index when the link is unambiguous.
+    pub fn resolve_indices(&self, link_target: &str, source_path: Option<&str>) -> Vec<usize> {
+        let link = if link_target.starts_with("./") || link_target.starts_with("../") {
+            match source_path {
+                Some(source) => join_relative(source, link_target),
+                None => link_target.to_string(),
+            }
+        } else {
+            link_target.to_string()
+        };
+        let link = normalize(&link);
+        if link.is_empty() {
+            return Vec::new();
+        }
+
+        // Shortest unique path, then the same ignoring case
+        for case_sensitive in [true, false] {
+            let matches = self.suffix_matches(&link, case_sensitive);
+            if matches.is_empty() {
+                continue;
+            }
+            return match self.disambiguate(&matches, &link, source_path) {
+                Some(index) => vec![index],
+                None => matches,
+            };
+        }
+
+        // Finally, the link may name a note by its title or one of its aliases
+        let lowered = link.to_lowercase();
+        self.names()
+            .iter()
+            .enumerate()
+            .filter(|(_, names)| {
+                names.title.to_lowercase() == lowered
+                    || names.aliases.iter().any(|a| a.to_lowercase() == lowered)
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Resolves a link to the note's relative path when it is unambiguous
+    pub fn resolve_path(&self, link_target: &str, source_path: Option<&str>) -> Option<String> {
+        let (note, _) = outline::split_link_target(link_target);
+        if note.is_empty() {
+            return source_path.map(|p| p.to_string());
+        }
+
+        let matches = self.resolve_indices(&note, source_path);
+        if matches.len() == 1 {
+            Some(self.notes[matches[0]].clone())
+        } else {
+            None
+        }
+    }
+
+    /// Resolves a link's text (`target`, `target#Heading`, `target|alias`...)
+    pub fn resolve(&self, link_text: &str, source_path: Option<&str>) -> LinkResolution {
+        let (note, subpath) = outline::split_link_target(link_text);
+        let (heading, block_id) = match subpath {
+            Subpath::None => (None, None),
+            Subpath::Heading(heading) => (Some(heading), None),
+            Subpath::Block(block_id) => (None, Some(block_id)),
+        };
+
+        // A link with only a subpath points into the source note
+        let matches = if note.is_empty() {
+            source_path
+                .and_then(|source| {
+                    let source = source.replace('\\', "/");
+                    self.notes.iter().position(|rel_path| *rel_path == source)
+                })
+                .into_iter()
+                .collect()
+        } else {
+            self.resolve_indices(&note, source_path)
+        };
+
+        let candidates: Vec<LinkCandidate> =
+            matches.iter().map(|&index| self.candidate(index)).collect();
+        let status = match candidates.len() {
+            0 => "unresolved",
+            1 => "resolved",
+            _ => "ambiguous",
+        };
+
+        LinkResolution {
+            status: status.to_string(),
+            note: if candidates.len() == 1 {
+                candidates.first().cloned()
+            } else {
+                None
+            },
+            candidates: if candidates.len() > 1 {
+                candidates
+            } else {
+                Vec::new()
+            },
+            heading,
+            block_id,
+        }
+    }
+}
+
+/// Resolves a single link from a source note
+pub fn resolve_link(source_path: &str, link_text: &str, vault_directory: &str) -> LinkResolution {
+    let resolver = LinkResolver::new(vault_directory);
+    let source = if source_path.is_empty() {
+        None
+    } else {
+        Some(source_path)
+    };
+    resolver.resolve(link_text, source)
+}
+
+/// Returns true when a link target means a file rather than a note. A dot in
+/// the name isn't enough: `[[v1.2 release]]` or `[[Dr. Smith]]` stay note
+/// links. Targets that resolve to a note are notes; the rest are files when
+/// their extension is a known attachment type or a file by that name exists.
+pub fn is_attachment_target(
+    resolver: &LinkResolver,
+    target: &str,
+    source_path: Option<&str>,
+    all_attachments: &[attachments::AttachmentInfo],
+) -> bool {
+    let (note, _) = outline::split_link_target(target);
+    let extension = match note.rsplit_once('.') {
+        Some((_, ext)) if !ext.is_empty() && !ext.contains('/') => ext,
+        _ => return false,
+    };
+    if scan::is_note_extension(extension)
+        || !resolver.resolve_indices(&note, source_path).is_empty()
+    {
+        return false;
+    }
+
+    attachments::attachment_kind(extension) != "other"
+        || attachments::resolve_attachment(
+            all_attachments,
+            source_path.unwrap_or_default(),
+            &note,
+            true,
+        )
+        .status
+            != "unresolved"
+}
+
+/// Returns true if any link in `content` resolves to `target_path`. Links
+/// are read the way the source note's format writes them.
+pub fn links_to(
+    resolver: &LinkResolver,
+    content: &str,
+    source_path: &str,
+    target_path: &str,
+) -> bool {
+    let target = target_path.replace('\\', "/");
+    let format = formats::format_for_path(source_path);
+    format.links(content).iter().any(|link| {
+        resolver
+            .resolve_path(&link.target, Some(source_path))
+            .map(|resolved| resolved == target)
+            .unwrap_or(false)
+    })
+}
+
+/// A plain-text mention of a note's title or alias that isn't linked yet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnlinkedMention {
+    pub relative_path: String,
+    pub title: String,
+    pub matched_text: String,
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+    pub context: String,
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Whether a match ends on a word boundary at `index`. Names that start or
+/// end with punctuation, like `C++` or `(draft)`, need no boundary there.
+fn is_boundary(text: &str, index: usize) -> bool {
+    let before = text[..index].chars().next_back();
+    let after = text[index..].chars().next();
+    !(before.is_some_and(is_word_char) && after.is_some_and(is_word_char))
+}
+
+/// Finds the matches of a name pattern that start and end on boundaries
+fn find_mentions<'a>(mention_regex: &Regex, content: &'a str) -> Vec<regex::Match<'a>> {
+    let mut found = Vec::new();
+    let mut from = 0;
+    while let Some(candidate) = mention_regex.find_at(content, from) {
+        if candidate.is_empty() {
+            break;
+        }
+        if is_boundary(content, candidate.start()) && is_boundary(content, candidate.end()) {
+            found.push(candidate);
+            from = candidate.end();
+        } else {
+            // Try again from the next character, inside the rejected match
+            from = candidate.start()
+                + content[candidate.start()..]
+                    .chars()
+                    .next()
+                    .map_or(1, char::len_utf8);
+        }
+    }
+    found
+}
+
+/// Finds notes that mention the target note's title or aliases in plain text
+/// without linking to it
+pub fn find_unlinked_mentions(relative_path: &str, vault_directory: &str) -> Vec<UnlinkedMention> {
+    let target = relative_path.replace('\\', "/");
+    let mut names = vec![helpers::get_title(
+        None,
+        Some(relative_path),
+        Some(vault_directory),
+    )];
+    names.extend(helpers::get_aliases(
+        None,
+        Some(relative_path),
+        Some(vault_directory),
+    ));
+    names.retain(|name| !name.trim().is_empty());
+    if names.is_empty() {
+        return Vec::new();
+    }
+
+    // Longest names first so "Kubernetes Operators" wins over "Kubernetes"
+    names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+    let pattern = names
+        .iter()
+        .map(|name| regex::escape(name))
+        .collect::<Vec<String>>()
+        .join("|");
+    let mention_regex = match Regex::new(&format!(r"(?i)(?:{})", pattern)) {
+        Ok(regex) => regex,
+        Err(_) => return Vec::new(),
+    };
+
+    let resolver = LinkResolver::new(vault_directory);
+    let mut mentions = Vec::new();
+
+    for (index, rel_path) in resolver.notes.iter().enumerate() {
+        if *rel_path == target {
+            continue;
+        }
+
+        let content = helpers::get_content(None, Some(rel_path), Some(vault_directory));
+        if encryption::is_encrypted(&content) || links_to(&resolver, &content, rel_path, &target) {
+            continue;
+        }
+
+        // Mentions inside existing links or code don't count
+        let note_ast = ast::parse(&content);
+        let link_ranges: Vec<(usize, usize)> = note_ast
+            .wiki_links
+            .iter()
+            .map(|link| (link.start, link.end))
+            .chain(note_ast.links.iter().map(|link| (link.start, link.end)))
+            .collect();
+
+        for found in find_mentions(&mention_regex, &content) {
+            let in_link = link_ranges
+                .iter()
+                .any(|(start, end)| found.start() >= *start && found.end() <= *end);
+            if in_link || note_ast.in_code(found.start(), found.end()) {
+                continue;
+            }
+
+            let line_start = content[..found.start()]
+                .rfind('\n')
+                .map(|i| i + 1)
+                .unwrap_or(0);
+            let line_end = content[found.end()..]
+                .find('\n')
+                .map(|i| found.end() + i)
+                .unwrap_or(content.len());
+
+            mentions.push(UnlinkedMention {
+                relative_path: rel_path.clone(),
+                title: resolver.names()[index].title.clone(),
+                matched_text: found.as_str().to_string(),
+                line: content[..found.start()].matches('\n').count() + 1,
+                start: found.start(),
+                end: found.end(),
+                context: content[line_start..line_end].trim().to_string(),
+            });
+        }
+    }
+
+    mentions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn write(vault: &Path, relative_path: &str, content: &str) {
+        let path = vault.join(relative_path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn mentions(vault: &Path, relative_path: &str) -> Vec<(String, String)> {
+        find_unlinked_mentions(relative_path, &vault.to_string_lossy())
+            .into_iter()
+            .map(|mention| (mention.relative_path, mention.matched_text))
+            .collect()
+    }
+
+    #[test]
+    fn mentions_respect_word_boundaries() {
+        let vault = tempfile::tempdir().unwrap();
+        write(vault.path(), "Rust.md", "body");
+        write(
+            vault.path(),
+            "other.md",
+            "I like rust, but not Rustacean or trust.\n`rust` in code and [[elsewhere|rust]].",
+        );
+        assert_eq!(
+            mentions(vault.path(), "Rust.md"),
+            vec![("other.md".to_string(), "rust".to_string())]
+        );
+    }
+
+    #[test]
+    fn mentions_names_with_punctuation() {
+        let vault = tempfile::tempdir().unwrap();
+        write(
+            vault.path(),
+            "lang.md",
+            "---\ntitle: C++\naliases: [.NET, (draft)]\n---\nbody",
+        );
+        write(
+            vault.path(),
+            "other.md",
+            "Compared C++ with .NET here (draft) and not in xC++ or ASP.NETCore.",
+        );
+        let found: Vec<String> = mentions(vault.path(), "lang.md")
+            .into_iter()
+            .map(|(_, text)| text)
+            .collect();
+        assert_eq!(found, vec!["C++", ".NET", "(draft)"]);
+    }
+
+    #[test]
+    fn string_aliases_are_not_split_on_commas() {
+        let vault = tempfile::tempdir().unwrap();
+        write(
+            vault.path(),
+            "person.md",
+            "---\naliases: Smith, John\n---\nbody",
+        );
+        write(
+            vault.path(),
+            "other.md",
+            "Met Smith, John today. Smith alone.",
+        );
+        assert_eq!(
+            mentions(vault.path(), "person.md"),
+            vec![("other.md".to_string(), "Smith, John".to_string())]
+        );
+    }
+
+    /// A vault with the given notes, titles and aliases, and a resolver over it
+    fn resolver(notes: &[(&str, &str, &[&str])]) -> (tempfile::TempDir, LinkResolver) {
+        let vault = tempfile::tempdir().unwrap();
+        for (relative_path, title, aliases) in notes {
+            let path = vault.path().join(relative_path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(
+                path,
+                format!("---\ntitle: {}\naliases: {:?}\n---\n", title, aliases),
+            )
+            .unwrap();
+        }
+        let resolver = LinkResolver::new(&vault.path().to_string_lossy());
+        (vault, resolver)
+    }
+
+    #[test]
+    fn resolves_the_shortest_unique_path() {
+        let (_vault, resolver) = resolver(&[
+            ("projects/alpha/plan.md", "Plan", &[]),
+            ("projects/beta/notes.md", "Notes", &[]),
+        ]);
+        assert_eq!(
+            resolver.resolve_path("plan", None).as_deref(),
+            Some("projects/alpha/plan.md")
+        );
+        assert_eq!(
+            resolver.resolve_path("alpha/plan.md", None).as_deref(),
+            Some("projects/alpha/plan.md")
+        );
+        assert_eq!(
+            resolver.resolve_path("PLAN", None).as_deref(),
+            Some("projects/alpha/plan.md")
+        );
+        // Suffixes only match on folder boundaries
+        assert_eq!(resolver.resolve_path("lan", None), None);
+        assert_eq!(
+            resolver
+                .resolve_path("../beta/notes", Some("projects/alpha/plan.md"))
+                .as_deref(),
+            Some("projects/beta/notes.md")
+        );
+    }
+
+    #[test]
+    fn ambiguous_links_prefer_exact_paths_and_siblings() {
+        let (_vault, resolver) = resolver(&[
+            ("a/todo.md", "Todo A", &[]),
+            ("b/todo.md", "Todo B", &[]),
+            ("todo/todo.md", "Todo", &[]),
+        ]);
+
+        let resolution = resolver.resolve("todo", Some("c/source.md"));
+        assert_eq!(resolution.status, "ambiguous");
+        assert_eq!(resolution.note, None);
+        assert_eq!(resolution.candidates.len(), 3);
+
+        assert_eq!(
+            resolver
+                .resolve_path("todo", Some("b/source.md"))
+                .as_deref(),
+            Some("b/todo.md")
+        );
+        assert_eq!(
+            resolver.resolve_path("a/todo", None).as_deref(),
+            Some("a/todo.md")
+        );
+        assert_eq!(
+            resolver.resolve_path("todo/todo", None).as_deref(),
+            Some("todo/todo.md")
+        );
+    }
+
+    #[test]
+    fn falls_back_to_titles_and_aliases() {
+        let (_vault, resolver) = resolver(&[
+            ("2024-01-01.md", "New Year", &["Resolutions"]),
+            ("x.md", "Shared", &[]),
+            ("y.md", "Other", &["shared"]),
+        ]);
+        assert_eq!(
+            resolver.resolve_path("new year", None).as_deref(),
+            Some("2024-01-01.md")
+        );
+        assert_eq!(
+            resolver.resolve_path("Resolutions", None).as_deref(),
+            Some("2024-01-01.md")
+        );
+        assert_eq!(resolver.resolve("Shared", None).status, "ambiguous");
+        assert_eq!(resolver.resolve("Nothing", None).status, "unresolved");
+    }
+
+    #[test]
+    fn resolves_subpaths_and_links_into_the_source() {
+        let (_vault, resolver) =
+            resolver(&[("guide.md", "Guide", &[]), ("other.md", "Other", &[])]);
+
+        let resolution = resolver.resolve("guide#Setup", Some("other.md"));
+        assert_eq!(resolution.status, "resolved");
+        assert_eq!(resolution.note.unwrap().relative_path, "guide.md");
+        assert_eq!(resolution.heading.as_deref(), Some("Setup"));
+
+        let resolution = resolver.resolve("#^block", Some("other.md"));
+        assert_eq!(resolution.note.unwrap().title, "Other");
+        assert_eq!(resolution.block_id.as_deref(), Some("block"));
+    }
+
+    #[test]
+    fn tells_attachments_from_notes_with_dots_in_their_names() {
+        let (vault, resolver) = resolver(&[("v1.2 release.md", "v1.2 release", &[])]);
+        fs::write(vault.path().join("data.xyz"), "").unwrap();
+        let all_attachments = attachments::get_all_attachments(&vault.path().to_string_lossy());
+        let is_attachment =
+            |target: &str| is_attachment_target(&resolver, target, Some("a.md"), &all_attachments);
+
+        assert!(!is_attachment("v1.2 release"));
+        assert!(!is_attachment("v1.2 release#Notes"));
+        assert!(!is_attachment("Dr. Smith"));
+        assert!(!is_attachment("note"));
+        assert!(is_attachment("image.png"));
+        assert!(is_attachment("data.xyz"));
+    }
+
+    #[test]
+    fn extracts_wiki_links_outside_code() {
+        let links =
+            extract_wiki_links("See [[a|alias]] and ![[b#H]]\n`[[code]]`\n```\n[[fenced]]\n```");
+        let found: Vec<(&str, Option<&str>, bool, usize)> = links
+            .iter()
+            .map(|link| {
+                (
+                    link.target.as_str(),
+                    link.alias.as_deref(),
+                    link.embed,
+                    link.line,
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![("a", Some("alias"), false, 1), ("b#H", None, true, 1)]
+        );
+        assert_eq!(links[0].raw, "[[a|alias]]");
+    }
+}
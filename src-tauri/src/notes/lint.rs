@@ -0,0 +1,380 @@
+// src/notes/lint.rs
+use super::helpers;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value as YamlValue;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_max_note_size() -> u64 {
+    1024 * 1024
+}
+
+/// Which lint rules run and how they are tuned. Every rule is on by default.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LintConfig {
+    #[serde(default = "default_true")]
+    pub duplicate_titles: bool,
+    #[serde(default = "default_true")]
+    pub invalid_frontmatter: bool,
+    #[serde(default = "default_true")]
+    pub missing_keys: bool,
+    #[serde(default = "default_true")]
+    pub empty_notes: bool,
+    #[serde(default = "default_true")]
+    pub non_utf8: bool,
+    #[serde(default = "default_true")]
+    pub large_notes: bool,
+    #[serde(default = "default_true")]
+    pub case_collisions: bool,
+    #[serde(default)]
+    pub required_keys: Vec<String>,
+    #[serde(default = "default_max_note_size")]
+    pub max_note_size: u64,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        LintConfig {
+            duplicate_titles: true,
+            invalid_frontmatter: true,
+            missing_keys: true,
+            empty_notes: true,
+            non_utf8: true,
+            large_notes: true,
+            case_collisions: true,
+            required_keys: Vec::new(),
+            max_note_size: default_max_note_size(),
+        }
+    }
+}
+
+/// A single problem found by the lint pass
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LintIssue {
+    pub rule: String,
+    pub severity: String,
+    pub relative_path: String,
+    pub message: String,
+    pub related: Vec<String>,
+}
+
+/// The result of linting a whole vault
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LintReport {
+    pub vault_directory: String,
+    pub note_count: usize,
+    pub issue_counts: BTreeMap<String, usize>,
+    pub issues: Vec<LintIssue>,
+}
+
+fn issue(rule: &str, severity: &str, relative_path: &str, message: String) -> LintIssue {
+    LintIssue {
+        rule: rule.to_string(),
+        severity: severity.to_string(),
+        relative_path: relative_path.to_string(),
+        message,
+        related: Vec::new(),
+    }
+}
+
+/// Runs the configured lint rules over every note in the vault
+pub fn lint_vault(vault_directory: &str, config: &LintConfig) -> LintReport {
+    let notes = helpers::get_all_notes(vault_directory);
+    let mut issues: Vec<LintIssue> = Vec::new();
+    let mut titles: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut lowercase_paths: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for (abs_path, rel_path) in notes.iter() {
+        let rel_path = rel_path.replace('\\', "/");
+        lowercase_paths
+            .entry(rel_path.to_lowercase())
+            .or_default()
+            .push(rel_path.clone());
+
+        let size = fs::metadata(abs_path).map(|m| m.len()).unwrap_or(0);
+        if config.large_notes && size > config.max_note_size {
+            issues.push(issue(
+                "large_note",
+                "warning",
+                &rel_path,
+                format!(
+                    "Note is {} bytes, above the {} byte limit",
+                    size, config.max_note_size
+                ),
+            ));
+        }
+
+        // read_file_content fails on these, so read the raw bytes first
+        let bytes = match fs::read(abs_path) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                issues.push(issue(
+                    "unreadable",
+                    "error",
+                    &rel_path,
+                    format!("Could not read note: {}", e),
+                ));
+                continue;
+            }
+        };
+        let content = match String::from_utf8(bytes) {
+            Ok(content) => content,
+            Err(e) => {
+                if config.non_utf8 {
+                    issues.push(issue(
+                        "non_utf8",
+                        "error",
+                        &rel_path,
+                        format!(
+                            "Note is not valid UTF-8 (first invalid byte at offset {})",
+                            e.utf8_error().valid_up_to()
+                        ),
+                    ));
+                }
+                continue;
+            }
+        };
+
+        let (frontmatter, body) = helpers::extract_frontmatter_and_content(&content);
+
+        if config.empty_notes && body.trim().is_empty() {
+            issues.push(issue(
+                "empty_note",
+                "warning",
+                &rel_path,
+                "Note has no content".to_string(),
+            ));
+        }
+
+        let parsed = match frontmatter
+            .as_deref()
+            .map(serde_yaml::from_str::<YamlValue>)
+        {
+            Some(Ok(value)) => Some(value),
+            Some(Err(e)) => {
+                if config.invalid_frontmatter {
+                    issues.push(issue(
+                        "invalid_frontmatter",
+                        "error",
+                        &rel_path,
+                        format!("Frontmatter is not valid YAML: {}", e),
+                    ));
+                }
+                None
+            }
+            None => None,
+        };
+
+        if config.missing_keys && !config.required_keys.is_empty() {
+            let missing: Vec<&String> = config
+                .required_keys
+                .iter()
+                .filter(|key| {
+                    parsed
+                        .as_ref()
+                        .and_then(|value| value.get(key.as_str()))
+                        .map(|value| value.is_null())
+                        .unwrap_or(true)
+                })
+                .collect();
+            if !missing.is_empty() {
+                issues.push(issue(
+                    "missing_keys",
+                    "warning",
+                    &rel_path,
+                    format!(
+                        "Missing frontmatter keys: {}",
+                        missing
+                            .iter()
+                            .map(|k| k.as_str())
+                            .collect::<Vec<&str>>()
+                            .join(", ")
+                    ),
+                ));
+            }
+        }
+
+        if config.duplicate_titles {
+            let title = helpers::get_title(Some(abs_path), Some(&rel_path), Some(vault_directory));
+            titles
+                .entry(title.to_lowercase())
+                .or_default()
+                .push(rel_path.clone());
+        }
+    }
+
+    if config.duplicate_titles {
+        for paths in titles.values().filter(|paths| paths.len() > 1) {
+            for path in paths {
+                let mut duplicate = issue(
+                    "duplicate_title",
+                    "warning",
+                    path,
+                    format!("{} notes share this title", paths.len()),
+                );
+                duplicate.related = paths.iter().filter(|p| *p != path).cloned().collect();
+                issues.push(duplicate);
+            }
+        }
+    }
+
+    if config.case_collisions {
+        for paths in lowercase_paths.values().filter(|paths| paths.len() > 1) {
+            for path in paths {
+                let mut collision = issue(
+                    "case_collision",
+                    "error",
+                    path,
+                    "File name differs from another only by case".to_string(),
+                );
+                collision.related = paths.iter().filter(|p| *p != path).cloned().collect();
+                issues.push(collision);
+            }
+        }
+    }
+
+    let mut issue_counts: BTreeMap<String, usize> = BTreeMap::new();
+    for found in issues.iter() {
+        *issue_counts.entry(found.rule.clone()).or_insert(0) += 1;
+    }
+
+    println!(
+        "Linted {} notes, found {} issues",
+        notes.len(),
+        issues.len()
+    );
+
+    LintReport {
+        vault_directory: vault_directory.to_string(),
+        note_count: notes.len(),
+        issue_counts,
+        issues,
+    }
+}
+
+/// Writes a lint report as pretty-printed JSON
+pub fn export_report(report: &LintReport, output_path: &str) -> Result<(), io::Error> {
+    let json = serde_json::to_string_pretty(report)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    if let Some(parent) = Path::new(output_path).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(output_path, json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules_for(report: &LintReport, relative_path: &str) -> Vec<String> {
+        let mut rules: Vec<String> = report
+            .issues
+            .iter()
+            .filter(|issue| issue.relative_path == relative_path)
+            .map(|issue| issue.rule.clone())
+            .collect();
+        rules.sort();
+        rules
+    }
+
+    fn lint_fixture() -> (tempfile::TempDir, LintReport) {
+        let vault = tempfile::tempdir().unwrap();
+        let write = |relative_path: &str, content: &[u8]| {
+            fs::write(vault.path().join(relative_path), content).unwrap();
+        };
+        write("good.md", b"---\ntitle: Good\nstatus: done\n---\nbody");
+        write("empty.md", b"---\ntitle: Empty\nstatus: done\n---\n  \n");
+        write("broken.md", b"---\ntitle: [unclosed\n---\nbody");
+        write("binary.md", b"caf\xe9");
+        write("one.md", b"---\ntitle: Same\nstatus: done\n---\nbody");
+        write("two.md", b"---\ntitle: same\nstatus: done\n---\nbody");
+        write(
+            "large.md",
+            format!("---\nstatus: done\n---\n{}", "x".repeat(200)).as_bytes(),
+        );
+
+        let config = LintConfig {
+            required_keys: vec!["status".to_string()],
+            max_note_size: 100,
+            ..LintConfig::default()
+        };
+        let report = lint_vault(vault.path().to_str().unwrap(), &config);
+        (vault, report)
+    }
+
+    #[test]
+    fn reports_each_rule() {
+        let (_vault, report) = lint_fixture();
+
+        assert_eq!(report.note_count, 7);
+        assert!(rules_for(&report, "good.md").is_empty());
+        assert_eq!(rules_for(&report, "empty.md"), vec!["empty_note"]);
+        assert_eq!(
+            rules_for(&report, "broken.md"),
+            vec!["invalid_frontmatter", "missing_keys"]
+        );
+        assert_eq!(rules_for(&report, "binary.md"), vec!["non_utf8"]);
+        assert_eq!(rules_for(&report, "large.md"), vec!["large_note"]);
+        assert_eq!(rules_for(&report, "one.md"), vec!["duplicate_title"]);
+
+        let duplicate = report
+            .issues
+            .iter()
+            .find(|issue| issue.rule == "duplicate_title" && issue.relative_path == "one.md")
+            .unwrap();
+        assert_eq!(duplicate.related, vec!["two.md".to_string()]);
+    }
+
+    // Needs a file system where `Case.md` and `case.md` can both exist
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn reports_case_collisions() {
+        let vault = tempfile::tempdir().unwrap();
+        fs::write(vault.path().join("Case.md"), "body").unwrap();
+        fs::write(vault.path().join("case.md"), "body").unwrap();
+
+        let report = lint_vault(vault.path().to_str().unwrap(), &LintConfig::default());
+        // Their titles come from the file names, so they match too
+        assert_eq!(
+            rules_for(&report, "Case.md"),
+            vec!["case_collision", "duplicate_title"]
+        );
+        assert_eq!(report.issue_counts["case_collision"], 2);
+    }
+
+    #[test]
+    fn skips_disabled_rules() {
+        let vault = tempfile::tempdir().unwrap();
+        fs::write(vault.path().join("empty.md"), "").unwrap();
+        fs::write(vault.path().join("other.md"), "").unwrap();
+        let config = LintConfig {
+            empty_notes: false,
+            duplicate_titles: false,
+            ..LintConfig::default()
+        };
+        let report = lint_vault(vault.path().to_str().unwrap(), &config);
+        assert!(report.issues.is_empty(), "{:?}", report.issues);
+    }
+
+    #[test]
+    fn exports_the_report_as_json() {
+        let (vault, report) = lint_fixture();
+        let output = vault.path().join("reports/lint.json");
+        export_report(&report, output.to_str().unwrap()).unwrap();
+
+        let written: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(output).unwrap()).unwrap();
+        assert_eq!(written["note_count"], 7);
+        assert_eq!(
+            written["issues"].as_array().unwrap().len(),
+            report.issues.len()
+        );
+    }
+}
@@ -1,2 +1,14 @@
 // src/notes/mod.rs
+pub mod ast;
+pub mod attachments;
+pub mod embeds;
+pub mod encryption;
+pub mod flashcards;
+pub mod folders;
+pub mod formats;
+pub mod health;
 pub mod helpers;
+pub mod links;
+pub mod lint;
+pub mod outline;
+pub mod scan;
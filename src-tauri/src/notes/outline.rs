@@ -0,0 +1,301 @@
+// src/notes/outline.rs
+use super::encryption;
+use super::helpers;
+use super::links::LinkResolver;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// A single heading of a note. Line numbers are 1-based and refer to the
+/// content returned by `get_content` (frontmatter stripped), which is what
+/// the editor displays.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Heading {
+    pub level: usize,
+    pub text: String,
+    pub slug: String,
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+/// The part of a note a link points to, together with its position
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolvedSection {
+    pub relative_path: String,
+    pub title: String,
+    pub heading: Option<String>,
+    pub block_id: Option<String>,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub text: String,
+}
+
+/// A link target split into its note part and its optional subpath
+#[derive(Debug, Clone, PartialEq)]
+pub enum Subpath {
+    None,
+    Heading(String),
+    Block(String),
+}
+
+/// Splits a link target like `note#Heading`, `note^abc123` or `note#^abc123`
+/// into the note part and the subpath it refers to
+pub fn split_link_target(link_target: &str) -> (String, Subpath) {
+    // Drop any alias first: [[target|alias]]
+    let target = link_target.split('|').next().unwrap_or("").trim();
+
+    if let Some(pos) = target.find('#') {
+        let note = target[..pos].trim().to_string();
+        let subpath = target[pos + 1..].trim();
+        return match subpath.strip_prefix('^') {
+            Some(block_id) => (note, Subpath::Block(block_id.trim().to_string())),
+            None => (note, Subpath::Heading(subpath.to_string())),
+        };
+    }
+
+    // Without a `#`, a caret only marks a block when a block id ends the target
+    if let Some(pos) = target.rfind('^') {
+        let block_id = &target[pos + 1..];
+        if !block_id.is_empty()
+            && block_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-')
+        {
+            return (
+                target[..pos].trim().to_string(),
+                Subpath::Block(block_id.to_string()),
+            );
+        }
+    }
+
+    (target.to_string(), Subpath::None)
+}
+
+/// Generates a GitHub-style slug for a heading, matching the anchors the
+/// editor's anchor plugin puts into the rendered HTML
+pub fn slugify(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                Some(c)
+            } else if c.is_whitespace() {
+                Some('-')
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Parses the headings of a markdown string
+pub fn parse_headings(content: &str) -> Vec<Heading> {
+    super::ast::parse(content).headings
+}
+
+/// Finds the line range of the block marked with `^block_id`. A block is the
+/// paragraph or list item the marker is attached to.
+pub fn find_block(content: &str, block_id: &str) -> Option<(usize, usize)> {
+    let marker = Regex::new(&format!(r"(?:^|\s)\^{}\s*$", regex::escape(block_id))).ok()?;
+    let lines: Vec<&str> = content.lines().collect();
+
+    for (index, line) in lines.iter().enumerate() {
+        if !marker.is_match(line) {
+            continue;
+        }
+
+        // A marker on its own line refers to the block right above it
+        let marker_only = line.trim() == format!("^{}", block_id);
+        let mut end = if marker_only && index > 0 {
+            index - 1
+        } else {
+            index
+        };
+
+        // List items are blocks on their own
+        let item = lines[end].trim_start();
+        if item.starts_with("- ") || item.starts_with("* ") || item.starts_with("+ ") {
+            return Some((end + 1, end + 1));
+        }
+
+        // Otherwise walk back to the start of the paragraph
+        let mut start = end;
+        while start > 0
+            && !lines[start - 1].trim().is_empty()
+            && !lines[start - 1].trim_start().starts_with('#')
+        {
+            start -= 1;
+        }
+        if marker_only {
+            end = index;
+        }
+        return Some((start + 1, end + 1));
+    }
+
+    None
+}
+
+/// Removes block id markers from a piece of text
+pub fn strip_block_ids(text: &str) -> String {
+    let marker = Regex::new(r"(?m)(?:^|\s)\^[A-Za-z0-9-]+\s*$").unwrap();
+    marker.replace_all(text, "").to_string()
+}
+
+/// Returns the given 1-based, inclusive line range of a string
+pub fn slice_lines(content: &str, start_line: usize, end_line: usize) -> String {
+    content
+        .lines()
+        .skip(start_line.saturating_sub(1))
+        .take((end_line + 1).saturating_sub(start_line.max(1)))
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
+
+/// Gets the outline of a note
+pub fn get_outline(
+    absolute_path: Option<&str>,
+    relative_path: Option<&str>,
+    vault_directory: Option<&str>,
+) -> Vec<Heading> {
+    let content = helpers::get_content(absolute_path, relative_path, vault_directory);
+    parse_headings(&content)
+}
+
+/// Resolves a link target with an optional heading or block id to the exact
+/// section it points to
+pub fn resolve_reference(
+    link_target: &str,
+    source_path: Option<&str>,
+    vault_directory: &str,
+) -> Result<ResolvedSection, String> {
+    let resolver = LinkResolver::new(vault_directory);
+    resolve_reference_with(&resolver, link_target, source_path, vault_directory)
+}
+
+/// Like `resolve_reference`, reusing an existing resolver
+pub fn resolve_reference_with(
+    resolver: &LinkResolver,
+    link_target: &str,
+    source_path: Option<&str>,
+    vault_directory: &str,
+) -> Result<ResolvedSection, String> {
+    let (note, subpath) = split_link_target(link_target);
+
+    let relative_path = resolver
+        .resolve_path(link_target, source_path)
+        .ok_or_else(|| format!("Note not found or ambiguous: {}", note))?;
+    let content = helpers::get_content(None, Some(&relative_path), Some(vault_directory));
+    if encryption::is_encrypted(&content) {
+        return Err(format!("Note is encrypted: {}", relative_path));
+    }
+    let title = helpers::get_title(None, Some(&relative_path), Some(vault_directory));
+    let line_count = content.lines().count().max(1);
+
+    let (heading, block_id, start_line, end_line) = match subpath {
+        Subpath::None => (None, None, 1, line_count),
+        Subpath::Heading(wanted) => {
+            let wanted_slug = slugify(&wanted);
+            let heading = parse_headings(&content)
+                .into_iter()
+                .find(|h| h.text.eq_ignore_ascii_case(&wanted) || h.slug == wanted_slug)
+                .ok_or_else(|| format!("Heading '{}' not found in {}", wanted, relative_path))?;
+            (
+                Some(heading.text),
+                None,
+                heading.start_line,
+                heading.end_line,
+            )
+        }
+        Subpath::Block(id) => {
+            let (start, end) = find_block(&content, &id)
+                .ok_or_else(|| format!("Block '^{}' not found in {}", id, relative_path))?;
+            (None, Some(id), start, end)
+        }
+    };
+
+    let mut text = slice_lines(&content, start_line, end_line);
+    if block_id.is_some() {
+        text = strip_block_ids(&text).trim_end().to_string();
+    }
+
+    Ok(ResolvedSection {
+        relative_path,
+        title,
+        heading,
+        block_id,
+        start_line,
+        end_line,
+        text,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_headings_and_blocks() {
+        assert_eq!(
+            split_link_target("note#Heading|alias"),
+            ("note".to_string(), Subpath::Heading("Heading".to_string()))
+        );
+        assert_eq!(
+            split_link_target("note#^abc123"),
+            ("note".to_string(), Subpath::Block("abc123".to_string()))
+        );
+        assert_eq!(
+            split_link_target("note^abc123"),
+            ("note".to_string(), Subpath::Block("abc123".to_string()))
+        );
+        assert_eq!(
+            split_link_target("note"),
+            ("note".to_string(), Subpath::None)
+        );
+    }
+
+    #[test]
+    fn caret_inside_heading_is_not_a_block() {
+        assert_eq!(
+            split_link_target("note#Heading with ^x"),
+            (
+                "note".to_string(),
+                Subpath::Heading("Heading with ^x".to_string())
+            )
+        );
+        assert_eq!(
+            split_link_target("x^2 notes"),
+            ("x^2 notes".to_string(), Subpath::None)
+        );
+    }
+
+    #[test]
+    fn finds_paragraph_and_list_blocks() {
+        let content = "# Title\nfirst line\nsecond line ^para\n\n- item ^item\n- other";
+        assert_eq!(find_block(content, "para"), Some((2, 3)));
+        assert_eq!(find_block(content, "item"), Some((5, 5)));
+        assert_eq!(find_block(content, "missing"), None);
+        assert_eq!(strip_block_ids("text ^para"), "text");
+    }
+
+    #[test]
+    fn slugs_match_editor_anchors() {
+        assert_eq!(slugify("Hello, World!"), "hello-world");
+        assert_eq!(slugify("  snake_case and-dash "), "snake_case-and-dash");
+    }
+
+    #[test]
+    fn resolves_sections_but_not_of_encrypted_notes() {
+        let vault = tempfile::tempdir().unwrap();
+        let path = vault.path().to_str().unwrap();
+        std::fs::write(vault.path().join("plain.md"), "# Intro\nhello\n# Next\nbye").unwrap();
+        std::fs::write(
+            vault.path().join("secret.md"),
+            "---\nencrypted: true\n---\n-----BEGIN ENCRYPTED NOTE-----\nAQ==\n-----END ENCRYPTED NOTE-----\n",
+        )
+        .unwrap();
+
+        let section = resolve_reference("plain#Intro", None, path).unwrap();
+        assert_eq!(section.text, "# Intro\nhello");
+        assert!(resolve_reference("secret", None, path).is_err());
+    }
+}
@@ -0,0 +1,424 @@
+// src/notes/scan.rs
+use super::formats;
+use crate::db::settings::{self, ScanSettings};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::{WalkBuilder, WalkState};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// Vault-level ignore file, in `.gitignore` syntax
+pub const IGNORE_FILE: &str = ".notemancyignore";
+
+/// The global scan settings. Scanning code has no database access, so the
+/// settings module keeps this up to date whenever settings are read or saved.
+static GLOBAL_SETTINGS: RwLock<Option<ScanSettings>> = RwLock::new(None);
+
+pub fn set_global_settings(settings: &ScanSettings) {
+    if let Ok(mut global) = GLOBAL_SETTINGS.write() {
+        *global = Some(settings.clone());
+    }
+}
+
+fn global_settings() -> ScanSettings {
+    GLOBAL_SETTINGS
+        .read()
+        .ok()
+        .and_then(|global| global.clone())
+        .unwrap_or_default()
+}
+
+/// Returns true if files with this extension are notes. Link handling has
+/// no vault at hand, so it goes by the global settings.
+pub fn is_note_extension(extension: &str) -> bool {
+    let Ok(global) = GLOBAL_SETTINGS.read() else {
+        return false;
+    };
+    match global.as_ref() {
+        Some(settings) => settings
+            .note_extensions
+            .iter()
+            .any(|ext| ext.eq_ignore_ascii_case(extension)),
+        None => formats::DEFAULT_NOTE_EXTENSIONS
+            .iter()
+            .any(|ext| ext.eq_ignore_ascii_case(extension)),
+    }
+}
+
+/// Lets a running scan be stopped from another thread
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Scans in progress, by vault. Starting a scan of one vault cancels those of
+/// any other, so switching vaults doesn't leave the old one being scanned.
+#[derive(Default)]
+pub struct ActiveScans(Mutex<Vec<(String, CancelToken)>>);
+
+impl ActiveScans {
+    /// Registers a new scan and returns its token
+    pub fn start(&self, vault_directory: &str) -> CancelToken {
+        let token = CancelToken::default();
+        if let Ok(mut scans) = self.0.lock() {
+            for (vault, other) in scans.iter() {
+                if vault != vault_directory {
+                    other.cancel();
+                }
+            }
+            scans.push((vault_directory.to_string(), token.clone()));
+        }
+        token
+    }
+
+    /// Forgets a scan that has ended
+    pub fn finish(&self, token: &CancelToken) {
+        if let Ok(mut scans) = self.0.lock() {
+            scans.retain(|(_, other)| !Arc::ptr_eq(&other.0, &token.0));
+        }
+    }
+
+    /// Cancels the scans of one vault, or of all vaults. Returns how many
+    /// were cancelled.
+    pub fn cancel(&self, vault_directory: Option<&str>) -> usize {
+        self.cancel_where(|vault| vault_directory.is_none_or(|v| v == vault))
+    }
+
+    /// Cancels the scans of every vault but `keep`
+    pub fn cancel_others(&self, keep: &str) -> usize {
+        self.cancel_where(|vault| vault != keep)
+    }
+
+    fn cancel_where(&self, matches: impl Fn(&str) -> bool) -> usize {
+        let Ok(scans) = self.0.lock() else {
+            return 0;
+        };
+        let mut cancelled = 0;
+        for (vault, token) in scans.iter() {
+            if matches(vault) && !token.is_cancelled() {
+                token.cancel();
+                cancelled += 1;
+            }
+        }
+        cancelled
+    }
+}
+
+/// Checks that ignore patterns from the settings parse
+pub fn check_patterns(patterns: &[String]) -> Result<(), String> {
+    let mut builder = GitignoreBuilder::new("");
+    for pattern in patterns {
+        builder
+            .add_line(None, pattern)
+            .map_err(|e| format!("Invalid ignore pattern '{}': {}", pattern, e))?;
+    }
+    Ok(())
+}
+
+/// Checks the note extensions from the settings: at least one, without dots
+pub fn check_extensions(extensions: &[String]) -> Result<(), String> {
+    if extensions.is_empty() {
+        return Err("At least one note extension is needed".to_string());
+    }
+    for extension in extensions {
+        if extension.is_empty()
+            || !extension
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err(format!("Invalid note extension '{}'", extension));
+        }
+    }
+    Ok(())
+}
+
+/// Decides which files of a vault are scanned: hidden files and folders are
+/// skipped unless the settings include them, then the patterns from the
+/// settings and `.notemancyignore` apply. Listing, backlinks, search and
+/// export all go through this, so they agree on what is in the vault.
+#[derive(Clone)]
+pub struct VaultFilter {
+    root: PathBuf,
+    include_hidden: bool,
+    patterns: Gitignore,
+    note_extensions: Vec<String>,
+}
+
+impl VaultFilter {
+    /// Loads the rules of a vault: the global settings, the vault's
+    /// `.notemancy/config` overrides and its `.notemancyignore`
+    pub fn load(vault_directory: &str) -> Self {
+        let root = PathBuf::from(vault_directory);
+        let scan = settings::vault_scan_settings(vault_directory, &global_settings());
+
+        let mut builder = GitignoreBuilder::new(&root);
+        for pattern in &scan.ignore_patterns {
+            if let Err(e) = builder.add_line(None, pattern) {
+                println!("Skipping invalid ignore pattern '{}': {}", pattern, e);
+            }
+        }
+        let ignore_file = root.join(IGNORE_FILE);
+        if ignore_file.is_file() {
+            if let Some(e) = builder.add(&ignore_file) {
+                println!("Problem reading {}: {}", ignore_file.display(), e);
+            }
+        }
+        let patterns = builder.build().unwrap_or_else(|e| {
+            println!("Failed to build ignore rules: {}", e);
+            Gitignore::empty()
+        });
+
+        VaultFilter {
+            root,
+            include_hidden: scan.include_hidden,
+            patterns,
+            note_extensions: scan.note_extensions,
+        }
+    }
+
+    /// Returns true if a path inside the vault is excluded from scanning.
+    /// Paths outside the vault are never ignored.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        if relative.as_os_str().is_empty() {
+            return false;
+        }
+
+        if !self.include_hidden
+            && relative
+                .components()
+                .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+        {
+            return true;
+        }
+
+        self.patterns
+            .matched_path_or_any_parents(relative, is_dir)
+            .is_ignore()
+    }
+
+    /// Returns true if a file has one of the vault's note extensions
+    pub fn is_note(&self, path: &Path) -> bool {
+        path.extension().is_some_and(|extension| {
+            let extension = extension.to_string_lossy();
+            self.note_extensions
+                .iter()
+                .any(|ext| ext.eq_ignore_ascii_case(&extension))
+        })
+    }
+
+    /// The vault's note extensions, without the dot
+    pub fn note_extensions(&self) -> &[String] {
+        &self.note_extensions
+    }
+
+    /// Lists every file in the vault that isn't ignored, sorted by path.
+    /// Symlinks are followed, but a link back into a folder being walked is
+    /// skipped instead of looping forever.
+    pub fn files(&self) -> Vec<PathBuf> {
+        self.files_until(&CancelToken::default())
+            .unwrap_or_default()
+    }
+
+    /// Walks the folders on a thread pool like `files`. Returns `None` if
+    /// the token was cancelled before the walk finished.
+    pub fn files_until(&self, cancel: &CancelToken) -> Option<Vec<PathBuf>> {
+        if !self.root.is_dir() {
+            return Some(Vec::new());
+        }
+
+        let filter = self.clone();
+        let walker = WalkBuilder::new(&self.root)
+            .standard_filters(false)
+            .follow_links(true)
+            .filter_entry(move |entry| {
+                let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+                !filter.is_ignored(entry.path(), is_dir)
+            })
+            .build_parallel();
+
+        let files = Mutex::new(Vec::new());
+        walker.run(|| {
+            Box::new(|entry| {
+                if cancel.is_cancelled() {
+                    return WalkState::Quit;
+                }
+                match entry {
+                    Ok(entry) if entry.file_type().is_some_and(|t| t.is_file()) => {
+                        if let Ok(mut files) = files.lock() {
+                            files.push(entry.into_path());
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => println!("Skipping during vault scan: {}", e),
+                }
+                WalkState::Continue
+            })
+        });
+
+        if cancel.is_cancelled() {
+            return None;
+        }
+        let mut files = files.into_inner().unwrap_or_default();
+        files.sort();
+        Some(files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// A vault whose scan settings are pinned in `.notemancy/config`, so
+    /// tests don't depend on the global settings
+    fn vault(scan: &str, files: &[&str]) -> tempfile::TempDir {
+        let vault = tempfile::tempdir().unwrap();
+        fs::create_dir_all(vault.path().join(".notemancy")).unwrap();
+        fs::write(
+            vault.path().join(".notemancy/config"),
+            format!(r#"{{"scan": {}}}"#, scan),
+        )
+        .unwrap();
+        for file in files {
+            let path = vault.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        vault
+    }
+
+    fn relative_files(vault: &tempfile::TempDir, filter: &VaultFilter) -> Vec<String> {
+        filter
+            .files()
+            .iter()
+            .map(|path| {
+                path.strip_prefix(vault.path())
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn skips_hidden_and_ignored_files() {
+        let vault = vault(
+            r#"{"include_hidden": false, "ignore_patterns": ["node_modules/"]}"#,
+            &[
+                "a.md",
+                "notes/b.md",
+                "notes/scratch.tmp",
+                ".hidden/c.md",
+                "node_modules/pkg/readme.md",
+                "drafts/d.md",
+            ],
+        );
+        fs::write(vault.path().join(IGNORE_FILE), "drafts/\n*.tmp\n").unwrap();
+        let filter = VaultFilter::load(vault.path().to_str().unwrap());
+
+        assert_eq!(relative_files(&vault, &filter), vec!["a.md", "notes/b.md"]);
+        assert!(filter.is_ignored(&vault.path().join("drafts"), true));
+        assert!(filter.is_ignored(&vault.path().join("drafts/new.md"), false));
+        assert!(!filter.is_ignored(vault.path(), true));
+        assert!(!filter.is_ignored(Path::new("/elsewhere/.hidden"), true));
+    }
+
+    #[test]
+    fn includes_hidden_files_when_asked() {
+        let vault = vault(
+            r#"{"include_hidden": true, "ignore_patterns": [".notemancy/"], "note_extensions": ["md", "txt"]}"#,
+            &["a.md", ".hidden/c.txt"],
+        );
+        let filter = VaultFilter::load(vault.path().to_str().unwrap());
+
+        assert_eq!(
+            relative_files(&vault, &filter),
+            vec![".hidden/c.txt", "a.md"]
+        );
+        assert!(filter.is_note(Path::new("c.TXT")));
+        assert!(!filter.is_note(Path::new("c.pdf")));
+        assert!(!filter.is_note(Path::new("README")));
+        assert_eq!(filter.note_extensions(), ["md", "txt"]);
+    }
+
+    #[test]
+    fn falls_back_to_global_settings_on_invalid_overrides() {
+        let bad_pattern = vault(
+            r#"{"include_hidden": true, "ignore_patterns": ["[z-a]"]}"#,
+            &["a.md", ".hidden/b.md"],
+        );
+        let filter = VaultFilter::load(bad_pattern.path().to_str().unwrap());
+        assert_eq!(relative_files(&bad_pattern, &filter), vec!["a.md"]);
+
+        let no_extensions = vault(r#"{"note_extensions": []}"#, &["a.md"]);
+        let filter = VaultFilter::load(no_extensions.path().to_str().unwrap());
+        assert!(!filter.note_extensions().is_empty());
+    }
+
+    #[test]
+    fn checks_patterns_and_extensions() {
+        assert!(check_patterns(&["*.tmp".to_string(), "drafts/".to_string()]).is_ok());
+        assert!(check_patterns(&["[z-a]".to_string()]).is_err());
+
+        assert!(check_extensions(&["md".to_string(), "org-mode".to_string()]).is_ok());
+        assert!(check_extensions(&[]).is_err());
+        assert!(check_extensions(&[".md".to_string()]).is_err());
+        assert!(check_extensions(&[String::new()]).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn does_not_loop_on_symlinked_folders() {
+        let vault = vault(r#"{"include_hidden": false}"#, &["notes/a.md"]);
+        std::os::unix::fs::symlink(vault.path(), vault.path().join("notes/loop")).unwrap();
+        let filter = VaultFilter::load(vault.path().to_str().unwrap());
+        assert_eq!(relative_files(&vault, &filter), vec!["notes/a.md"]);
+    }
+
+    #[test]
+    fn cancelled_walks_return_nothing() {
+        let vault = vault(r#"{"include_hidden": false}"#, &["a.md"]);
+        let filter = VaultFilter::load(vault.path().to_str().unwrap());
+        let token = CancelToken::default();
+        token.cancel();
+        assert_eq!(filter.files_until(&token), None);
+        assert_eq!(
+            filter.files_until(&CancelToken::default()).unwrap().len(),
+            1
+        );
+    }
+
+    #[test]
+    fn starting_a_scan_cancels_other_vaults() {
+        let scans = ActiveScans::default();
+        let first = scans.start("/vault/one");
+        let again = scans.start("/vault/one");
+        assert!(!first.is_cancelled());
+
+        let other = scans.start("/vault/two");
+        assert!(first.is_cancelled() && again.is_cancelled());
+        assert!(!other.is_cancelled());
+        assert_eq!(scans.cancel_others("/vault/two"), 0);
+
+        let third = scans.start("/vault/two");
+        assert_eq!(scans.cancel(Some("/vault/two")), 2);
+        assert!(third.is_cancelled());
+
+        scans.finish(&other);
+        scans.finish(&third);
+        let fresh = scans.start("/vault/three");
+        assert_eq!(scans.cancel(None), 1);
+        assert!(fresh.is_cancelled());
+    }
+}